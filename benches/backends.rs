@@ -0,0 +1,68 @@
+//! Times the interpreter, the block cache and the recompiler against each
+//! other on bundled games, as bulk simulation runs them: no pacing and
+//! nothing to show the screen to.
+//!
+//!     cargo bench --no-default-features --bench backends
+
+extern crate chip8;
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use chip8::config::Config;
+use chip8::headless;
+use chip8::vm::CPU;
+
+const GAMES : [&str; 4] = ["BRIX", "INVADERS", "TETRIS", "UFO"];
+/// Instructions each backend runs on each game.
+const INSTRUCTIONS : usize = 5_000_000;
+/// Instructions between timer ticks, so that frames go by and display
+/// waits end.
+const FRAME : usize = 1000;
+/// Times each run is repeated; the fastest is kept, to keep noise down.
+const REPEATS : usize = 5;
+
+fn main() {
+	println!("{:<10} {:>14} {:>14} {:>14} {:>8} {:>8}", "game", "interpreter", "cached", "recompiled",
+		"cached", "recomp.");
+	for game in GAMES.iter() {
+		let rom = fs::read(format!("games/{}", game)).unwrap();
+		let interpreter = _time(&rom, |cpu, _| cpu.step().map(|_| 1));
+		let cached = _time(&rom, |cpu, budget| cpu.run_cached(budget));
+		let recompiled = _time(&rom, |cpu, budget| cpu.run_recompiled(budget));
+		println!("{:<10} {:>10.1} M/s {:>10.1} M/s {:>10.1} M/s {:>7.2}x {:>7.2}x", game,
+			_rate(interpreter), _rate(cached), _rate(recompiled),
+			_secs(interpreter) / _secs(cached), _secs(interpreter) / _secs(recompiled));
+	}
+}
+
+/// The shortest time `run` takes to get through `INSTRUCTIONS` of `rom`
+/// from power on, given what is left of the frame each time.
+fn _time<F>(rom: &[u8], mut run: F) -> Duration where F: FnMut(&mut CPU, usize) -> Result<usize, chip8::error::CpuError> {
+	(0..REPEATS).map(|_| _time_once(rom, &mut run)).min().unwrap()
+}
+
+fn _time_once<F>(rom: &[u8], run: &mut F) -> Duration where F: FnMut(&mut CPU, usize) -> Result<usize, chip8::error::CpuError> {
+	let mut chip8 = headless::machine(rom, Config { clock_speed: (FRAME * 60) as u32, ..Config::default() }, 0);
+	let start = Instant::now();
+	let (mut executed, mut in_frame) = (0, 0);
+	while executed < INSTRUCTIONS {
+		if in_frame >= FRAME || chip8.cpu.waiting_for_vblank() {
+			chip8.tick();
+			in_frame = 0;
+			continue;
+		}
+		let n = run(&mut chip8.cpu, FRAME - in_frame).unwrap();
+		executed += n;
+		in_frame += n;
+	}
+	start.elapsed()
+}
+
+fn _secs(duration: Duration) -> f64 {
+	duration.as_secs_f64()
+}
+
+fn _rate(duration: Duration) -> f64 {
+	INSTRUCTIONS as f64 / _secs(duration) / 1_000_000.0
+}
@@ -0,0 +1,62 @@
+/*
+ * Runs a ROM for a second with a key held down and prints the screen.
+ *
+ *     cargo build --release
+ *     cc examples/run.c -Iinclude -Ltarget/release -lchip8 -o run
+ *     LD_LIBRARY_PATH=target/release ./run ../games/BRIX 4
+ */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "chip8.h"
+
+int main(int argc, char **argv) {
+	static uint8_t rom[4096];
+	uint8_t screen[CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT];
+	uint32_t executed = 0;
+	chip8_machine *machine;
+	size_t len;
+	FILE *f;
+	int status, x, y;
+
+	if (argc < 2) {
+		fprintf(stderr, "usage: %s ROM [KEY]\n", argv[0]);
+		return 2;
+	}
+	if (chip8_api_version() != CHIP8_API_VERSION) {
+		fprintf(stderr, "libchip8 doesn't match chip8.h\n");
+		return 1;
+	}
+	f = fopen(argv[1], "rb");
+	if (f == NULL) {
+		perror(argv[1]);
+		return 1;
+	}
+	len = fread(rom, 1, sizeof(rom), f);
+	fclose(f);
+
+	machine = chip8_create("modern", 700, 1);
+	if (machine == NULL) {
+		fprintf(stderr, "couldn't create a machine\n");
+		return 1;
+	}
+	status = chip8_load(machine, rom, len);
+	if (status == CHIP8_OK && argc > 2) {
+		status = chip8_set_key(machine, (uint8_t)strtol(argv[2], NULL, 16), 1);
+	}
+	if (status == CHIP8_OK) {
+		status = chip8_run_frames(machine, 60, &executed);
+	}
+	if (status != CHIP8_OK) {
+		fprintf(stderr, "%s after %u instructions\n", chip8_strerror(status), executed);
+	}
+	chip8_framebuffer(machine, screen, sizeof(screen));
+	for (y = 0; y < CHIP8_SCREEN_HEIGHT; y++) {
+		for (x = 0; x < CHIP8_SCREEN_WIDTH; x++) {
+			putchar(screen[y * CHIP8_SCREEN_WIDTH + x] ? '#' : '.');
+		}
+		putchar('\n');
+	}
+	chip8_destroy(machine);
+	return status == CHIP8_OK ? 0 : 1;
+}
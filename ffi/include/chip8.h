@@ -0,0 +1,78 @@
+/*
+ * C interface to the chip8 emulator core. Link against libchip8, built
+ * with `cargo build --release` in the ffi directory.
+ *
+ * Machines are opaque handles from chip8_create, freed with chip8_destroy.
+ * Calls that can fail return CHIP8_OK or one of the codes below, which
+ * chip8_strerror describes. A machine may be used from any one thread at
+ * a time.
+ */
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CHIP8_API_VERSION 1
+
+#define CHIP8_SCREEN_WIDTH 64
+#define CHIP8_SCREEN_HEIGHT 32
+
+#define CHIP8_OK 0
+#define CHIP8_INVALID_ARGUMENT 1
+#define CHIP8_ROM_TOO_LARGE 2
+#define CHIP8_BUFFER_TOO_SMALL 3
+#define CHIP8_INTERNAL_ERROR 4
+/* Faults in the program. The pc is left on the instruction that caused
+ * them. */
+#define CHIP8_STACK_OVERFLOW 16
+#define CHIP8_STACK_UNDERFLOW 17
+#define CHIP8_ADDRESS_OUT_OF_RANGE 18
+#define CHIP8_PROTECTED_WRITE 19
+#define CHIP8_MACHINE_CODE_CALL 20
+#define CHIP8_UNKNOWN_INSTRUCTION 21
+
+typedef struct chip8_machine chip8_machine;
+
+/* The CHIP8_API_VERSION the library was built with. */
+uint32_t chip8_api_version(void);
+
+/* Makes a machine with no program loaded. quirks names a profile ("vip",
+ * "schip", "xochip" or "modern") or is NULL for the default, clock_speed
+ * is instructions per second or 0 for the default, and seed seeds CXNN.
+ * Returns NULL if the profile is unknown. */
+chip8_machine *chip8_create(const char *quirks, uint32_t clock_speed, uint64_t seed);
+
+/* Resets the machine and loads len bytes of program at 0x200. */
+int chip8_load(chip8_machine *machine, const uint8_t *rom, size_t len);
+
+/* Executes one instruction. Timers only count down between frames. */
+int chip8_step(chip8_machine *machine);
+
+/* Runs frames 60 Hz frames as fast as it can, stopping at a fault.
+ * executed, if not NULL, is set to how many instructions ran. */
+int chip8_run_frames(chip8_machine *machine, uint32_t frames, uint32_t *executed);
+
+/* Copies the screen into out, a byte for each pixel, 0 or 1, row by row
+ * from the top left. len has to be at least
+ * CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT. */
+int chip8_framebuffer(const chip8_machine *machine, uint8_t *out, size_t len);
+
+/* Presses key 0x0-0xF if pressed is nonzero and releases it otherwise. */
+int chip8_set_key(chip8_machine *machine, uint8_t key, int pressed);
+
+/* Frees a machine. NULL is ignored. */
+void chip8_destroy(chip8_machine *machine);
+
+/* A description of a status code. The string is never freed. */
+const char *chip8_strerror(int code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,221 @@
+//! A C interface to the emulator core, declared in `include/chip8.h`.
+//! Machines are opaque handles owned by the caller, and every call that
+//! can fail returns one of the `CHIP8_*` codes. Panics never cross into C;
+//! they come back as `CHIP8_INTERNAL_ERROR`.
+
+extern crate chip8_core;
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+use std::slice;
+
+use chip8_core::config::Config;
+use chip8_core::display::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use chip8_core::error::CpuError;
+use chip8_core::headless;
+use chip8_core::memory::{PROGRAM_START, RAM_SIZE};
+use chip8_core::quirks::Quirks;
+use chip8_core::vm::{Chip8, Key};
+
+/// Bumped whenever the header changes in a way old callers would notice.
+pub const CHIP8_API_VERSION : u32 = 1;
+
+pub const CHIP8_OK : c_int = 0;
+pub const CHIP8_INVALID_ARGUMENT : c_int = 1;
+pub const CHIP8_ROM_TOO_LARGE : c_int = 2;
+pub const CHIP8_BUFFER_TOO_SMALL : c_int = 3;
+pub const CHIP8_INTERNAL_ERROR : c_int = 4;
+pub const CHIP8_STACK_OVERFLOW : c_int = 16;
+pub const CHIP8_STACK_UNDERFLOW : c_int = 17;
+pub const CHIP8_ADDRESS_OUT_OF_RANGE : c_int = 18;
+pub const CHIP8_PROTECTED_WRITE : c_int = 19;
+pub const CHIP8_MACHINE_CODE_CALL : c_int = 20;
+pub const CHIP8_UNKNOWN_INSTRUCTION : c_int = 21;
+
+/// The machine behind a `chip8_machine *`.
+pub struct Machine {
+	chip8: Chip8,
+	config: Config,
+	seed: u64
+}
+
+#[no_mangle]
+pub extern "C" fn chip8_api_version() -> u32 {
+	CHIP8_API_VERSION
+}
+
+/// Makes a machine with no program loaded, or returns null if `quirks`
+/// isn't a profile name. A null `quirks` and a `clock_speed` of 0 keep the
+/// defaults.
+///
+/// # Safety
+/// `quirks` has to be null or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_create(quirks: *const c_char, clock_speed: u32, seed: u64) -> *mut Machine {
+	let result = panic::catch_unwind(|| {
+		let mut config = Config::default();
+		if !quirks.is_null() {
+			let name = CStr::from_ptr(quirks).to_str().ok()?;
+			config.quirks = Quirks::by_name(name)?;
+		}
+		if clock_speed > 0 {
+			config.clock_speed = clock_speed;
+		}
+		let chip8 = headless::machine(&[], config.clone(), seed);
+		Some(Box::into_raw(Box::new(Machine { chip8, config, seed })))
+	});
+	match result {
+		Ok(Some(machine)) => machine,
+		_ => ptr::null_mut()
+	}
+}
+
+/// Resets the machine and loads a program into it.
+///
+/// # Safety
+/// `machine` has to come from `chip8_create`, and `rom` has to point to
+/// `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load(machine: *mut Machine, rom: *const u8, len: usize) -> c_int {
+	if machine.is_null() || (rom.is_null() && len > 0) {
+		return CHIP8_INVALID_ARGUMENT;
+	}
+	if len > RAM_SIZE - PROGRAM_START {
+		return CHIP8_ROM_TOO_LARGE;
+	}
+	let machine = &mut *machine;
+	let rom = if len > 0 { slice::from_raw_parts(rom, len) } else { &[] };
+	_guard(|| {
+		machine.chip8 = headless::machine(rom, machine.config.clone(), machine.seed);
+		CHIP8_OK
+	})
+}
+
+/// Executes one instruction. Timers only count down between frames.
+///
+/// # Safety
+/// `machine` has to come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(machine: *mut Machine) -> c_int {
+	if machine.is_null() {
+		return CHIP8_INVALID_ARGUMENT;
+	}
+	let machine = &mut *machine;
+	_guard(|| _status(machine.chip8.cpu.step()))
+}
+
+/// Runs `frames` 60 Hz frames as fast as it can, stopping at a fault.
+/// `executed`, if not null, is set to how many instructions ran.
+///
+/// # Safety
+/// `machine` has to come from `chip8_create`, and `executed` has to be
+/// null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frames(machine: *mut Machine, frames: u32, executed: *mut u32) -> c_int {
+	if machine.is_null() {
+		return CHIP8_INVALID_ARGUMENT;
+	}
+	let machine = &mut *machine;
+	let mut count = 0;
+	let status = _guard(|| {
+		for _ in 0..frames {
+			match machine.chip8.run_frame() {
+				Ok(n) => count += n as u32,
+				Err(e) => return _status(Err(e))
+			}
+		}
+		CHIP8_OK
+	});
+	if !executed.is_null() {
+		*executed = count;
+	}
+	status
+}
+
+/// Copies the screen into `out`, a byte for each pixel, 0 or 1, row by row
+/// from the top left. `len` has to be at least 64 * 32.
+///
+/// # Safety
+/// `machine` has to come from `chip8_create`, and `out` has to point to
+/// `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(machine: *const Machine, out: *mut u8, len: usize) -> c_int {
+	if machine.is_null() || out.is_null() {
+		return CHIP8_INVALID_ARGUMENT;
+	}
+	if len < (SCREEN_WIDTH * SCREEN_HEIGHT) as usize {
+		return CHIP8_BUFFER_TOO_SMALL;
+	}
+	let out = slice::from_raw_parts_mut(out, len);
+	for (i, pixel) in (*machine).chip8.cpu.gfx.iter().flat_map(|row| row.iter()).enumerate() {
+		out[i] = pixel & 1;
+	}
+	CHIP8_OK
+}
+
+/// Presses key 0-F if `pressed` is nonzero and releases it otherwise.
+///
+/// # Safety
+/// `machine` has to come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(machine: *mut Machine, key: u8, pressed: c_int) -> c_int {
+	match (machine.is_null(), Key::from_byte(key)) {
+		(false, Some(key)) => {
+			(*machine).chip8.cpu.set_key(key, pressed != 0);
+			CHIP8_OK
+		},
+		_ => CHIP8_INVALID_ARGUMENT
+	}
+}
+
+/// Frees a machine. Null is ignored.
+///
+/// # Safety
+/// `machine` has to come from `chip8_create` and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(machine: *mut Machine) {
+	if !machine.is_null() {
+		drop(Box::from_raw(machine));
+	}
+}
+
+/// A description of a status code, which lives as long as the program.
+#[no_mangle]
+pub extern "C" fn chip8_strerror(code: c_int) -> *const c_char {
+	let message : &'static [u8] = match code {
+		CHIP8_OK => b"ok\0",
+		CHIP8_INVALID_ARGUMENT => b"invalid argument\0",
+		CHIP8_ROM_TOO_LARGE => b"the ROM doesn't fit in memory\0",
+		CHIP8_BUFFER_TOO_SMALL => b"the buffer is too small\0",
+		CHIP8_INTERNAL_ERROR => b"internal error in the emulator\0",
+		CHIP8_STACK_OVERFLOW => b"stack overflow\0",
+		CHIP8_STACK_UNDERFLOW => b"stack underflow\0",
+		CHIP8_ADDRESS_OUT_OF_RANGE => b"address outside of memory\0",
+		CHIP8_PROTECTED_WRITE => b"write to the protected interpreter area\0",
+		CHIP8_MACHINE_CODE_CALL => b"call to a machine code routine\0",
+		CHIP8_UNKNOWN_INSTRUCTION => b"unknown instruction\0",
+		_ => b"unknown status\0"
+	};
+	message.as_ptr() as *const c_char
+}
+
+fn _status(result: Result<(), CpuError>) -> c_int {
+	match result {
+		Ok(()) => CHIP8_OK,
+		Err(CpuError::StackOverflow(_)) => CHIP8_STACK_OVERFLOW,
+		Err(CpuError::StackUnderflow) => CHIP8_STACK_UNDERFLOW,
+		Err(CpuError::AddressOutOfRange(_)) => CHIP8_ADDRESS_OUT_OF_RANGE,
+		Err(CpuError::ProtectedWrite(_)) => CHIP8_PROTECTED_WRITE,
+		Err(CpuError::MachineCodeCall(_)) => CHIP8_MACHINE_CODE_CALL,
+		Err(CpuError::UnknownInstruction(_)) => CHIP8_UNKNOWN_INSTRUCTION
+	}
+}
+
+fn _guard<F: FnOnce() -> c_int>(f: F) -> c_int {
+	panic::catch_unwind(panic::AssertUnwindSafe(f)).unwrap_or(CHIP8_INTERNAL_ERROR)
+}
+
+#[cfg(test)]
+mod tests;
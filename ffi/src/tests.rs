@@ -0,0 +1,81 @@
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::io::Read;
+use std::ptr;
+
+use super::*;
+
+#[test]
+fn test_runs_a_game() {
+	let mut rom = Vec::new();
+	fs::File::open("../games/BRIX").unwrap().read_to_end(&mut rom).unwrap();
+	let quirks = CString::new("modern").unwrap();
+	let mut screen = [0xFF; 64 * 32];
+	let mut executed = 0;
+	unsafe {
+		let machine = chip8_create(quirks.as_ptr(), 700, 1);
+		assert!(!machine.is_null());
+		assert_eq!(CHIP8_OK, chip8_load(machine, rom.as_ptr(), rom.len()));
+		assert_eq!(CHIP8_OK, chip8_framebuffer(machine, screen.as_mut_ptr(), screen.len()));
+		assert!(screen.iter().all(|&pixel| pixel == 0));
+		assert_eq!(CHIP8_OK, chip8_set_key(machine, 0x4, 1));
+		assert_eq!(CHIP8_OK, chip8_run_frames(machine, 60, &mut executed));
+		assert_eq!(700, executed);
+		assert_eq!(CHIP8_OK, chip8_framebuffer(machine, screen.as_mut_ptr(), screen.len()));
+		assert!(screen.contains(&1));
+		chip8_destroy(machine);
+	}
+}
+
+#[test]
+fn test_errors() {
+	let unknown = CString::new("pdp-11").unwrap();
+	let mut screen = [0; 64 * 32];
+	unsafe {
+		assert!(chip8_create(unknown.as_ptr(), 0, 0).is_null());
+		let machine = chip8_create(ptr::null(), 0, 0);
+		assert_eq!(CHIP8_INVALID_ARGUMENT, chip8_set_key(machine, 0x10, 1));
+		assert_eq!(CHIP8_INVALID_ARGUMENT, chip8_step(ptr::null_mut()));
+		assert_eq!(CHIP8_BUFFER_TOO_SMALL, chip8_framebuffer(machine, screen.as_mut_ptr(), 100));
+		let rom = vec![0; 0x1000];
+		assert_eq!(CHIP8_ROM_TOO_LARGE, chip8_load(machine, rom.as_ptr(), rom.len()));
+		//00EE with nothing to return to
+		assert_eq!(CHIP8_OK, chip8_load(machine, [0x00, 0xEE].as_ptr(), 2));
+		assert_eq!(CHIP8_STACK_UNDERFLOW, chip8_step(machine));
+		assert_eq!(CHIP8_STACK_UNDERFLOW, chip8_run_frames(machine, 1, ptr::null_mut()));
+		chip8_destroy(machine);
+		chip8_destroy(ptr::null_mut());
+		assert_eq!(b"stack underflow", CStr::from_ptr(chip8_strerror(CHIP8_STACK_UNDERFLOW)).to_bytes());
+	}
+}
+
+#[test]
+fn test_header_matches_library() {
+	let mut header = String::new();
+	fs::File::open("include/chip8.h").unwrap().read_to_string(&mut header).unwrap();
+	let defines : Vec<(&str, i64)> = header.lines()
+		.filter_map(|line| {
+			let words : Vec<&str> = line.split_whitespace().collect();
+			match words[..] {
+				["#define", name, value] => value.parse().ok().map(|value| (name, value)),
+				_ => None
+			}
+		}).collect();
+	let expected = [
+		("CHIP8_API_VERSION", CHIP8_API_VERSION as i64),
+		("CHIP8_SCREEN_WIDTH", 64),
+		("CHIP8_SCREEN_HEIGHT", 32),
+		("CHIP8_OK", CHIP8_OK as i64),
+		("CHIP8_INVALID_ARGUMENT", CHIP8_INVALID_ARGUMENT as i64),
+		("CHIP8_ROM_TOO_LARGE", CHIP8_ROM_TOO_LARGE as i64),
+		("CHIP8_BUFFER_TOO_SMALL", CHIP8_BUFFER_TOO_SMALL as i64),
+		("CHIP8_INTERNAL_ERROR", CHIP8_INTERNAL_ERROR as i64),
+		("CHIP8_STACK_OVERFLOW", CHIP8_STACK_OVERFLOW as i64),
+		("CHIP8_STACK_UNDERFLOW", CHIP8_STACK_UNDERFLOW as i64),
+		("CHIP8_ADDRESS_OUT_OF_RANGE", CHIP8_ADDRESS_OUT_OF_RANGE as i64),
+		("CHIP8_PROTECTED_WRITE", CHIP8_PROTECTED_WRITE as i64),
+		("CHIP8_MACHINE_CODE_CALL", CHIP8_MACHINE_CODE_CALL as i64),
+		("CHIP8_UNKNOWN_INSTRUCTION", CHIP8_UNKNOWN_INSTRUCTION as i64)
+	];
+	assert_eq!(&expected[..], &defines[..]);
+}
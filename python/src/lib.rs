@@ -0,0 +1,183 @@
+//! Python bindings for the emulator core, built with maturin:
+//!
+//! ```python
+//! import pychip8
+//!
+//! machine = pychip8.Chip8(open("games/BRIX", "rb").read(), seed=1, db="roms.toml")
+//! machine.set_key(0x4, True)
+//! machine.run_frames(60)
+//! screen = machine.framebuffer()    # 64 * 32 bytes, 0 or 1, row by row
+//! score = machine.read_ram(0x314, 3)
+//! state = machine.save()
+//! ```
+//!
+//! Faults in the program raise `pychip8.CpuError`, leaving the pc on the
+//! instruction that caused them. The tests in `tests/` run against the
+//! built module: `maturin develop && python -m unittest discover tests`.
+
+extern crate chip8;
+extern crate pyo3;
+
+use chip8::config::Config;
+use chip8::error;
+use chip8::gym::Observation;
+use chip8::headless;
+use chip8::memory::{PROGRAM_START, RAM_SIZE};
+use chip8::quirks::Quirks;
+use chip8::rom_db::RomDatabase;
+use chip8::vm::{Chip8, Key, SavedState};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+create_exception!(pychip8, CpuError, PyException);
+
+/// A headless machine. Frames run as fast as they are asked for, and the
+/// same ROM, seed and keys always do the same thing.
+#[pyclass(name = "Chip8", unsendable)]
+struct Machine {
+	chip8: Chip8
+}
+
+/// A machine's state from `Chip8.save`.
+#[pyclass(name = "State")]
+#[derive(Clone)]
+struct State {
+	state: SavedState
+}
+
+#[pymethods]
+impl Machine {
+	/// Settings come from the ROM database at `db`, if given and the ROM is
+	/// in it, then from `quirks` and `clock_speed`.
+	#[new]
+	#[pyo3(signature = (rom, seed = 0, quirks = None, clock_speed = None, db = None))]
+	fn new(rom: &[u8], seed: u64, quirks: Option<&str>, clock_speed: Option<u32>,
+		db: Option<&str>) -> PyResult<Machine> {
+		if rom.len() > RAM_SIZE - PROGRAM_START {
+			return Err(PyValueError::new_err(format!("The ROM is {} bytes but only {} fit in memory",
+				rom.len(), RAM_SIZE - PROGRAM_START)));
+		}
+		let mut config = Config::default();
+		if let Some(path) = db {
+			let db = RomDatabase::load(path).map_err(PyValueError::new_err)?;
+			if let Some(info) = db.lookup(rom) {
+				info.apply(&mut config);
+			}
+		}
+		if let Some(name) = quirks {
+			config.quirks = Quirks::by_name(name)
+				.ok_or_else(|| PyValueError::new_err(format!("Unknown quirk profile '{}'", name)))?;
+		}
+		if let Some(speed) = clock_speed {
+			if speed == 0 {
+				return Err(PyValueError::new_err("clock_speed has to be a positive number"));
+			}
+			config.clock_speed = speed;
+		}
+		Ok(Machine { chip8: headless::machine(rom, config, seed) })
+	}
+
+	/// Executes one instruction. Timers only count down between frames.
+	fn step(&mut self) -> PyResult<()> {
+		self.chip8.cpu.step().map_err(|e| _fault(&self.chip8, e))
+	}
+
+	/// Runs 60 Hz frames and returns how many instructions ran.
+	#[pyo3(signature = (frames = 1))]
+	fn run_frames(&mut self, frames: u32) -> PyResult<usize> {
+		let mut executed = 0;
+		for _ in 0..frames {
+			executed += self.chip8.run_frame().map_err(|e| _fault(&self.chip8, e))?;
+		}
+		Ok(executed)
+	}
+
+	/// A byte for each pixel, 0 or 1, row by row from the top left.
+	fn framebuffer<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+		PyBytes::new(py, &self._observation().bytes())
+	}
+
+	/// A bit for each pixel, with the leftmost of each eight in the high bit.
+	fn framebuffer_bits<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+		PyBytes::new(py, &self._observation().bits())
+	}
+
+	fn read_ram<'py>(&self, py: Python<'py>, address: usize, length: usize) -> PyResult<&'py PyBytes> {
+		_check_range(address, length)?;
+		Ok(PyBytes::new(py, self.chip8.cpu.read_memory(address, address + length)))
+	}
+
+	/// Writes into RAM on behalf of the host, so write protection doesn't
+	/// apply.
+	fn write_ram(&mut self, address: usize, data: &[u8]) -> PyResult<()> {
+		_check_range(address, data.len())?;
+		self.chip8.cpu.write_memory(data, address as u16);
+		Ok(())
+	}
+
+	fn set_key(&mut self, key: u8, pressed: bool) -> PyResult<()> {
+		let key = Key::from_byte(key)
+			.ok_or_else(|| PyValueError::new_err(format!("There is no key {:X}", key)))?;
+		self.chip8.cpu.set_key(key, pressed);
+		Ok(())
+	}
+
+	/// Holds down the keys in `mask`, bit N for key N, and lets go of the
+	/// rest.
+	fn set_keys(&mut self, mask: u16) {
+		for key in 0..16 {
+			self.chip8.cpu.set_key(Key::from_byte(key).unwrap(), mask & (1 << key) != 0);
+		}
+	}
+
+	fn save(&self) -> State {
+		State { state: self.chip8.save() }
+	}
+
+	fn restore(&mut self, state: &State) {
+		self.chip8.restore(&state.state);
+	}
+
+	#[getter]
+	fn pc(&self) -> u16 {
+		self.chip8.cpu.pc
+	}
+
+	#[getter]
+	fn index(&self) -> u16 {
+		self.chip8.cpu.index
+	}
+
+	#[getter]
+	fn registers(&self) -> [u8; 16] {
+		self.chip8.cpu.registers
+	}
+}
+
+impl Machine {
+	fn _observation(&self) -> Observation {
+		Observation { screen: self.chip8.cpu.gfx }
+	}
+}
+
+#[pymodule]
+fn pychip8(py: Python, module: &PyModule) -> PyResult<()> {
+	module.add_class::<Machine>()?;
+	module.add_class::<State>()?;
+	module.add("CpuError", py.get_type::<CpuError>())?;
+	module.add("RAM_SIZE", RAM_SIZE)?;
+	Ok(())
+}
+
+fn _fault(chip8: &Chip8, e: error::CpuError) -> PyErr {
+	CpuError::new_err(format!("{:X}: {}", chip8.cpu.pc, e))
+}
+
+fn _check_range(address: usize, length: usize) -> PyResult<()> {
+	if address > RAM_SIZE || length > RAM_SIZE - address {
+		return Err(PyIndexError::new_err(format!("{:X}+{} is outside of memory", address, length)));
+	}
+	Ok(())
+}
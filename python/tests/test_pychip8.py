@@ -0,0 +1,71 @@
+"""Smoke tests for the bindings, run against a built module with
+
+    maturin develop && python -m unittest discover tests
+"""
+
+import os
+import unittest
+
+import pychip8
+
+ROOT = os.path.join(os.path.dirname(__file__), "..", "..")
+
+
+def read_game(name):
+    with open(os.path.join(ROOT, "games", name), "rb") as f:
+        return f.read()
+
+
+class MachineTest(unittest.TestCase):
+    def test_runs_a_game(self):
+        machine = pychip8.Chip8(read_game("BRIX"), seed=1, db=os.path.join(ROOT, "roms.toml"))
+        self.assertEqual(0x200, machine.pc)
+        self.assertGreater(machine.run_frames(60), 0)
+        screen = machine.framebuffer()
+        self.assertEqual(64 * 32, len(screen))
+        self.assertTrue(set(screen) <= {0, 1})
+        self.assertIn(1, screen)
+        self.assertEqual(64 * 32 // 8, len(machine.framebuffer_bits()))
+
+    def test_save_and_restore_replay_the_same_frames(self):
+        machine = pychip8.Chip8(read_game("TETRIS"), seed=3)
+        machine.run_frames(30)
+        state = machine.save()
+        machine.set_keys(1 << 7)
+        machine.run_frames(30)
+        first = machine.framebuffer()
+        machine.restore(state)
+        machine.set_keys(1 << 7)
+        machine.run_frames(30)
+        self.assertEqual(first, machine.framebuffer())
+
+    def test_ram_access(self):
+        machine = pychip8.Chip8(b"\x12\x00")
+        machine.write_ram(0x300, b"\x01\x02\x03")
+        self.assertEqual(b"\x01\x02\x03", machine.read_ram(0x300, 3))
+        self.assertEqual(b"", machine.read_ram(pychip8.RAM_SIZE, 0))
+        with self.assertRaises(IndexError):
+            machine.read_ram(pychip8.RAM_SIZE - 1, 2)
+        with self.assertRaises(IndexError):
+            machine.write_ram(pychip8.RAM_SIZE + 1, b"")
+
+    def test_bad_arguments(self):
+        with self.assertRaises(ValueError):
+            pychip8.Chip8(b"\x00" * pychip8.RAM_SIZE)
+        with self.assertRaises(ValueError):
+            pychip8.Chip8(b"", quirks="chip-9")
+        with self.assertRaises(ValueError):
+            pychip8.Chip8(b"", clock_speed=0)
+        with self.assertRaises(ValueError):
+            pychip8.Chip8(b"").set_key(0x10, True)
+
+    def test_faults_raise_cpu_error(self):
+        # 00EE with nothing on the stack
+        machine = pychip8.Chip8(b"\x00\xEE")
+        with self.assertRaises(pychip8.CpuError):
+            machine.step()
+        self.assertEqual(0x200, machine.pc)
+
+
+if __name__ == "__main__":
+    unittest.main()
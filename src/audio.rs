@@ -0,0 +1,301 @@
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+pub const DEFAULT_SAMPLE_RATE : u32 = 44100;
+pub const TIMER_HZ : u32 = 60;
+/// The XO-CHIP pitch register starts at 64, which plays 4000 bits a second.
+pub const DEFAULT_PITCH : u8 = 64;
+pub const PATTERN_BYTES : usize = 16;
+const PATTERN_BITS : usize = PATTERN_BYTES * 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+	Square,
+	Sine,
+	Triangle,
+	Sawtooth
+}
+
+impl Waveform {
+	pub fn by_name(name: &str) -> Option<Waveform> {
+		match name {
+			"square" => Some(Waveform::Square),
+			"sine" => Some(Waveform::Sine),
+			"triangle" => Some(Waveform::Triangle),
+			"sawtooth" => Some(Waveform::Sawtooth),
+			_ => None
+		}
+	}
+
+	/// The value of the wave at `phase`, which runs from 0 to 1.
+	fn sample(&self, phase: f32) -> f32 {
+		match *self {
+			Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+			Waveform::Sine => (2.0 * PI * phase).sin(),
+			Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+			Waveform::Sawtooth => 2.0 * phase - 1.0
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct BuzzerSettings {
+	pub frequency: f32,
+	/// From 0 (silent) to 1 (full scale).
+	pub volume: f32,
+	pub waveform: Waveform,
+	pub sample_rate: u32
+}
+
+impl Default for BuzzerSettings {
+	fn default() -> BuzzerSettings {
+		BuzzerSettings {
+			frequency: 440.0,
+			volume: 0.25,
+			waveform: Waveform::Square,
+			sample_rate: DEFAULT_SAMPLE_RATE
+		}
+	}
+}
+
+/// The tone generator that sounds while the sound timer is non-zero.
+pub struct Buzzer {
+	settings: BuzzerSettings,
+	phase: f32,
+	sounding: bool
+}
+
+impl Buzzer {
+	pub fn new(settings: BuzzerSettings) -> Buzzer {
+		Buzzer {
+			settings,
+			phase: 0.0,
+			sounding: false
+		}
+	}
+
+	/// Fills `out` with samples. Every tone starts at the beginning of its
+	/// wave, so the same session always renders to the same samples.
+	pub fn render(&mut self, on: bool, out: &mut [f32]) {
+		if on && !self.sounding {
+			self.phase = 0.0;
+		}
+		self.sounding = on;
+
+		if !on {
+			for sample in out.iter_mut() {
+				*sample = 0.0;
+			}
+			return;
+		}
+		let step = self.settings.frequency / self.settings.sample_rate as f32;
+		for sample in out.iter_mut() {
+			*sample = self.settings.volume * self.settings.waveform.sample(self.phase);
+			self.phase = (self.phase + step) % 1.0;
+		}
+	}
+}
+
+/// An XO-CHIP audio pattern, loaded by F002 and played at the rate set by
+/// FX3A instead of the buzzer tone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pattern {
+	pub bits: [u8; PATTERN_BYTES],
+	pub pitch: u8
+}
+
+impl Pattern {
+	/// Bits played per second.
+	pub fn playback_rate(&self) -> f64 {
+		4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0)
+	}
+
+	fn bit(&self, index: usize) -> f32 {
+		let byte = self.bits[(index % PATTERN_BITS) / 8];
+		if byte & (0x80 >> (index % 8)) != 0 { 1.0 } else { -1.0 }
+	}
+}
+
+/// Plays a looping `Pattern`. Each output sample is the average of the bit
+/// stream over the time the sample covers, rather than whichever bit
+/// happens to be current, which keeps the hard edges of the pattern from
+/// aliasing when the bit rate doesn't divide the sample rate.
+pub struct PatternPlayer {
+	volume: f32,
+	sample_rate: u32,
+	position: f64,
+	sounding: bool
+}
+
+impl PatternPlayer {
+	pub fn new(volume: f32, sample_rate: u32) -> PatternPlayer {
+		PatternPlayer {
+			volume,
+			sample_rate,
+			position: 0.0,
+			sounding: false
+		}
+	}
+
+	pub fn render(&mut self, on: bool, pattern: &Pattern, out: &mut [f32]) {
+		if on && !self.sounding {
+			self.position = 0.0;
+		}
+		self.sounding = on;
+
+		if !on {
+			for sample in out.iter_mut() {
+				*sample = 0.0;
+			}
+			return;
+		}
+		let step = pattern.playback_rate() / self.sample_rate as f64;
+		for sample in out.iter_mut() {
+			let end = self.position + step;
+			let mut t = self.position;
+			let mut total = 0.0;
+			while t < end {
+				let bit = t.floor();
+				let next = if bit + 1.0 < end { bit + 1.0 } else { end };
+				total += pattern.bit(bit as usize) * (next - t) as f32;
+				t = next;
+			}
+			*sample = self.volume * total / step as f32;
+			self.position = end % PATTERN_BITS as f64;
+		}
+	}
+}
+
+/// Somewhere rendered audio goes: a sound card, a file, a test.
+pub trait AudioSink: Send {
+	/// Takes mono samples between -1 and 1 at the session's sample rate.
+	fn write(&mut self, samples: &[f32]);
+}
+
+/// Produces one 60 Hz timer frame of audio at a time and hands it to every
+/// sink.
+pub struct Audio {
+	buzzer: Buzzer,
+	player: PatternPlayer,
+	sample_rate: u32,
+	sinks: Vec<Box<dyn AudioSink>>,
+	buffer: Vec<f32>,
+	frames: u64
+}
+
+impl Audio {
+	pub fn new(settings: BuzzerSettings) -> Audio {
+		Audio {
+			sample_rate: settings.sample_rate,
+			player: PatternPlayer::new(settings.volume, settings.sample_rate),
+			buzzer: Buzzer::new(settings),
+			sinks: Vec::new(),
+			buffer: Vec::new(),
+			frames: 0
+		}
+	}
+
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	pub fn add_sink(&mut self, sink: Box<dyn AudioSink>) {
+		self.sinks.push(sink);
+	}
+
+	/// Renders a frame of the buzzer, or of `pattern` once the program has
+	/// loaded one.
+	pub fn frame(&mut self, sound_on: bool, pattern: Option<&Pattern>) {
+		//frames don't always divide the sample rate evenly, so spread the
+		//remainder out rather than drifting
+		let rate = self.sample_rate as u64;
+		let start = self.frames * rate / TIMER_HZ as u64;
+		let end = (self.frames + 1) * rate / TIMER_HZ as u64;
+		self.frames += 1;
+
+		self.buffer.resize((end - start) as usize, 0.0);
+		match pattern {
+			Some(pattern) => self.player.render(sound_on, pattern, &mut self.buffer),
+			None => self.buzzer.render(sound_on, &mut self.buffer)
+		}
+		for sink in self.sinks.iter_mut() {
+			sink.write(&self.buffer);
+		}
+	}
+}
+
+/// Writes the session's audio to a 16-bit mono WAV file. The sizes in the
+/// header are filled in by `finish`, or when the sink is dropped.
+pub struct WavSink {
+	writer: BufWriter<File>,
+	sample_rate: u32,
+	data_bytes: u32
+}
+
+impl WavSink {
+	pub fn create(path: &str, sample_rate: u32) -> io::Result<WavSink> {
+		let mut sink = WavSink {
+			writer: BufWriter::new(File::create(path)?),
+			sample_rate,
+			data_bytes: 0
+		};
+		sink._write_header()?;
+		Ok(sink)
+	}
+
+	pub fn finish(&mut self) -> io::Result<()> {
+		self.writer.seek(SeekFrom::Start(0))?;
+		self._write_header()?;
+		self.writer.seek(SeekFrom::End(0))?;
+		self.writer.flush()
+	}
+
+	fn _write_header(&mut self) -> io::Result<()> {
+		let byte_rate = self.sample_rate * 2;
+		let w = &mut self.writer;
+		w.write_all(b"RIFF")?;
+		w.write_all(&_le32(36 + self.data_bytes))?;
+		w.write_all(b"WAVEfmt ")?;
+		w.write_all(&_le32(16))?;
+		w.write_all(&_le16(1))?; //PCM
+		w.write_all(&_le16(1))?; //mono
+		w.write_all(&_le32(self.sample_rate))?;
+		w.write_all(&_le32(byte_rate))?;
+		w.write_all(&_le16(2))?; //block align
+		w.write_all(&_le16(16))?; //bits per sample
+		w.write_all(b"data")?;
+		w.write_all(&_le32(self.data_bytes))
+	}
+}
+
+impl AudioSink for WavSink {
+	fn write(&mut self, samples: &[f32]) {
+		let mut bytes = Vec::with_capacity(samples.len() * 2);
+		for &sample in samples {
+			let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+			bytes.extend_from_slice(&_le16(value as u16));
+		}
+		match self.writer.write_all(&bytes) {
+			Ok(()) => self.data_bytes += bytes.len() as u32,
+			Err(e) => println!("Failed to write audio: {}", e)
+		}
+	}
+}
+
+impl Drop for WavSink {
+	fn drop(&mut self) {
+		if let Err(e) = self.finish() {
+			println!("Failed to finish WAV file: {}", e);
+		}
+	}
+}
+
+fn _le16(value: u16) -> [u8; 2] {
+	[value as u8, (value >> 8) as u8]
+}
+
+fn _le32(value: u32) -> [u8; 4] {
+	[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use instruction::Instruction;
+use recompiler::CompiledBlock;
+
+const RAM_SIZE : usize = 4096;
+pub const MAX_BLOCK_LENGTH : usize = 64;
+
+/// A straight run of decoded instructions. Only the last instruction may
+/// change control flow, so a block always executes from start to end.
+pub struct Block {
+	pub start: u16,
+	pub end: u16,
+	pub instructions: Vec<Instruction>
+}
+
+/// A cached block with how often it ran and, once hot, its compiled form.
+pub struct Entry {
+	pub block: Arc<Block>,
+	pub hits: u32,
+	pub compiled: Option<Arc<CompiledBlock>>,
+	generation: u64
+}
+
+/// Decoded basic blocks in a table indexed by their start address, so that
+/// finding the next block costs no more than fetching an instruction. Any
+/// write to memory covered by a cached block has to go through
+/// `invalidate`, since CHIP-8 programs do modify their own code.
+pub struct BlockCache {
+	blocks: Vec<Option<Entry>>,
+	covered: Vec<bool>,
+	compiled_runs: u64,
+	//bumped whenever blocks are dropped, so put_back knows a taken entry went stale
+	generation: u64
+}
+
+impl BlockCache {
+	pub fn new() -> BlockCache {
+		BlockCache {
+			blocks: (0..RAM_SIZE).map(|_| None).collect(),
+			covered: vec![false; RAM_SIZE],
+			compiled_runs: 0,
+			generation: 0
+		}
+	}
+
+	pub fn get(&self, address: u16) -> Option<Arc<Block>> {
+		self._entry(address).map(|entry| entry.block.clone())
+	}
+
+	/// Decodes the block starting at `address` from `ram` and caches it.
+	pub fn decode(&mut self, ram: &[u8], address: u16) -> Arc<Block> {
+		let mut instructions = Vec::new();
+		let mut pc = address as usize;
+		while pc + 1 < ram.len() && instructions.len() < MAX_BLOCK_LENGTH {
+			let opcode = (ram[pc] as u16) << 8 | ram[pc + 1] as u16;
+			let instruction = Instruction::decode(opcode);
+			instructions.push(instruction);
+			pc += 2;
+			if instruction.ends_block() {
+				break;
+			}
+		}
+
+		for i in address as usize..pc {
+			self.covered[i] = true;
+		}
+		let block = Arc::new(Block {
+			start: address,
+			end: pc as u16,
+			instructions
+		});
+		self.blocks[address as usize % RAM_SIZE] = Some(Entry {
+			block: block.clone(),
+			hits: 0,
+			compiled: None,
+			generation: 0
+		});
+		block
+	}
+
+	/// Takes the entry for the block at `address` out of the cache, decoding
+	/// it from `ram` first if need be, so that it can run while the CPU is
+	/// borrowed mutably. Hand it back with `put_back` once it has run.
+	pub fn take(&mut self, ram: &[u8], address: u16) -> Entry {
+		let slot = address as usize % RAM_SIZE;
+		let mut entry = match self.blocks[slot].take() {
+			Some(entry) => entry,
+			None => {
+				self.decode(ram, address);
+				self.blocks[slot].take().expect("block was just decoded")
+			}
+		};
+		entry.generation = self.generation;
+		entry
+	}
+
+	/// Returns an entry from `take` to the cache, unless memory it covers
+	/// was written while it was out.
+	pub fn put_back(&mut self, address: u16, entry: Entry) {
+		if entry.generation == self.generation {
+			self.blocks[address as usize % RAM_SIZE] = Some(entry);
+		}
+	}
+
+	/// Counts one more run of a compiled block.
+	pub fn record_compiled_run(&mut self) {
+		self.compiled_runs += 1;
+	}
+
+	/// How many times compiled blocks have run, however often the cache was
+	/// cleared.
+	pub fn compiled_runs(&self) -> u64 {
+		self.compiled_runs
+	}
+
+	/// Drops every block overlapping `start..start + len`, along with any
+	/// compiled form of it.
+	pub fn invalidate(&mut self, start: usize, len: usize) {
+		let end = if start + len > RAM_SIZE { RAM_SIZE } else { start + len };
+		if start >= end || !self.covered[start..end].iter().any(|&c| c) {
+			return;
+		}
+
+		self.generation += 1;
+		for slot in self.blocks.iter_mut() {
+			let overlaps = slot.as_ref().is_some_and(|entry| {
+				(entry.block.end as usize) > start && (entry.block.start as usize) < end
+			});
+			if overlaps {
+				*slot = None;
+			}
+		}
+		for c in self.covered.iter_mut() {
+			*c = false;
+		}
+		for entry in self.blocks.iter().flatten() {
+			for i in entry.block.start as usize..entry.block.end as usize {
+				self.covered[i] = true;
+			}
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.generation += 1;
+		for slot in self.blocks.iter_mut() {
+			*slot = None;
+		}
+		for c in self.covered.iter_mut() {
+			*c = false;
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.blocks.iter().flatten().count()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.blocks.iter().all(|slot| slot.is_none())
+	}
+
+	fn _entry(&self, address: u16) -> Option<&Entry> {
+		self.blocks.get(address as usize).and_then(|entry| entry.as_ref())
+	}
+}
+
+impl Default for BlockCache {
+	fn default() -> BlockCache {
+		BlockCache::new()
+	}
+}
@@ -0,0 +1,48 @@
+use audio::BuzzerSettings;
+use font::{Font, FONT_ADDRESS_LOW};
+use machine_code::MachineCodePolicy;
+use memory::AddressMode;
+use quirks::Quirks;
+use stack::DEFAULT_STACK_DEPTH;
+use timing::Timing;
+use vm::DEFAULT_CLOCK_SPEED;
+
+/// Settings that differ between CHIP-8 interpreters.
+#[derive(Clone, Debug)]
+pub struct Config {
+	pub stack_depth: usize,
+	pub address_mode: AddressMode,
+	/// Report writes by the program to 0x000-0x1FF instead of letting it
+	/// overwrite the font.
+	pub protect_interpreter_area: bool,
+	pub font: Font,
+	pub font_address: u16,
+	pub machine_code: MachineCodePolicy,
+	/// How the tone played while the sound timer runs sounds.
+	pub buzzer: BuzzerSettings,
+	pub quirks: Quirks,
+	/// Instructions per second when running in real time.
+	pub clock_speed: u32,
+	pub timing: Timing,
+	/// Send the screen once a frame, as it is at vertical blank, rather
+	/// than after every change to it.
+	pub present_at_vblank: bool
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			stack_depth: DEFAULT_STACK_DEPTH,
+			address_mode: AddressMode::Wrap,
+			protect_interpreter_area: false,
+			font: Font::default(),
+			font_address: FONT_ADDRESS_LOW,
+			machine_code: MachineCodePolicy::Error,
+			buzzer: BuzzerSettings::default(),
+			quirks: Quirks::default(),
+			clock_speed: DEFAULT_CLOCK_SPEED,
+			timing: Timing::default(),
+			present_at_vblank: false
+		}
+	}
+}
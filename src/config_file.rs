@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+
+/// A value in a config file. This is the part of TOML the emulator's
+/// config files need: strings, integers (decimal or 0x hex), booleans and
+/// flat arrays of those.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	String(String),
+	Integer(i64),
+	Boolean(bool),
+	Array(Vec<Value>)
+}
+
+impl Value {
+	pub fn as_str(&self) -> Option<&str> {
+		match *self {
+			Value::String(ref s) => Some(s),
+			_ => None
+		}
+	}
+
+	pub fn as_integer(&self) -> Option<i64> {
+		match *self {
+			Value::Integer(i) => Some(i),
+			_ => None
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match *self {
+			Value::Boolean(b) => Some(b),
+			_ => None
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&[Value]> {
+		match *self {
+			Value::Array(ref values) => Some(values),
+			_ => None
+		}
+	}
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+/// The tables of a config file by name. Keys before the first `[table]`
+/// header go into the table named "".
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigFile {
+	tables: BTreeMap<String, Table>
+}
+
+impl ConfigFile {
+	pub fn read(path: &str) -> Result<ConfigFile, String> {
+		let mut text = String::new();
+		let mut f = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+		f.read_to_string(&mut text).map_err(|e| format!("{}: {}", path, e))?;
+		ConfigFile::parse(&text).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn parse(text: &str) -> Result<ConfigFile, String> {
+		let mut tables = BTreeMap::new();
+		let mut current = String::new();
+		tables.insert(current.clone(), Table::new());
+
+		for (i, line) in text.lines().enumerate() {
+			let line = _strip_comment(line).trim();
+			if line.is_empty() {
+				continue;
+			}
+			if line.starts_with('[') {
+				if !line.ends_with(']') {
+					return Err(format!("Line {}: unterminated table header", i + 1));
+				}
+				current = line[1..line.len() - 1].trim().to_string();
+				tables.entry(current.clone()).or_insert_with(Table::new);
+				continue;
+			}
+
+			let equals = match line.find('=') {
+				Some(equals) => equals,
+				None => return Err(format!("Line {}: expected key = value", i + 1))
+			};
+			let key = _parse_key(line[..equals].trim())
+				.ok_or_else(|| format!("Line {}: invalid key", i + 1))?;
+			let (value, rest) = _parse_value(line[equals + 1..].trim())
+				.map_err(|e| format!("Line {}: {}", i + 1, e))?;
+			if !rest.trim().is_empty() {
+				return Err(format!("Line {}: unexpected '{}' after value", i + 1, rest.trim()));
+			}
+			tables.get_mut(&current).unwrap().insert(key, value);
+		}
+
+		Ok(ConfigFile {
+			tables
+		})
+	}
+
+	pub fn table(&self, name: &str) -> Option<&Table> {
+		self.tables.get(name)
+	}
+
+	pub fn get(&self, table: &str, key: &str) -> Option<&Value> {
+		self.tables.get(table).and_then(|t| t.get(key))
+	}
+
+	pub fn table_names(&self) -> Vec<&str> {
+		self.tables.keys().map(|name| name.as_str()).collect()
+	}
+}
+
+fn _strip_comment(line: &str) -> &str {
+	let mut in_string = false;
+	let mut escaped = false;
+	for (i, c) in line.char_indices() {
+		match c {
+			'\\' if in_string => escaped = !escaped,
+			'"' if !escaped => in_string = !in_string,
+			'#' if !in_string => return &line[..i],
+			_ => escaped = false
+		}
+		if c != '\\' {
+			escaped = false;
+		}
+	}
+	line
+}
+
+fn _parse_key(key: &str) -> Option<String> {
+	if key.starts_with('"') {
+		return match _parse_string(key) {
+			Ok((s, "")) => Some(s),
+			_ => None
+		};
+	}
+	let valid = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+	if valid { Some(key.to_string()) } else { None }
+}
+
+/// Parses one value off the front of `text`, returning it and whatever
+/// follows it.
+fn _parse_value(text: &str) -> Result<(Value, &str), String> {
+	if text.starts_with('"') {
+		let (s, rest) = _parse_string(text)?;
+		return Ok((Value::String(s), rest));
+	}
+	if let Some(inner) = text.strip_prefix('[') {
+		let mut values = Vec::new();
+		let mut rest = inner.trim_start();
+		loop {
+			if let Some(after) = rest.strip_prefix(']') {
+				return Ok((Value::Array(values), after));
+			}
+			let (value, after) = _parse_value(rest)?;
+			values.push(value);
+			rest = after.trim_start();
+			if let Some(after) = rest.strip_prefix(',') {
+				rest = after.trim_start();
+			} else if !rest.starts_with(']') {
+				return Err("expected ',' or ']' in array".to_string());
+			}
+		}
+	}
+
+	let end = text.find(|c: char| c == ',' || c == ']' || c.is_whitespace()).unwrap_or(text.len());
+	let (word, rest) = text.split_at(end);
+	let value = match word {
+		"true" => Value::Boolean(true),
+		"false" => Value::Boolean(false),
+		_ => {
+			let parsed = if let Some(hex) = word.strip_prefix("0x") {
+				i64::from_str_radix(hex, 16)
+			} else {
+				word.parse()
+			};
+			match parsed {
+				Ok(i) => Value::Integer(i),
+				Err(_) => return Err(format!("invalid value '{}'", word))
+			}
+		}
+	};
+	Ok((value, rest))
+}
+
+fn _parse_string(text: &str) -> Result<(String, &str), String> {
+	let mut out = String::new();
+	let mut chars = text.char_indices().skip(1);
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Ok((out, &text[i + 1..])),
+			'\\' => match chars.next() {
+				Some((_, 'n')) => out.push('\n'),
+				Some((_, 't')) => out.push('\t'),
+				Some((_, c)) => out.push(c),
+				None => break
+			},
+			c => out.push(c)
+		}
+	}
+	Err("unterminated string".to_string())
+}
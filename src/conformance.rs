@@ -0,0 +1,199 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use config::Config;
+use config_file::{ConfigFile, Table, Value};
+use golden;
+use headless;
+use headless::InputScript;
+use quirks::Quirks;
+use vm::GFX;
+
+/// The file in a conformance directory that lists its tests.
+pub const SUITE_FILE : &str = "suite.toml";
+/// Every quirk profile, the ones tests run under unless they say otherwise.
+pub const PROFILES : [&str; 4] = ["vip", "schip", "xochip", "modern"];
+/// How long a test ROM runs before its screen is read.
+pub const DEFAULT_TEST_FRAMES : u32 = 300;
+
+/// A test ROM and the screens it shows when it passes or fails, read from
+/// a suite file like
+///
+/// ```toml
+/// [flags]
+/// rom = "4-flags.ch8"
+/// frames = 120
+/// pass = "flags.pass"
+/// fail = ["flags.fail"]
+///
+/// [flags.vip]
+/// keys = "flags-vip.keys"
+/// pass = "flags-vip.pass"
+/// ```
+///
+/// where `profiles` can limit the quirk profiles it runs under, and a table
+/// named after a profile changes `frames`, `keys`, `pass` or `fail` for
+/// that profile. Screens are in golden frame form and keys in input script
+/// form, with paths relative to the suite file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConformanceTest {
+	pub name: String,
+	pub rom: String,
+	pub profiles: Vec<String>,
+	pub settings: Table
+}
+
+/// How one test did under one quirk profile.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+	Passed,
+	/// Either a known failing screen, by file name, or a screen unlike the
+	/// passing one, with the path of its diff.
+	Failed(String),
+	/// The test can't be checked: the ROM or its passing screen isn't
+	/// there, or the suite file is wrong.
+	Missing(String)
+}
+
+pub struct Suite {
+	pub dir: String,
+	pub tests: Vec<ConformanceTest>,
+	file: ConfigFile
+}
+
+impl Suite {
+	pub fn load(dir: &str) -> Result<Suite, String> {
+		let path = Path::new(dir).join(SUITE_FILE);
+		let file = ConfigFile::read(&path.to_string_lossy())?;
+		let mut tests = Vec::new();
+		for name in file.table_names() {
+			if name.is_empty() || name.contains('.') {
+				continue;
+			}
+			let table = file.table(name).unwrap();
+			let rom = table.get("rom").and_then(|value| value.as_str())
+				.ok_or_else(|| format!("{} needs the file name of its rom", name))?;
+			let profiles = match table.get("profiles") {
+				Some(value) => _strings(value)
+					.ok_or_else(|| format!("{}: profiles has to be a list of names", name))?,
+				None => PROFILES.iter().map(|profile| profile.to_string()).collect()
+			};
+			for profile in profiles.iter() {
+				if Quirks::by_name(profile).is_none() {
+					return Err(format!("{}: unknown quirk profile '{}'", name, profile));
+				}
+			}
+			tests.push(ConformanceTest {
+				name: name.to_string(),
+				rom: rom.to_string(),
+				profiles,
+				settings: table.clone()
+			});
+		}
+		Ok(Suite {
+			dir: dir.to_string(),
+			tests,
+			file
+		})
+	}
+
+	/// Runs every test under each of its profiles, writing diffs of screens
+	/// that aren't the passing one to `diff_dir`. Returns the status of
+	/// every test and profile, in order.
+	pub fn run(&self, diff_dir: &str) -> Vec<(String, String, Status)> {
+		let mut results = Vec::new();
+		for test in self.tests.iter() {
+			for profile in test.profiles.iter() {
+				let status = match self._run(test, profile, diff_dir) {
+					Ok(status) => status,
+					Err(e) => Status::Missing(e)
+				};
+				results.push((test.name.clone(), profile.clone(), status));
+			}
+		}
+		results
+	}
+
+	/// A setting for a test, as the profile's own table has it or else as
+	/// the test does.
+	fn _setting<'a>(&'a self, test: &'a ConformanceTest, profile: &str, key: &str) -> Option<&'a Value> {
+		self.file.get(&format!("{}.{}", test.name, profile), key)
+			.or_else(|| test.settings.get(key))
+	}
+
+	fn _run(&self, test: &ConformanceTest, profile: &str, diff_dir: &str) -> Result<Status, String> {
+		let rom = _read(&self._path(&test.rom))?;
+		let script = match self._setting(test, profile, "keys").and_then(|value| value.as_str()) {
+			Some(keys) => InputScript::load(&self._path(keys))?,
+			None => InputScript::new()
+		};
+		let frames = match self._setting(test, profile, "frames") {
+			Some(value) => value.as_integer().filter(|&frames| frames > 0)
+				.ok_or("frames has to be a positive number")? as u32,
+			None => script.frames.unwrap_or(DEFAULT_TEST_FRAMES)
+		};
+
+		let config = Config {
+			quirks: Quirks::by_name(profile).unwrap(),
+			..Config::default()
+		};
+		let mut screen = [[0; 64]; 32];
+		let result = headless::run_script(&rom, config, &script, frames, |gfx| screen = *gfx);
+		let stopped = match result {
+			Ok(()) => String::new(),
+			Err(e) => format!("stopped with {:?}\n", e)
+		};
+
+		//without a passing screen, keep this one so it can be checked by eye
+		let pass = match self._setting(test, profile, "pass").and_then(|value| value.as_str()) {
+			Some(pass) => pass,
+			None => {
+				let path = Path::new(diff_dir).join(format!("{}-{}.screen", test.name, profile));
+				_write(&path, &format!("{}{}", stopped, golden::format_frame(&screen)))?;
+				return Ok(Status::Missing(format!("no passing screen, this run's is in {}",
+					path.display())));
+			}
+		};
+		let diff = match golden::diff(&_read_screen(&self._path(pass))?, &screen) {
+			None => return Ok(Status::Passed),
+			Some((_, diff)) => diff
+		};
+		let fails = self._setting(test, profile, "fail").and_then(_strings).unwrap_or_default();
+		for fail in fails.iter() {
+			if _read_screen(&self._path(fail))? == screen {
+				return Ok(Status::Failed(fail.clone()));
+			}
+		}
+		let path = Path::new(diff_dir).join(format!("{}-{}.diff", test.name, profile));
+		_write(&path, &format!("{}{}", stopped, diff))?;
+		Ok(Status::Failed(path.to_string_lossy().into_owned()))
+	}
+
+	fn _path(&self, file: &str) -> String {
+		Path::new(&self.dir).join(file).to_string_lossy().into_owned()
+	}
+}
+
+fn _strings(value: &Value) -> Option<Vec<String>> {
+	value.as_array()?.iter().map(|value| value.as_str().map(|s| s.to_string())).collect()
+}
+
+fn _read(path: &str) -> Result<Vec<u8>, String> {
+	let mut data = Vec::new();
+	fs::File::open(path).and_then(|mut f| f.read_to_end(&mut data))
+		.map_err(|e| format!("{}: {}", path, e))?;
+	Ok(data)
+}
+
+fn _write(path: &Path, text: &str) -> Result<(), String> {
+	path.parent().map_or(Ok(()), fs::create_dir_all)
+		.and_then(|_| fs::File::create(path))
+		.and_then(|mut f| f.write_all(text.as_bytes()))
+		.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn _read_screen(path: &str) -> Result<GFX, String> {
+	let text = _read(path)?;
+	golden::parse_frame(&String::from_utf8_lossy(&text)).map_err(|e| format!("{}: {}", path, e))
+}
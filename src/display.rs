@@ -0,0 +1,225 @@
+use palette::Palette;
+use vm::GFX;
+
+pub const SCREEN_WIDTH : u32 = 64;
+pub const SCREEN_HEIGHT : u32 = 32;
+
+/// How the screen is fitted into a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Scaling {
+	/// As large as fits, keeping the 2:1 shape of the screen.
+	#[default]
+	Fit,
+	/// As large as fits at a whole number of window pixels per screen pixel,
+	/// so every pixel is the same size.
+	Integer,
+	/// Filling the whole window, whatever its shape.
+	Stretch
+}
+
+impl Scaling {
+	pub fn by_name(name: &str) -> Option<Scaling> {
+		match name {
+			"fit" => Some(Scaling::Fit),
+			"integer" => Some(Scaling::Integer),
+			"stretch" => Some(Scaling::Stretch),
+			_ => None
+		}
+	}
+
+	/// The size of the screen in a `width` by `height` window, as fractions
+	/// of the window's width and height. The screen is centred, so in GL
+	/// coordinates these are the half extents of the quad it is drawn on.
+	pub fn extent(&self, width: u32, height: u32) -> [f32; 2] {
+		if width == 0 || height == 0 {
+			return [1.0, 1.0];
+		}
+		let fit = (width as f32 / SCREEN_WIDTH as f32).min(height as f32 / SCREEN_HEIGHT as f32);
+		let scale = match *self {
+			Scaling::Stretch => return [1.0, 1.0],
+			Scaling::Fit => fit,
+			//a window smaller than the screen still gets something
+			Scaling::Integer => fit.floor().max(1.0)
+		};
+		[
+			SCREEN_WIDTH as f32 * scale / width as f32,
+			SCREEN_HEIGHT as f32 * scale / height as f32
+		]
+	}
+}
+
+/// A CRT look applied when the screen is scaled up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+	#[default]
+	None,
+	/// The lower half of every row of pixels is darkened.
+	Scanlines,
+	/// Scanlines plus a red, green and blue aperture grille.
+	Crt
+}
+
+impl Filter {
+	pub fn by_name(name: &str) -> Option<Filter> {
+		match name {
+			"none" => Some(Filter::None),
+			"scanlines" => Some(Filter::Scanlines),
+			"crt" => Some(Filter::Crt),
+			_ => None
+		}
+	}
+
+	/// How much of its colour a window pixel keeps, for the window pixel in
+	/// `column` whose position within its screen pixel's row is `row_offset`
+	/// (0 at the top, approaching 1 at the bottom). The shaders do the same.
+	pub fn shade(&self, column: u32, row_offset: f32) -> [f32; 3] {
+		let line = if *self != Filter::None && row_offset >= 0.5 { SCANLINE_LEVEL } else { 1.0 };
+		if *self != Filter::Crt {
+			return [line, line, line];
+		}
+		let mut mask = [GRILLE_LEVEL; 3];
+		mask[(column % 3) as usize] = 1.0;
+		[line * mask[0], line * mask[1], line * mask[2]]
+	}
+}
+
+pub const SCANLINE_LEVEL : f32 = 0.6;
+pub const GRILLE_LEVEL : f32 = 0.7;
+
+/// When the screen is shown, and how consecutive frames are combined to
+/// hide the flicker of sprites being erased with XOR and drawn again.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Presentation {
+	/// After every change to the screen, as the CPU sends them.
+	#[default]
+	Immediate,
+	/// Once a frame at 60 Hz, showing the screen as it is at vertical blank.
+	Vblank,
+	/// Once a frame, lighting pixels lit in this frame or the one before.
+	Or,
+	/// Once a frame, mixing this frame with the one before, the given
+	/// weight being how much of this frame is shown.
+	Blend(f32)
+}
+
+pub const DEFAULT_BLEND_WEIGHT : f32 = 0.5;
+
+impl Presentation {
+	pub fn by_name(name: &str) -> Option<Presentation> {
+		match name {
+			"immediate" => Some(Presentation::Immediate),
+			"vblank" => Some(Presentation::Vblank),
+			"or" => Some(Presentation::Or),
+			"blend" => Some(Presentation::Blend(DEFAULT_BLEND_WEIGHT)),
+			_ => None
+		}
+	}
+}
+
+/// Post-processing applied to the screen before it is shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Effects {
+	/// How much of a pixel's brightness is left after each frame once it
+	/// goes dark, from 0 (gone at once) to just under 1 (a slow fade). The
+	/// fade hides the flicker of sprites being erased and redrawn.
+	pub persistence: f32,
+	pub filter: Filter,
+	pub presentation: Presentation
+}
+
+impl Effects {
+	/// Whether frames have to be shown at 60 Hz rather than whenever the
+	/// program draws, either because frames are combined at vertical blank
+	/// or because the phosphor goes on fading between draws.
+	pub fn at_vblank(&self) -> bool {
+		self.presentation != Presentation::Immediate || self.persistence > 0.0
+	}
+}
+
+/// An RGB image, 3 bytes a pixel, rows from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+	pub width: u32,
+	pub height: u32,
+	pub data: Vec<u8>
+}
+
+/// Turns screens into colours with a palette and effects. The same
+/// renderer feeds both the window, which applies the filter in a shader,
+/// and `render_image`, the software path used for screenshots. Only what
+/// is shown changes; the screen the CPU sees is left alone.
+pub struct Renderer {
+	pub palette: Palette,
+	pub effects: Effects,
+	phosphor: Vec<[f32; 3]>,
+	previous: GFX
+}
+
+impl Renderer {
+	pub fn new(palette: Palette, effects: Effects) -> Renderer {
+		let background = Palette::rgb(palette.background());
+		Renderer {
+			palette,
+			effects,
+			phosphor: vec![background; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+			previous: [[0; 64]; 32]
+		}
+	}
+
+	/// Advances the phosphor by one frame showing `gfx` and returns the
+	/// colour of every screen pixel, row by row from the top.
+	pub fn frame(&mut self, gfx: &GFX) -> &[[f32; 3]] {
+		let background = Palette::rgb(self.palette.background());
+		let keep = self.effects.persistence.clamp(0.0, 1.0);
+		for (y, line) in gfx.iter().enumerate() {
+			for (x, &pixel) in line.iter().enumerate() {
+				let previous = self.previous[y][x];
+				let glow = &mut self.phosphor[y * SCREEN_WIDTH as usize + x];
+				match self.effects.presentation {
+					Presentation::Blend(weight) if pixel != 0 || previous != 0 => {
+						let weight = weight.clamp(0.0, 1.0);
+						let now = Palette::rgb(self.palette.color(pixel));
+						let before = Palette::rgb(self.palette.color(previous));
+						for c in 0..3 {
+							glow[c] = now[c] * weight + before[c] * (1.0 - weight);
+						}
+					},
+					Presentation::Or if pixel == 0 && previous != 0 => {
+						*glow = Palette::rgb(self.palette.color(previous));
+					},
+					_ if pixel != 0 => *glow = Palette::rgb(self.palette.color(pixel)),
+					_ => for c in 0..3 {
+						glow[c] = background[c] + (glow[c] - background[c]) * keep;
+					}
+				}
+			}
+		}
+		self.previous = *gfx;
+		&self.phosphor
+	}
+
+	/// Renders a frame in software at `scale` image pixels per screen pixel,
+	/// filter included.
+	pub fn render_image(&mut self, gfx: &GFX, scale: u32) -> Image {
+		let scale = scale.max(1);
+		let filter = self.effects.filter;
+		let colors = self.frame(gfx);
+		let (width, height) = (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+		let mut data = Vec::with_capacity((width * height * 3) as usize);
+		for y in 0..height {
+			let row_offset = (y % scale) as f32 / scale as f32;
+			for x in 0..width {
+				let color = colors[((y / scale) * SCREEN_WIDTH + x / scale) as usize];
+				let shade = if scale > 1 { filter.shade(x, row_offset) } else { [1.0; 3] };
+				for c in 0..3 {
+					data.push((color[c] * shade[c] * 255.0).round() as u8);
+				}
+			}
+		}
+		Image {
+			width,
+			height,
+			data
+		}
+	}
+}
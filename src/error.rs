@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::fmt;
+
+/// Faults in the program being emulated. The CPU stops on the instruction
+/// that caused the fault, leaving the pc pointing at it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CpuError {
+	StackOverflow(usize),
+	StackUnderflow,
+	AddressOutOfRange(usize),
+	ProtectedWrite(usize),
+	MachineCodeCall(u16),
+	UnknownInstruction(u16)
+}
+
+impl fmt::Display for CpuError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CpuError::StackOverflow(depth) =>
+				write!(f, "Stack overflow: more than {} nested subroutine calls", depth),
+			CpuError::StackUnderflow =>
+				write!(f, "Stack underflow: returned from a subroutine with an empty stack"),
+			CpuError::AddressOutOfRange(address) =>
+				write!(f, "Address {:X} is outside of memory", address),
+			CpuError::ProtectedWrite(address) =>
+				write!(f, "Write to {:X} in the protected interpreter area", address),
+			CpuError::MachineCodeCall(address) =>
+				write!(f, "Call to a machine code routine at {:X} that is not emulated", address),
+			CpuError::UnknownInstruction(opcode) =>
+				write!(f, "Unknown instruction {:04X}", opcode)
+		}
+	}
+}
+
+impl Error for CpuError {}
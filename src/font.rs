@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+
+use memory::PROGRAM_START;
+
+/// Where fonts traditionally live. Most interpreters put the font at 0x050,
+/// this one always used to put it at the very start of memory.
+pub const FONT_ADDRESS_LOW : u16 = 0x000;
+pub const FONT_ADDRESS_COMMON : u16 = 0x050;
+
+pub const SMALL_GLYPH_SIZE : usize = 5;
+pub const LARGE_GLYPH_SIZE : usize = 10;
+const SMALL_FONT_SIZE : usize = 16 * SMALL_GLYPH_SIZE;
+
+/// The hex digit sprites used by FX29 (4x5) and, if present, by FX30
+/// (8x10). The large font is placed directly after the small one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+	pub small: Vec<u8>,
+	pub large: Option<Vec<u8>>
+}
+
+impl Font {
+	pub fn cosmac_vip() -> Font {
+		Font {
+			small: COSMAC_VIP_SMALL.to_vec(),
+			large: None
+		}
+	}
+
+	pub fn chip48() -> Font {
+		Font {
+			small: CHIP48_SMALL.to_vec(),
+			large: None
+		}
+	}
+
+	/// The CHIP-48 font along with the SCHIP 1.1 large digits (0-9 only).
+	pub fn schip() -> Font {
+		Font {
+			small: CHIP48_SMALL.to_vec(),
+			large: Some(SCHIP_LARGE.to_vec())
+		}
+	}
+
+	pub fn octo() -> Font {
+		Font {
+			small: OCTO_SMALL.to_vec(),
+			large: Some(OCTO_LARGE.to_vec())
+		}
+	}
+
+	pub fn by_name(name: &str) -> Option<Font> {
+		match name {
+			"vip" | "cosmac-vip" => Some(Font::cosmac_vip()),
+			"chip48" | "chip-48" => Some(Font::chip48()),
+			"schip" => Some(Font::schip()),
+			"octo" => Some(Font::octo()),
+			_ => None
+		}
+	}
+
+	/// Reads a raw font file: 80 bytes of 4x5 glyphs, optionally followed by
+	/// 100 (0-9) or 160 (0-F) bytes of 8x10 glyphs.
+	pub fn from_file(path: &str) -> io::Result<Font> {
+		let mut f = File::open(path)?;
+		let mut data = Vec::new();
+		f.read_to_end(&mut data)?;
+		Font::from_bytes(&data)
+	}
+
+	pub fn from_bytes(data: &[u8]) -> io::Result<Font> {
+		let large = match data.len() {
+			SMALL_FONT_SIZE => None,
+			n if n == SMALL_FONT_SIZE + 10 * LARGE_GLYPH_SIZE ||
+				n == SMALL_FONT_SIZE + 16 * LARGE_GLYPH_SIZE => Some(data[SMALL_FONT_SIZE..].to_vec()),
+			n => return Err(io::Error::new(io::ErrorKind::InvalidData,
+				format!("A font has to be 80, 180 or 240 bytes long, not {}", n)))
+		};
+		Ok(Font {
+			small: data[0..SMALL_FONT_SIZE].to_vec(),
+			large
+		})
+	}
+
+	/// How many bytes of memory the font takes up.
+	pub fn size(&self) -> usize {
+		self.small.len() + self.large.as_ref().map_or(0, |large| large.len())
+	}
+
+	/// Whether the font fits at `address` without running into the program.
+	pub fn check_address(&self, address: u16) -> Result<(), String> {
+		if address as usize + self.size() > PROGRAM_START {
+			return Err(format!("A {} byte font at {:X} runs into the program at {:X}",
+				self.size(), address, PROGRAM_START));
+		}
+		Ok(())
+	}
+
+	pub fn large_glyph_count(&self) -> usize {
+		match self.large {
+			Some(ref large) => large.len() / LARGE_GLYPH_SIZE,
+			None => 0
+		}
+	}
+}
+
+impl Default for Font {
+	fn default() -> Font {
+		Font::chip48()
+	}
+}
+
+const COSMAC_VIP_SMALL : [u8; 80] = [
+	0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+	0x60, 0x20, 0x20, 0x20, 0x70, //1
+	0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+	0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+	0xA0, 0xA0, 0xF0, 0x20, 0x20, //4
+	0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+	0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+	0xF0, 0x10, 0x10, 0x10, 0x10, //7
+	0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+	0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+	0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+	0xF0, 0x50, 0x70, 0x50, 0xF0, //B
+	0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+	0xF0, 0x50, 0x50, 0x50, 0xF0, //D
+	0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+	0xF0, 0x80, 0xF0, 0x80, 0x80  //F
+];
+
+const CHIP48_SMALL : [u8; 80] = [
+	0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+	0x20, 0x60, 0x20, 0x20, 0x70, //1
+	0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+	0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+	0x90, 0x90, 0xF0, 0x10, 0x10, //4
+	0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+	0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+	0xF0, 0x10, 0x20, 0x40, 0x40, //7
+	0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+	0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+	0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+	0xE0, 0x90, 0xE0, 0x90, 0xE0, //B
+	0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+	0xE0, 0x90, 0x90, 0x90, 0xE0, //D
+	0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+	0xF0, 0x80, 0xF0, 0x80, 0x80  //F
+];
+
+const OCTO_SMALL : [u8; 80] = [
+	0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+	0x20, 0x60, 0x20, 0x20, 0x70, //1
+	0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+	0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+	0xA0, 0xA0, 0xF0, 0x20, 0x20, //4
+	0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+	0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+	0xF0, 0x10, 0x20, 0x40, 0x40, //7
+	0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+	0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+	0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+	0xE0, 0x90, 0xE0, 0x90, 0xE0, //B
+	0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+	0xE0, 0x90, 0x90, 0x90, 0xE0, //D
+	0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+	0xF0, 0x80, 0xF0, 0x80, 0x80  //F
+];
+
+const SCHIP_LARGE : [u8; 100] = [
+	0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, //0
+	0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, //1
+	0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, //2
+	0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, //3
+	0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, //4
+	0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, //5
+	0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, //6
+	0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, //7
+	0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, //8
+	0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C  //9
+];
+
+const OCTO_LARGE : [u8; 160] = [
+	0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, //0
+	0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, //1
+	0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, //2
+	0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, //3
+	0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, //4
+	0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, //5
+	0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, //6
+	0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, //7
+	0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, //8
+	0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, //9
+	0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, //A
+	0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, //B
+	0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, //C
+	0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, //D
+	0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, //E
+	0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  //F
+];
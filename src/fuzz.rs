@@ -0,0 +1,305 @@
+use rand::{Rng, XorShiftRng};
+use std::any::Any;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::panic;
+use std::path::Path;
+
+use error::CpuError;
+use headless;
+use headless::InputScript;
+use lockstep::Core;
+use memory::{PROGRAM_START, RAM_SIZE};
+use sha1;
+use vm;
+use vm::Key;
+
+/// The longest ROM that fits in memory after the interpreter area.
+pub const MAX_ROM_SIZE : usize = RAM_SIZE - PROGRAM_START;
+/// How many 60 Hz frames each input runs for.
+pub const DEFAULT_FUZZ_FRAMES : u32 = 60;
+/// The clock speed fuzzing runs at unless told otherwise, fast enough that
+/// a second of frames gets somewhere.
+pub const DEFAULT_FUZZ_CLOCK_SPEED : u32 = 1000;
+/// Where crashing inputs go, under the corpus directory.
+pub const CRASH_DIR : &str = "crashes";
+
+/// Jumps from one pc to the next.
+type Edges = HashSet<(u16, u16)>;
+
+/// An input that made the emulator panic or break one of its own limits,
+/// rather than fault the way a bad program should.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crash {
+	pub rom: Vec<u8>,
+	pub script: InputScript,
+	pub message: String
+}
+
+/// What a fuzzing session did.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+	pub runs: usize,
+	/// Runs the program ended with a `CpuError`, which is fine.
+	pub faults: usize,
+	/// Distinct jumps from one pc to the next seen so far.
+	pub edges: usize,
+	pub corpus: usize,
+	pub crashes: Vec<Crash>
+}
+
+/// A coverage guided fuzzer for the CPU. Inputs are ROMs and key scripts;
+/// each is mutated, run headlessly for `frames` frames, and kept in the
+/// corpus if it made the program take a jump no input took before.
+pub struct Fuzzer {
+	pub core: Core,
+	pub frames: u32,
+	corpus: Vec<(Vec<u8>, InputScript)>,
+	coverage: Edges,
+	rng: XorShiftRng
+}
+
+impl Fuzzer {
+	pub fn new(core: Core, seed: u64) -> Fuzzer {
+		Fuzzer {
+			core,
+			frames: DEFAULT_FUZZ_FRAMES,
+			corpus: Vec::new(),
+			coverage: HashSet::new(),
+			rng: vm::seeded_rng(seed)
+		}
+	}
+
+	/// Runs an input and adds it to the corpus whatever it covers. Returns
+	/// the crash if it crashed.
+	pub fn add(&mut self, rom: Vec<u8>, script: InputScript) -> Option<Crash> {
+		let (result, edges) = self.execute(&rom, &script);
+		self.coverage.extend(edges);
+		let crash = result.err().map(|message| Crash {
+			rom: rom.clone(),
+			script: script.clone(),
+			message
+		});
+		self.corpus.push((rom, script));
+		crash
+	}
+
+	/// Adds every ROM in `dir` to the corpus, with the key script in
+	/// `<ROM>.keys` if there is one, and returns how many there were.
+	pub fn load_corpus(&mut self, dir: &str) -> Result<usize, String> {
+		let mut crashes = Vec::new();
+		let names = _rom_names(dir)?;
+		for name in names.iter() {
+			let path = Path::new(dir).join(name);
+			let rom = _read(&path)?;
+			let keys = Path::new(dir).join(format!("{}.keys", name));
+			let script = if keys.exists() {
+				InputScript::load(&keys.to_string_lossy())?
+			} else {
+				InputScript::new()
+			};
+			if let Some(crash) = self.add(rom, script) {
+				crashes.push(format!("{}: {}", name, crash.message));
+			}
+		}
+		if !crashes.is_empty() {
+			return Err(format!("the corpus already crashes: {}", crashes.join(", ")));
+		}
+		Ok(names.len())
+	}
+
+	/// Mutates inputs from the corpus `runs` times. New inputs that cover
+	/// something are written to `save_dir`, if given, and crashing ones to
+	/// its `crashes` directory.
+	pub fn run(&mut self, runs: usize, save_dir: Option<&str>) -> Result<Report, String> {
+		if self.corpus.is_empty() {
+			self.corpus.push((Vec::new(), InputScript::new()));
+		}
+		let mut report = Report::default();
+		for _ in 0..runs {
+			let (rom, script) = self._mutate();
+			let (result, edges) = self.execute(&rom, &script);
+			report.runs += 1;
+			match result {
+				Ok(Some(_)) => report.faults += 1,
+				Ok(None) => (),
+				Err(message) => {
+					if let Some(dir) = save_dir {
+						_save(&Path::new(dir).join(CRASH_DIR), &rom, &script)?;
+					}
+					report.crashes.push(Crash { rom, script, message });
+					continue;
+				}
+			}
+			let before = self.coverage.len();
+			self.coverage.extend(edges);
+			if self.coverage.len() > before {
+				if let Some(dir) = save_dir {
+					_save(Path::new(dir), &rom, &script)?;
+				}
+				self.corpus.push((rom, script));
+			}
+		}
+		report.edges = self.coverage.len();
+		report.corpus = self.corpus.len();
+		Ok(report)
+	}
+
+	/// Runs one input, returning the fault the program stopped with, if
+	/// any, or what went wrong in the emulator, and the jumps it took.
+	pub fn execute(&self, rom: &[u8], script: &InputScript)
+		-> (Result<Option<CpuError>, String>, Edges) {
+		let mut edges = HashSet::new();
+		let result = panic::catch_unwind(panic::AssertUnwindSafe(|| self._execute(rom, script, &mut edges)));
+		let result = match result {
+			Ok(result) => result,
+			Err(payload) => Err(format!("panicked: {}", _panic_message(payload)))
+		};
+		(result, edges)
+	}
+
+	fn _execute(&self, rom: &[u8], script: &InputScript, edges: &mut Edges)
+		-> Result<Option<CpuError>, String> {
+		let mut chip8 = headless::machine(rom, self.core.config.clone(), 0);
+		let mut events = script.events.iter().peekable();
+		for frame in 0..self.frames {
+			while let Some(&&(_, key, pressed)) = events.peek().filter(|event| event.0 <= frame) {
+				chip8.cpu.set_key(key, pressed);
+				events.next();
+			}
+			let budget = chip8.instructions_per_frame();
+			let mut executed = 0;
+			while executed < budget && !chip8.cpu.waiting_for_vblank() {
+				let pc = chip8.cpu.pc;
+				match self.core.backend.run(&mut chip8.cpu, budget - executed) {
+					Ok(n) => executed += n,
+					Err(e) => return Ok(Some(e))
+				}
+				edges.insert((pc, chip8.cpu.pc));
+			}
+			chip8.tick();
+			let stack = chip8.cpu.stack();
+			if stack.len() > stack.depth() {
+				return Err(format!("the stack holds {} entries but is only {} deep",
+					stack.len(), stack.depth()));
+			}
+		}
+		Ok(None)
+	}
+
+	fn _mutate(&mut self) -> (Vec<u8>, InputScript) {
+		let (mut rom, mut script) = self.rng.choose(&self.corpus).unwrap().clone();
+		for _ in 0..self.rng.gen_range(1, 5) {
+			match self.rng.gen_range(0, 8) {
+				//flip a bit
+				0 if !rom.is_empty() => {
+					let at = self.rng.gen_range(0, rom.len());
+					rom[at] ^= 1 << self.rng.gen_range(0, 8);
+				},
+				//overwrite a whole instruction, so every opcode gets tried
+				1 if rom.len() >= 2 => {
+					let at = self.rng.gen_range(0, rom.len() / 2) * 2;
+					rom[at] = self.rng.gen();
+					rom[at + 1] = self.rng.gen();
+				},
+				2 if !rom.is_empty() => {
+					let at = self.rng.gen_range(0, rom.len());
+					rom[at] = self.rng.gen();
+				},
+				3 if !rom.is_empty() => {
+					let start = self.rng.gen_range(0, rom.len());
+					let end = self.rng.gen_range(start, rom.len()) + 1;
+					rom.drain(start..end);
+				},
+				//graft the end of another input onto this one
+				4 => {
+					let other = &self.rng.choose(&self.corpus).unwrap().0;
+					let at = self.rng.gen_range(0, rom.len() + 1);
+					let from = self.rng.gen_range(0, other.len() + 1);
+					rom.truncate(at);
+					rom.extend_from_slice(&other[from..]);
+				},
+				5 => {
+					let frame = self.rng.gen_range(0, self.frames.max(1));
+					let key = Key::from_byte(self.rng.gen_range(0, 16)).unwrap();
+					let pressed = self.rng.gen();
+					let at = script.events.iter().take_while(|event| event.0 <= frame).count();
+					script.events.insert(at, (frame, key, pressed));
+				},
+				6 if !script.events.is_empty() => {
+					let at = self.rng.gen_range(0, script.events.len());
+					script.events.remove(at);
+				},
+				_ => {
+					let at = self.rng.gen_range(0, rom.len() + 1);
+					let byte = self.rng.gen();
+					rom.insert(at, byte);
+				}
+			}
+		}
+		rom.truncate(MAX_ROM_SIZE);
+		(rom, script)
+	}
+}
+
+/// Copies the ROMs in `seed_dir` into `corpus_dir` if it has none yet, so
+/// a first session starts from real programs. Returns how many it copied.
+pub fn seed_corpus(corpus_dir: &str, seed_dir: &str) -> Result<usize, String> {
+	if Path::new(corpus_dir).exists() && !_rom_names(corpus_dir)?.is_empty() {
+		return Ok(0);
+	}
+	let names = _rom_names(seed_dir)?;
+	for name in names.iter() {
+		let rom = _read(&Path::new(seed_dir).join(name))?;
+		_write(&Path::new(corpus_dir).join(name), &rom)?;
+	}
+	Ok(names.len())
+}
+
+fn _save(dir: &Path, rom: &[u8], script: &InputScript) -> Result<(), String> {
+	let keys = script.to_string();
+	let mut input = rom.to_vec();
+	input.extend_from_slice(keys.as_bytes());
+	let name = sha1::hex_digest(&input);
+	_write(&dir.join(&name), rom)?;
+	if !keys.is_empty() {
+		_write(&dir.join(format!("{}.keys", name)), keys.as_bytes())?;
+	}
+	Ok(())
+}
+
+fn _rom_names(dir: &str) -> Result<Vec<String>, String> {
+	let mut names : Vec<String> = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir, e))?
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.path().is_file())
+		.filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+		.filter(|name| !name.starts_with('.') && !name.ends_with(".keys"))
+		.collect();
+	names.sort();
+	Ok(names)
+}
+
+fn _panic_message(payload: Box<dyn Any + Send>) -> String {
+	match payload.downcast::<String>() {
+		Ok(message) => *message,
+		Err(payload) => match payload.downcast::<&str>() {
+			Ok(message) => message.to_string(),
+			Err(_) => "no message".to_string()
+		}
+	}
+}
+
+fn _read(path: &Path) -> Result<Vec<u8>, String> {
+	let mut data = Vec::new();
+	fs::File::open(path).and_then(|mut f| f.read_to_end(&mut data))
+		.map_err(|e| format!("{}: {}", path.display(), e))?;
+	Ok(data)
+}
+
+fn _write(path: &Path, data: &[u8]) -> Result<(), String> {
+	path.parent().map_or(Ok(()), fs::create_dir_all)
+		.and_then(|_| fs::File::create(path))
+		.and_then(|mut f| f.write_all(data))
+		.map_err(|e| format!("{}: {}", path.display(), e))
+}
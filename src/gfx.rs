@@ -1,115 +1,159 @@
 use glium;
 
-use vm::GFX;
+use chip8::display::{Filter, Renderer, Scaling, SCREEN_WIDTH, SCREEN_HEIGHT, SCANLINE_LEVEL, GRILLE_LEVEL};
+use chip8::palette::Palette;
+use chip8::vm::GFX;
 use glium::backend::glutin_backend::GlutinFacade;
 use glium::DisplayBuild;
 use glium::Surface;
+use glium::texture::{RawImage2d, Texture2d, UncompressedFloatFormat, MipmapsOption};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
 
 #[derive(Copy, Clone)]
 struct Vertex {
-	position: [f32; 2]
+	position: [f32; 2],
+	tex_coords: [f32; 2]
 }
 
-implement_vertex!(Vertex, position);
-
-type Shape = Vec<Vertex>;
+implement_vertex!(Vertex, position, tex_coords);
 
+/// Draws the screen as a 64x32 texture on a single quad, so a frame costs
+/// the same however many pixels are lit. Colours and phosphor persistence
+/// come from the `Renderer`; the filter is applied by the fragment shader.
 pub struct Chip8GFX {
 	program: glium::program::Program,
-	display: GlutinFacade
+	display: GlutinFacade,
+	quad: glium::VertexBuffer<Vertex>,
+	texture: Texture2d,
+	scaling: Scaling,
+	renderer: Renderer
 }
 
 impl Chip8GFX {
-	pub fn new() -> Chip8GFX {
+	pub fn new(title: &str, renderer: Renderer, scaling: Scaling) -> Chip8GFX {
 		let display = glium::glutin::WindowBuilder::new()
 	        .with_dimensions(1024, 768)
-	        .with_title(format!("CHIP8"))
+	        .with_title(title.to_string())
 	        .build_glium()
 	        .unwrap();
 
-	    let program = glium::Program::from_source(&display, 
+	    let program = glium::Program::from_source(&display,
 			VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC, None)
-			.unwrap();   
-
-	    let mut frame = display.draw();
-	    frame.clear_color(0.0, 0.0, 0.0, 1.0);
-	    frame.finish().unwrap();
-
-		Chip8GFX {
-			program: program,
-			display: display
-		}
+			.unwrap();
+
+		let quad = glium::VertexBuffer::new(&display, &[
+			Vertex { position: [-1.0, -1.0], tex_coords: [0.0, 0.0] },
+			Vertex { position: [1.0, -1.0], tex_coords: [1.0, 0.0] },
+			Vertex { position: [-1.0, 1.0], tex_coords: [0.0, 1.0] },
+			Vertex { position: [1.0, 1.0], tex_coords: [1.0, 1.0] }
+		]).unwrap();
+		let texture = Texture2d::empty_with_format(&display, UncompressedFloatFormat::U8U8U8,
+			MipmapsOption::NoMipmap, SCREEN_WIDTH, SCREEN_HEIGHT).unwrap();
+
+		let mut gfx = Chip8GFX {
+			program,
+			display,
+			quad,
+			texture,
+			scaling,
+			renderer
+		};
+		gfx.update_graphics([[0; 64]; 32]);
+		gfx
 	}
 
-	pub fn get_display<'a>(&'a mut self) -> &'a mut GlutinFacade {
+	pub fn get_display(&mut self) -> &mut GlutinFacade {
 		&mut self.display
 	}
- 
-	pub fn update_graphics(&mut self, gfx: GFX) {
-		println!("Updating graphics...");
-		let pixel_shapes = self._generate_pixels(gfx);
-		let mut target = self.display.draw();
 
-		for shape in pixel_shapes {
-			let test_shape = shape;
-			let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
-			let vertex_buffer = glium::VertexBuffer::new(&self.display, &test_shape).unwrap();
-			target.draw(&vertex_buffer, &indices, &self.program, &glium::uniforms::EmptyUniforms,
-	            &Default::default()).unwrap();
+	pub fn update_graphics(&mut self, gfx: GFX) {
+		//GL textures start from the bottom row
+		let mut data = Vec::with_capacity((SCREEN_WIDTH * SCREEN_HEIGHT * 3) as usize);
+		for row in self.renderer.frame(&gfx).chunks(SCREEN_WIDTH as usize).rev() {
+			for color in row {
+				for &c in color.iter() {
+					data.push((c * 255.0).round() as u8);
+				}
+			}
 		}
-
-		target.finish().unwrap();
-
+		let image = RawImage2d {
+			data: data.into(),
+			width: SCREEN_WIDTH,
+			height: SCREEN_HEIGHT,
+			format: glium::texture::ClientFormat::U8U8U8
+		};
+		self.texture.write(glium::Rect {
+			left: 0,
+			bottom: 0,
+			width: SCREEN_WIDTH,
+			height: SCREEN_HEIGHT
+		}, image);
+		self.redraw();
 	}
 
-	fn _generate_pixels(&self, gfx: GFX) -> Vec<Shape> {
-		const PIXEL_DISPLAY_SIZE : f32 = 0.01;
-		let mut out = Vec::new();
-		let mut out_string = String::new();
-        for xi in 0..63 {
-            for yi in 0..31 {
-                let x = xi as f32 * PIXEL_DISPLAY_SIZE;
-                let y = yi as f32 * PIXEL_DISPLAY_SIZE;
-                let pixel = gfx[yi][xi];
-                if pixel != 0 {
-                    out.push(vec![
-					    Vertex { position: [x, y]},
-					    Vertex { position: [x + PIXEL_DISPLAY_SIZE, y]},
-					    Vertex { position: [x, y + PIXEL_DISPLAY_SIZE]}
-				    ]);
-				    out.push(vec![
-					    Vertex { position: [x + PIXEL_DISPLAY_SIZE, y]},
-					    Vertex { position: [x + PIXEL_DISPLAY_SIZE, y + PIXEL_DISPLAY_SIZE]},
-					    Vertex { position: [x, y + PIXEL_DISPLAY_SIZE]}
-				    ]);
-				    out_string.push_str("0");
-                } else {
-				    out_string.push_str(" ");
-                }
-            }
-            out_string.push_str("\n");
-        }
-		println!("--------------\n{}\n--------------", out_string);
-		out
+	/// Draws the last screen again, e.g. after the window changed size.
+	pub fn redraw(&mut self) {
+		let mut target = self.display.draw();
+		let background = Palette::rgb(self.renderer.palette.background());
+		target.clear_color(background[0], background[1], background[2], 1.0);
+
+		let (width, height) = target.get_dimensions();
+		let uniforms = uniform! {
+			extent: self.scaling.extent(width, height),
+			screen: self.texture.sampled()
+				.magnify_filter(MagnifySamplerFilter::Nearest)
+				.minify_filter(MinifySamplerFilter::Nearest),
+			filter: match self.renderer.effects.filter {
+				Filter::None => 0,
+				Filter::Scanlines => 1,
+				Filter::Crt => 2
+			},
+			scanline_level: SCANLINE_LEVEL,
+			grille_level: GRILLE_LEVEL
+		};
+		let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+		target.draw(&self.quad, indices, &self.program, &uniforms,
+			&Default::default()).unwrap();
+		target.finish().unwrap();
 	}
 }
 
-const VERTEX_SHADER_SRC: &'static str = r#"
+const VERTEX_SHADER_SRC: &str = r#"
     #version 140
 
     in vec2 position;
+    in vec2 tex_coords;
+    out vec2 v_tex_coords;
+    uniform vec2 extent;
 
     void main() {
-        gl_Position = vec4(position, 0.0, 1.0);
+        v_tex_coords = tex_coords;
+        gl_Position = vec4(position * extent, 0.0, 1.0);
     }
 "#;
 
-const FRAGMENT_SHADER_SRC: &'static str = r#"
+const FRAGMENT_SHADER_SRC: &str = r#"
     #version 140
 
+    in vec2 v_tex_coords;
     out vec4 color;
+    uniform sampler2D screen;
+    uniform int filter;
+    uniform float scanline_level;
+    uniform float grille_level;
 
     void main() {
-        color = vec4(1.0, 1.0, 1.0, 1.0);
+        vec3 shade = vec3(1.0);
+        //how far down its screen pixel this fragment is, from 0 to 1
+        float row_offset = 1.0 - fract(v_tex_coords.y * 32.0);
+        if (filter != 0 && row_offset >= 0.5) {
+            shade *= scanline_level;
+        }
+        if (filter == 2) {
+            vec3 grille = vec3(grille_level);
+            grille[int(gl_FragCoord.x) % 3] = 1.0;
+            shade *= grille;
+        }
+        color = vec4(texture(screen, v_tex_coords).rgb * shade, 1.0);
     }
 "#;
@@ -0,0 +1,192 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use config::Config;
+use error::CpuError;
+use headless;
+use headless::InputScript;
+use rom_db::RomDatabase;
+use vm::GFX;
+
+/// How long a ROM runs before its screen is compared, unless its script
+/// says otherwise.
+pub const DEFAULT_GOLDEN_FRAMES : u32 = 600;
+
+/// How a ROM's screen compared with its golden frame.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+	Matched,
+	/// The screen differs, and a diff has been written to the given path.
+	Differed(String, usize),
+	/// The program stopped with a different fault than the golden run, or
+	/// only one of them stopped; the golden and the actual fault are given,
+	/// as they are written in golden files.
+	FaultDiffered(String, String),
+	/// There was no golden frame to compare with.
+	Missing,
+	/// The golden frame was written from this run.
+	Blessed
+}
+
+/// Runs every ROM in `games_dir` headlessly with the settings `db` has for
+/// it and the key presses in `<golden_dir>/<ROM>.keys`, if there is such a
+/// file, and compares the final screen and the fault it stopped with, if
+/// any, with `<golden_dir>/<ROM>.txt`. Diffs are written to `diff_dir`.
+/// With `bless`, the golden frames are replaced instead. Returns the fault
+/// and the outcome for every ROM by file name.
+pub fn check(games_dir: &str, golden_dir: &str, diff_dir: &str, db: &RomDatabase, bless: bool)
+	-> Result<Vec<(String, Option<CpuError>, Outcome)>, String> {
+	let mut names : Vec<String> = fs::read_dir(games_dir).map_err(|e| format!("{}: {}", games_dir, e))?
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.path().is_file())
+		.filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+		.filter(|name| !name.starts_with('.'))
+		.collect();
+	names.sort();
+
+	let mut outcomes = Vec::new();
+	for name in names {
+		let script_path = Path::new(golden_dir).join(format!("{}.keys", name));
+		let script = if script_path.exists() {
+			InputScript::load(&script_path.to_string_lossy())?
+		} else {
+			InputScript::new()
+		};
+		let rom = _read(&Path::new(games_dir).join(&name)).map_err(|e| format!("{}: {}", name, e))?;
+		let (actual, fault) = run(&rom, db, &script);
+
+		let golden_path = Path::new(golden_dir).join(format!("{}.txt", name));
+		let outcome = if bless {
+			fs::create_dir_all(golden_dir).and_then(|_| _write(&golden_path, &format_golden(&actual, fault.as_ref())))
+				.map_err(|e| format!("{}: {}", golden_path.display(), e))?;
+			Outcome::Blessed
+		} else if !golden_path.exists() {
+			Outcome::Missing
+		} else {
+			let text = _read(&golden_path).map_err(|e| format!("{}: {}", golden_path.display(), e))?;
+			let (expected_fault, expected) = parse_golden(&String::from_utf8_lossy(&text))
+				.map_err(|e| format!("{}: {}", golden_path.display(), e))?;
+			let actual_fault = _fault_text(fault.as_ref());
+			match diff(&expected, &actual) {
+				_ if expected_fault != actual_fault => Outcome::FaultDiffered(expected_fault, actual_fault),
+				None => Outcome::Matched,
+				Some((pixels, text)) => {
+					let diff_path = Path::new(diff_dir).join(format!("{}.diff", name));
+					fs::create_dir_all(diff_dir).and_then(|_| _write(&diff_path, &text))
+						.map_err(|e| format!("{}: {}", diff_path.display(), e))?;
+					Outcome::Differed(diff_path.to_string_lossy().into_owned(), pixels)
+				}
+			}
+		};
+		outcomes.push((name, fault, outcome));
+	}
+	Ok(outcomes)
+}
+
+/// The screen a ROM leaves after running through `script`, and the fault
+/// it stopped with, if it did. A ROM that faults leaves the screen it
+/// stopped with.
+pub fn run(rom: &[u8], db: &RomDatabase, script: &InputScript) -> (GFX, Option<CpuError>) {
+	let mut config = Config::default();
+	if let Some(info) = db.lookup(rom) {
+		info.apply(&mut config);
+	}
+	let frames = script.frames.unwrap_or(DEFAULT_GOLDEN_FRAMES);
+	let mut screen = [[0; 64]; 32];
+	let fault = headless::run_script(rom, config, script, frames, |gfx| screen = *gfx).err();
+	(screen, fault)
+}
+
+/// A golden file: a line saying which fault the program stopped with, or
+/// `fault: none`, then the screen as `format_frame` writes it.
+pub fn format_golden(gfx: &GFX, fault: Option<&CpuError>) -> String {
+	format!("{}\n{}", _fault_text(fault), format_frame(gfx))
+}
+
+/// Splits a golden file into its fault line and its screen.
+pub fn parse_golden(text: &str) -> Result<(String, GFX), String> {
+	let (first, frame) = text.split_at(text.find('\n').map_or(text.len(), |i| i + 1));
+	let fault = first.trim_end();
+	if !fault.starts_with("fault: ") {
+		return Err("the first line has to say which fault the program stopped with".to_string());
+	}
+	Ok((fault.to_string(), parse_frame(frame)?))
+}
+
+/// A screen as text, a line a row, with `#` for lit pixels and `.` for
+/// dark ones.
+pub fn format_frame(gfx: &GFX) -> String {
+	let mut text = String::new();
+	for row in gfx.iter() {
+		text.extend(row.iter().map(|&pixel| if pixel != 0 { '#' } else { '.' }));
+		text.push('\n');
+	}
+	text
+}
+
+pub fn parse_frame(text: &str) -> Result<GFX, String> {
+	let mut gfx = [[0; 64]; 32];
+	let rows : Vec<&str> = text.lines().collect();
+	if rows.len() != gfx.len() {
+		return Err(format!("expected {} rows, found {}", gfx.len(), rows.len()));
+	}
+	for (y, row) in rows.iter().enumerate() {
+		if row.chars().count() != gfx[y].len() {
+			return Err(format!("row {} isn't {} pixels wide", y + 1, gfx[y].len()));
+		}
+		for (x, c) in row.chars().enumerate() {
+			gfx[y][x] = match c {
+				'#' => 1,
+				'.' => 0,
+				_ => return Err(format!("row {} has '{}' in it", y + 1, c))
+			};
+		}
+	}
+	Ok(gfx)
+}
+
+/// Compares two screens. If they differ, returns how many pixels do and a
+/// picture of both, where `-` is a pixel only the expected screen has lit
+/// and `+` one only the actual screen has.
+pub fn diff(expected: &GFX, actual: &GFX) -> Option<(usize, String)> {
+	let mut pixels = 0;
+	let mut picture = String::new();
+	for (want, got) in expected.iter().zip(actual.iter()) {
+		for (&want, &got) in want.iter().zip(got.iter()) {
+			picture.push(match (want != 0, got != 0) {
+				(true, true) => '#',
+				(false, false) => '.',
+				(true, false) => '-',
+				(false, true) => '+'
+			});
+			if (want != 0) != (got != 0) {
+				pixels += 1;
+			}
+		}
+		picture.push('\n');
+	}
+	if pixels == 0 {
+		return None;
+	}
+	Some((pixels, format!("{} pixels differ (- only in the golden frame, + only now)\n{}",
+		pixels, picture)))
+}
+
+fn _fault_text(fault: Option<&CpuError>) -> String {
+	match fault {
+		Some(e) => format!("fault: {:?}", e),
+		None => "fault: none".to_string()
+	}
+}
+
+fn _read(path: &Path) -> io::Result<Vec<u8>> {
+	let mut data = Vec::new();
+	fs::File::open(path)?.read_to_end(&mut data)?;
+	Ok(data)
+}
+
+fn _write(path: &Path, text: &str) -> io::Result<()> {
+	fs::File::create(path)?.write_all(text.as_bytes())
+}
@@ -0,0 +1,265 @@
+use config::Config;
+use config_file::Table;
+use display::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use error::CpuError;
+use headless;
+use memory::RAM_SIZE;
+use rom_db::RomDatabase;
+use vm::{Chip8, GFX, Key, SavedState};
+
+/// The keys held down for a step, bit N for key N.
+pub type KeyMask = u16;
+
+/// The longest number a reward hook reads, so that even all nines fits an
+/// `i64`.
+const MAX_DIGITS : usize = 18;
+const MAX_BYTES : usize = 7;
+
+/// What the agent sees after a reset or a step.
+#[derive(Clone, Copy)]
+pub struct Observation {
+	pub screen: GFX
+}
+
+impl Observation {
+	/// A byte for each pixel, 0 or 1, row by row from the top left.
+	pub fn bytes(&self) -> Vec<u8> {
+		self.screen.iter().flat_map(|row| row.iter().map(|&pixel| pixel & 1)).collect()
+	}
+
+	/// A bit for each pixel, row by row from the top left, with the
+	/// leftmost of each eight pixels in the high bit.
+	pub fn bits(&self) -> Vec<u8> {
+		let mut bits = vec![0; (SCREEN_WIDTH * SCREEN_HEIGHT / 8) as usize];
+		for (i, pixel) in self.bytes().into_iter().enumerate() {
+			bits[i / 8] |= pixel << (7 - i % 8);
+		}
+		bits
+	}
+}
+
+/// How a number a game keeps in RAM is laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+	/// A byte for each decimal digit, most significant first, as FX33
+	/// stores them.
+	Bcd(usize),
+	/// Big-endian binary.
+	Binary(usize)
+}
+
+impl Encoding {
+	/// How many bytes of RAM the number takes up.
+	pub fn size(&self) -> usize {
+		match *self {
+			Encoding::Bcd(digits) => digits,
+			Encoding::Binary(bytes) => bytes
+		}
+	}
+}
+
+/// A number in a game's RAM, like its score, whose changes are rewarded.
+/// Read from a ROM database table like
+///
+/// ```toml
+/// [HASH.rewards.score]
+/// address = 0x314
+/// digits = 3
+/// weight = 1
+/// ```
+///
+/// where `digits` reads BCD and `bytes` reads binary, one byte if neither
+/// is given. A negative weight makes it a penalty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RewardHook {
+	pub name: String,
+	pub address: u16,
+	pub encoding: Encoding,
+	pub weight: i64
+}
+
+impl RewardHook {
+	pub fn from_table(name: &str, table: &Table) -> Result<RewardHook, String> {
+		let mut address = None;
+		let mut encoding = Encoding::Binary(1);
+		let mut weight = 1;
+		for (key, value) in table.iter() {
+			match key.as_str() {
+				"address" => match value.as_integer() {
+					Some(a) if a >= 0 && (a as usize) < RAM_SIZE => address = Some(a as u16),
+					_ => return Err("address has to be somewhere in RAM".to_string())
+				},
+				"digits" => match value.as_integer() {
+					Some(n) if n > 0 && n as usize <= MAX_DIGITS => encoding = Encoding::Bcd(n as usize),
+					_ => return Err(format!("digits has to be from 1 to {}", MAX_DIGITS))
+				},
+				"bytes" => match value.as_integer() {
+					Some(n) if n > 0 && n as usize <= MAX_BYTES => encoding = Encoding::Binary(n as usize),
+					_ => return Err(format!("bytes has to be from 1 to {}", MAX_BYTES))
+				},
+				"weight" => weight = value.as_integer().ok_or("weight has to be a number")?,
+				_ => return Err(format!("Unknown reward setting '{}'", key))
+			}
+		}
+		if table.contains_key("digits") && table.contains_key("bytes") {
+			return Err("a reward can't have both digits and bytes".to_string());
+		}
+		let address = address.ok_or("a reward needs an address")?;
+		if address as usize + encoding.size() > RAM_SIZE {
+			return Err("the reward runs past the end of RAM".to_string());
+		}
+		Ok(RewardHook {
+			name: name.to_string(),
+			address,
+			encoding,
+			weight
+		})
+	}
+
+	/// The hook's number as it is in `ram`. A BCD byte above 9 isn't a
+	/// digit, and counts as 9.
+	pub fn read(&self, ram: &[u8]) -> i64 {
+		let start = self.address as usize;
+		let bytes = &ram[start..start + self.encoding.size()];
+		match self.encoding {
+			Encoding::Bcd(_) => bytes.iter().fold(0, |n, &digit| n * 10 + digit.min(9) as i64),
+			Encoding::Binary(_) => bytes.iter().fold(0, |n, &byte| n << 8 | byte as i64)
+		}
+	}
+}
+
+/// What happened in a step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepInfo {
+	/// The weighted change in every reward hook's value.
+	pub reward: i64,
+	/// Each hook's value after the step.
+	pub values: Vec<(String, i64)>,
+	/// Frames since the reset.
+	pub frames: u64,
+	/// The fault the program stopped with, which ends the episode.
+	pub fault: Option<CpuError>,
+	pub done: bool
+}
+
+/// An environment's state from `Env::clone_state`.
+#[derive(Clone, Debug)]
+pub struct EnvState {
+	machine: SavedState,
+	values: Vec<i64>,
+	frames: u64,
+	fault: Option<CpuError>
+}
+
+/// A headless, deterministic machine for agents to play games on, one
+/// episode at a time. Frames run as fast as they can, and the same seed,
+/// ROM and actions always give the same observations and rewards.
+pub struct Env {
+	config: Config,
+	db: RomDatabase,
+	chip8: Chip8,
+	hooks: Vec<RewardHook>,
+	values: Vec<i64>,
+	frames: u64,
+	fault: Option<CpuError>
+}
+
+impl Env {
+	/// `config` is the machine to use for ROMs the database doesn't know.
+	pub fn new(config: Config, db: RomDatabase) -> Env {
+		let chip8 = headless::machine(&[], config.clone(), 0);
+		Env {
+			config,
+			db,
+			chip8,
+			hooks: Vec::new(),
+			values: Vec::new(),
+			frames: 0,
+			fault: None
+		}
+	}
+
+	/// Starts an episode of `rom`, with its settings and reward hooks from
+	/// the database, and CXNN seeded with `seed`.
+	pub fn reset(&mut self, rom: &[u8], seed: u64) -> Observation {
+		let mut config = self.config.clone();
+		self.hooks = match self.db.lookup(rom) {
+			Some(info) => {
+				info.apply(&mut config);
+				info.rewards.clone()
+			},
+			None => Vec::new()
+		};
+		self.chip8 = headless::machine(rom, config, seed);
+		self.values = self._read_hooks();
+		self.frames = 0;
+		self.fault = None;
+		self.observation()
+	}
+
+	/// Holds down the keys in `action` and runs `frames` frames. Once the
+	/// program faults the episode is over and steps do nothing.
+	pub fn step(&mut self, action: KeyMask, frames: u32) -> (Observation, StepInfo) {
+		for key in 0..16 {
+			self.chip8.cpu.set_key(Key::from_byte(key).unwrap(), action & (1 << key) != 0);
+		}
+		for _ in 0..frames {
+			if self.fault.is_some() {
+				break;
+			}
+			match self.chip8.run_frame() {
+				Ok(_) => self.frames += 1,
+				Err(e) => self.fault = Some(e)
+			}
+		}
+		let values = self._read_hooks();
+		//a huge weight saturates rather than overflowing
+		let reward = self.hooks.iter().zip(values.iter().zip(self.values.iter()))
+			.map(|(hook, (&now, &before))| hook.weight.saturating_mul(now - before))
+			.fold(0i64, |sum, reward| sum.saturating_add(reward));
+		self.values = values;
+		let info = StepInfo {
+			reward,
+			values: self.hooks.iter().map(|hook| hook.name.clone()).zip(self.values.iter().cloned()).collect(),
+			frames: self.frames,
+			fault: self.fault.clone(),
+			done: self.fault.is_some()
+		};
+		(self.observation(), info)
+	}
+
+	pub fn clone_state(&self) -> EnvState {
+		EnvState {
+			machine: self.chip8.save(),
+			values: self.values.clone(),
+			frames: self.frames,
+			fault: self.fault.clone()
+		}
+	}
+
+	/// Goes back to a state taken during this episode, or another episode
+	/// of the same ROM.
+	pub fn restore_state(&mut self, state: &EnvState) {
+		self.chip8.restore(&state.machine);
+		self.values = state.values.clone();
+		self.frames = state.frames;
+		self.fault = state.fault.clone();
+	}
+
+	pub fn observation(&self) -> Observation {
+		Observation { screen: self.chip8.cpu.gfx }
+	}
+
+	pub fn ram(&self) -> &[u8] {
+		self.chip8.cpu.read_memory(0, RAM_SIZE)
+	}
+
+	pub fn hooks(&self) -> &[RewardHook] {
+		&self.hooks
+	}
+
+	fn _read_hooks(&self) -> Vec<i64> {
+		self.hooks.iter().map(|hook| hook.read(self.ram())).collect()
+	}
+}
+
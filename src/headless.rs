@@ -0,0 +1,124 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc;
+
+use config::Config;
+use error::CpuError;
+use vm::{Chip8, GFX, Key};
+
+/// Key presses and releases to make during a headless run, read from text
+/// with one event a line,
+///
+/// ```text
+/// # hold 5 for the first second
+/// 0 press 5
+/// 60 release 5
+/// ```
+///
+/// giving the frame the event happens at the start of, what happens and
+/// the key as a hex digit. A `frames N` line says how long the run is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputScript {
+	pub frames: Option<u32>,
+	/// Frame, key and whether it is pressed, in the order they happen.
+	pub events: Vec<(u32, Key, bool)>
+}
+
+impl InputScript {
+	pub fn new() -> InputScript {
+		InputScript {
+			frames: None,
+			events: Vec::new()
+		}
+	}
+
+	pub fn load(path: &str) -> Result<InputScript, String> {
+		let mut text = String::new();
+		File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+			.map_err(|e| format!("{}: {}", path, e))?;
+		InputScript::parse(&text).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn parse(text: &str) -> Result<InputScript, String> {
+		let mut script = InputScript::new();
+		for (number, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap().trim();
+			let words : Vec<&str> = line.split_whitespace().collect();
+			let error = || format!("line {}: can't make sense of '{}'", number + 1, line);
+			match words[..] {
+				[] => (),
+				["frames", frames] => script.frames = Some(frames.parse().map_err(|_| error())?),
+				[frame, action, key] => {
+					let frame : u32 = frame.parse().map_err(|_| error())?;
+					let pressed = match action {
+						"press" => true,
+						"release" => false,
+						_ => return Err(error())
+					};
+					let key = u8::from_str_radix(key, 16).ok().and_then(Key::from_byte)
+						.ok_or_else(error)?;
+					if script.events.last().is_some_and(|&(last, _, _)| last > frame) {
+						return Err(format!("line {}: events have to be in order", number + 1));
+					}
+					script.events.push((frame, key, pressed));
+				},
+				_ => return Err(error())
+			}
+		}
+		Ok(script)
+	}
+}
+
+impl fmt::Display for InputScript {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let Some(frames) = self.frames {
+			writeln!(f, "frames {}", frames)?;
+		}
+		for &(frame, key, pressed) in self.events.iter() {
+			writeln!(f, "{} {} {:X}", frame, if pressed { "press" } else { "release" }, key.to_byte())?;
+		}
+		Ok(())
+	}
+}
+
+/// A machine with `rom` loaded and no frontend: nothing sends it keys or
+/// shows its screen, and CXNN is seeded with `seed`, so the same ROM and
+/// config always run the same way.
+pub fn machine(rom: &[u8], config: Config, seed: u64) -> Chip8 {
+	let (_, key_rx) = mpsc::channel();
+	let (gfx_tx, _) = mpsc::channel();
+	let mut chip8 = Chip8::with_config(key_rx, gfx_tx, config);
+	chip8.cpu.logging = false;
+	chip8.cpu.seed(seed);
+	chip8.load(rom);
+	chip8
+}
+
+/// Runs a ROM without a window or keyboard for `frames` 60 Hz frames,
+/// calling `on_frame` with the screen at the end of each. CXNN is seeded,
+/// so the same ROM and config always produce the same frames. A program
+/// that faults gets one last call with the screen it stopped with.
+pub fn run_frames<F>(rom: &[u8], config: Config, frames: u32, on_frame: F)
+	-> Result<(), CpuError> where F: FnMut(&GFX) {
+	run_script(rom, config, &InputScript::new(), frames, on_frame)
+}
+
+/// Like `run_frames`, pressing and releasing keys as `script` says.
+pub fn run_script<F>(rom: &[u8], config: Config, script: &InputScript, frames: u32,
+	mut on_frame: F) -> Result<(), CpuError> where F: FnMut(&GFX) {
+	//keys are set directly between frames, and FX0A waits across frames
+	//for one of them to be released
+	let mut chip8 = machine(rom, config, 0);
+	let mut events = script.events.iter().peekable();
+	for frame in 0..frames {
+		while let Some(&&(_, key, pressed)) = events.peek().filter(|event| event.0 <= frame) {
+			chip8.cpu.set_key(key, pressed);
+			events.next();
+		}
+		let result = chip8.run_frame();
+		on_frame(&chip8.cpu.gfx);
+		result?;
+	}
+	Ok(())
+}
@@ -0,0 +1,133 @@
+/// A decoded CHIP-8 instruction. Register operands are stored as indices
+/// into `CPU::registers` so that executing a decoded instruction never has
+/// to look at the opcode's nibbles again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+	MachineCall(u16),                   //0NNN
+	ClearScreen,                        //00E0
+	Return,                             //00EE
+	Jump(u16),                          //1NNN
+	Call(u16),                          //2NNN
+	SkipEqualByte(usize, u8),           //3XNN
+	SkipNotEqualByte(usize, u8),        //4XNN
+	SkipEqualRegister(usize, usize),    //5XY0
+	LoadByte(usize, u8),                //6XNN
+	AddByte(usize, u8),                 //7XNN
+	Move(usize, usize),                 //8XY0
+	Or(usize, usize),                   //8XY1
+	And(usize, usize),                  //8XY2
+	Xor(usize, usize),                  //8XY3
+	AddRegister(usize, usize),          //8XY4
+	SubRegister(usize, usize),          //8XY5
+	ShiftRight(usize, usize),           //8XY6
+	SubReverse(usize, usize),           //8XY7
+	ShiftLeft(usize, usize),            //8XYE
+	SkipNotEqualRegister(usize, usize), //9XY0
+	LoadIndex(u16),                     //ANNN
+	JumpOffset(u16),                    //BNNN
+	Random(usize, u8),                  //CXNN
+	Draw(usize, usize, usize),          //DXYN
+	SkipKeyPressed(usize),              //EX9E
+	SkipKeyNotPressed(usize),           //EXA1
+	LoadAudioPattern,                   //F002
+	LoadDelay(usize),                   //FX07
+	WaitKey(usize),                     //FX0A
+	SetDelay(usize),                    //FX15
+	SetSound(usize),                    //FX18
+	AddIndex(usize),                    //FX1E
+	LoadSprite(usize),                  //FX29
+	LoadLargeSprite(usize),             //FX30
+	StoreBcd(usize),                    //FX33
+	StoreRegisters(usize),              //FX55
+	LoadRegisters(usize),               //FX65
+	SetPitch(usize),                    //FX3A
+	Unknown(u16)
+}
+
+impl Instruction {
+	pub fn decode(opcode: u16) -> Instruction {
+		let x = ((opcode & 0x0F00) >> 8) as usize;
+		let y = ((opcode & 0x00F0) >> 4) as usize;
+		let n = (opcode & 0x000F) as usize;
+		let nn = (opcode & 0x00FF) as u8;
+		let nnn = opcode & 0x0FFF;
+
+		match opcode & 0xF000 {
+			0x0000 => match opcode {
+				0x00E0 => Instruction::ClearScreen,
+				0x00EE => Instruction::Return,
+				_ => Instruction::MachineCall(nnn)
+			},
+			0x1000 => Instruction::Jump(nnn),
+			0x2000 => Instruction::Call(nnn),
+			0x3000 => Instruction::SkipEqualByte(x, nn),
+			0x4000 => Instruction::SkipNotEqualByte(x, nn),
+			0x5000 if n == 0 => Instruction::SkipEqualRegister(x, y),
+			0x6000 => Instruction::LoadByte(x, nn),
+			0x7000 => Instruction::AddByte(x, nn),
+			0x8000 => match n {
+				0x0 => Instruction::Move(x, y),
+				0x1 => Instruction::Or(x, y),
+				0x2 => Instruction::And(x, y),
+				0x3 => Instruction::Xor(x, y),
+				0x4 => Instruction::AddRegister(x, y),
+				0x5 => Instruction::SubRegister(x, y),
+				0x6 => Instruction::ShiftRight(x, y),
+				0x7 => Instruction::SubReverse(x, y),
+				0xE => Instruction::ShiftLeft(x, y),
+				_ => Instruction::Unknown(opcode)
+			},
+			0x9000 if n == 0 => Instruction::SkipNotEqualRegister(x, y),
+			0xA000 => Instruction::LoadIndex(nnn),
+			0xB000 => Instruction::JumpOffset(nnn),
+			0xC000 => Instruction::Random(x, nn),
+			0xD000 => Instruction::Draw(x, y, n),
+			0xE000 => match nn {
+				0x9E => Instruction::SkipKeyPressed(x),
+				0xA1 => Instruction::SkipKeyNotPressed(x),
+				_ => Instruction::Unknown(opcode)
+			},
+			0xF000 => match nn {
+				0x02 if x == 0 => Instruction::LoadAudioPattern,
+				0x07 => Instruction::LoadDelay(x),
+				0x0A => Instruction::WaitKey(x),
+				0x15 => Instruction::SetDelay(x),
+				0x18 => Instruction::SetSound(x),
+				0x1E => Instruction::AddIndex(x),
+				0x29 => Instruction::LoadSprite(x),
+				0x30 => Instruction::LoadLargeSprite(x),
+				0x33 => Instruction::StoreBcd(x),
+				0x55 => Instruction::StoreRegisters(x),
+				0x65 => Instruction::LoadRegisters(x),
+				0x3A => Instruction::SetPitch(x),
+				_ => Instruction::Unknown(opcode)
+			},
+			_ => Instruction::Unknown(opcode)
+		}
+	}
+
+	/// Whether a basic block has to stop after this instruction. That is the
+	/// case for anything that moves the pc somewhere other than the next
+	/// instruction, blocks on input, writes to memory (which may be the
+	/// block itself), may wait for vertical blank or runs machine code that
+	/// might do any of those.
+	pub fn ends_block(&self) -> bool {
+		matches!(*self,
+			Instruction::MachineCall(_) |
+			Instruction::Return |
+			Instruction::Jump(_) |
+			Instruction::Call(_) |
+			Instruction::JumpOffset(_) |
+			Instruction::SkipEqualByte(_, _) |
+			Instruction::SkipNotEqualByte(_, _) |
+			Instruction::SkipEqualRegister(_, _) |
+			Instruction::SkipNotEqualRegister(_, _) |
+			Instruction::SkipKeyPressed(_) |
+			Instruction::SkipKeyNotPressed(_) |
+			Instruction::WaitKey(_) |
+			Instruction::Draw(_, _, _) |
+			Instruction::StoreBcd(_) |
+			Instruction::StoreRegisters(_) |
+			Instruction::Unknown(_))
+	}
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use config_file::{ConfigFile, Table, Value};
+use vm::Key;
+
+/// The keys of the COSMAC VIP hex keypad, row by row.
+const KEYPAD : [u8; 16] = [
+	0x1, 0x2, 0x3, 0xC,
+	0x4, 0x5, 0x6, 0xD,
+	0x7, 0x8, 0x9, 0xE,
+	0xA, 0x0, 0xB, 0xF
+];
+
+/// The 4x4 block under the left hand that takes the place of the keypad on
+/// each layout, row by row.
+const QWERTY : [&str; 16] = [
+	"Key1", "Key2", "Key3", "Key4",
+	"Q", "W", "E", "R",
+	"A", "S", "D", "F",
+	"Z", "X", "C", "V"
+];
+
+const AZERTY : [&str; 16] = [
+	"Key1", "Key2", "Key3", "Key4",
+	"A", "Z", "E", "R",
+	"Q", "S", "D", "F",
+	"W", "X", "C", "V"
+];
+
+const DVORAK : [&str; 16] = [
+	"Key1", "Key2", "Key3", "Key4",
+	"Apostrophe", "Comma", "Period", "P",
+	"A", "O", "E", "U",
+	"Semicolon", "Q", "J", "K"
+];
+
+/// Maps host keys, by the frontend's name for them (glutin's
+/// `VirtualKeyCode` names, e.g. "Key1", "Q", "Left"), to keypad keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+	bindings: HashMap<String, Key>
+}
+
+impl Keymap {
+	pub fn new() -> Keymap {
+		Keymap {
+			bindings: HashMap::new()
+		}
+	}
+
+	/// The keypad laid out on 1234/QWER/ASDF/ZXCV.
+	pub fn qwerty() -> Keymap {
+		Keymap::_from_layout(&QWERTY)
+	}
+
+	pub fn azerty() -> Keymap {
+		Keymap::_from_layout(&AZERTY)
+	}
+
+	pub fn dvorak() -> Keymap {
+		Keymap::_from_layout(&DVORAK)
+	}
+
+	/// Each keypad key on the host key with the same label: 0-9 on the
+	/// number row and A-F on the letters. This is how keys used to be mapped.
+	pub fn hex() -> Keymap {
+		let mut keymap = Keymap::new();
+		for byte in 0..16u8 {
+			let name = if byte < 10 { format!("Key{}", byte) } else { format!("{:X}", byte) };
+			keymap.bind(&name, Key::from_byte(byte).unwrap());
+		}
+		keymap
+	}
+
+	pub fn by_name(name: &str) -> Option<Keymap> {
+		match name {
+			"qwerty" => Some(Keymap::qwerty()),
+			"azerty" => Some(Keymap::azerty()),
+			"dvorak" => Some(Keymap::dvorak()),
+			"hex" => Some(Keymap::hex()),
+			_ => None
+		}
+	}
+
+	/// Reads a keymap file, applying the overrides for `rom` (the ROM's file
+	/// name) if it has any. See `from_config` for the format.
+	pub fn load(path: &str, rom: Option<&str>, rom_keys: Option<&Table>) -> Result<Keymap, String> {
+		let file = ConfigFile::read(path)?;
+		Keymap::from_config(&file, rom, rom_keys).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	/// Builds a keymap from a config file like
+	///
+	/// ```toml
+	/// preset = "azerty"
+	///
+	/// [keys]
+	/// Space = "5"
+	///
+	/// [roms.PONG]
+	/// Up = "1"
+	/// Down = "4"
+	/// ```
+	///
+	/// The preset (QWERTY if there isn't one) is the starting point, `[keys]`
+	/// is applied over it, then `rom_keys` (the ROM database's bindings for
+	/// the ROM) and finally the `[roms.NAME]` table for `rom`. Any of the
+	/// tables may set `preset` to start over from another layout, and
+	/// binding a key to "" unbinds it.
+	pub fn from_config(file: &ConfigFile, rom: Option<&str>, rom_keys: Option<&Table>)
+		-> Result<Keymap, String> {
+		let mut keymap = Keymap::qwerty();
+		for name in ["", "keys"].iter() {
+			if let Some(table) = file.table(name) {
+				keymap.apply(table)?;
+			}
+		}
+		if let Some(table) = rom_keys {
+			keymap.apply(table)?;
+		}
+		if let Some(table) = rom.and_then(|rom| file.table(&format!("roms.{}", rom))) {
+			keymap.apply(table)?;
+		}
+		Ok(keymap)
+	}
+
+	/// Applies the bindings in `table` over this keymap.
+	pub fn apply(&mut self, table: &Table) -> Result<(), String> {
+		if let Some(preset) = table.get("preset") {
+			let name = preset.as_str().unwrap_or("");
+			*self = Keymap::by_name(name).ok_or_else(|| format!("Unknown keymap preset '{}'", name))?;
+		}
+		for (name, value) in table.iter() {
+			if name == "preset" {
+				continue;
+			}
+			match _parse_key(value) {
+				Some(Some(key)) => self.bind(name, key),
+				Some(None) => self.unbind(name),
+				None => return Err(format!("'{}' has to be bound to a key from 0 to F", name))
+			}
+		}
+		Ok(())
+	}
+
+	pub fn bind(&mut self, name: &str, key: Key) {
+		self.bindings.insert(name.to_string(), key);
+	}
+
+	pub fn unbind(&mut self, name: &str) {
+		self.bindings.remove(name);
+	}
+
+	pub fn get(&self, name: &str) -> Option<Key> {
+		self.bindings.get(name).cloned()
+	}
+
+	fn _from_layout(layout: &[&str; 16]) -> Keymap {
+		let mut keymap = Keymap::new();
+		for (name, &byte) in layout.iter().zip(KEYPAD.iter()) {
+			keymap.bind(name, Key::from_byte(byte).unwrap());
+		}
+		keymap
+	}
+}
+
+impl Default for Keymap {
+	fn default() -> Keymap {
+		Keymap::qwerty()
+	}
+}
+
+/// A key is either a hex digit string ("C") or a number; "" means unbound.
+fn _parse_key(value: &Value) -> Option<Option<Key>> {
+	match *value {
+		Value::String(ref s) if s.is_empty() => Some(None),
+		Value::String(ref s) if s.len() == 1 => u8::from_str_radix(s, 16).ok().map(Key::from_byte),
+		Value::Integer(i) if (0..16).contains(&i) => Some(Key::from_byte(i as u8)),
+		_ => None
+	}
+}
@@ -0,0 +1,237 @@
+use glium;
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::DisplayBuild;
+use glium::Surface;
+use glutin::{Event, ElementState, MouseButton, VirtualKeyCode};
+
+use chip8::library::{label_glyph, Library};
+use chip8::palette::Palette;
+
+const WIDTH : u32 = 1024;
+const HEIGHT : u32 = 768;
+const COLUMNS : usize = 4;
+/// Each screen pixel of a thumbnail is this many window pixels across.
+const THUMBNAIL_SCALE : f32 = 3.0;
+const CELL_WIDTH : f32 = WIDTH as f32 / COLUMNS as f32;
+const CELL_HEIGHT : f32 = 32.0 * THUMBNAIL_SCALE + 48.0;
+/// Each pixel of a title's 3x5 glyphs is this many window pixels across.
+const LABEL_SCALE : f32 = 2.0;
+
+#[derive(Copy, Clone)]
+struct Vertex {
+	position: [f32; 2],
+	color: [f32; 3]
+}
+
+implement_vertex!(Vertex, position, color);
+
+/// A window showing the thumbnails of a `Library`, each titled underneath,
+/// to pick a game from with the arrow keys and Enter, or with the mouse. The
+/// window title shows the title of the selected game.
+pub struct Launcher {
+	display: GlutinFacade,
+	program: glium::program::Program,
+	library: Library,
+	selected: usize,
+	first_row: usize,
+	mouse: (i32, i32)
+}
+
+impl Launcher {
+	pub fn new(library: Library) -> Launcher {
+		let display = glium::glutin::WindowBuilder::new()
+			.with_dimensions(WIDTH, HEIGHT)
+			.with_title("CHIP8".to_string())
+			.build_glium()
+			.unwrap();
+		let program = glium::Program::from_source(&display,
+			VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC, None)
+			.unwrap();
+		Launcher {
+			display,
+			program,
+			library,
+			selected: 0,
+			first_row: 0,
+			mouse: (0, 0)
+		}
+	}
+
+	/// Shows the picker until a game is chosen, returning its path, or the
+	/// window is closed.
+	pub fn choose(mut self) -> Option<String> {
+		if self.library.entries.is_empty() {
+			return None;
+		}
+		self._update_title();
+		loop {
+			self._draw();
+			let events : Vec<Event> = self.display.wait_events().take(1)
+				.chain(self.display.poll_events()).collect();
+			for event in events {
+				match event {
+					Event::Closed => return None,
+					Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+						VirtualKeyCode::Left => self._select(self.selected as isize - 1),
+						VirtualKeyCode::Right => self._select(self.selected as isize + 1),
+						VirtualKeyCode::Up => self._select(self.selected as isize - COLUMNS as isize),
+						VirtualKeyCode::Down => self._select(self.selected as isize + COLUMNS as isize),
+						VirtualKeyCode::Return | VirtualKeyCode::Space => return self._chosen(),
+						VirtualKeyCode::Escape => return None,
+						_ => ()
+					},
+					Event::MouseMoved(x, y) => self.mouse = (x, y),
+					Event::MouseInput(ElementState::Pressed, MouseButton::Left) => {
+						if let Some(i) = self._entry_at(self.mouse) {
+							if i == self.selected {
+								return self._chosen();
+							}
+							self._select(i as isize);
+						}
+					},
+					_ => ()
+				}
+			}
+		}
+	}
+
+	fn _chosen(&self) -> Option<String> {
+		Some(self.library.entries[self.selected].path.clone())
+	}
+
+	fn _select(&mut self, index: isize) {
+		if index < 0 || index as usize >= self.library.entries.len() {
+			return;
+		}
+		self.selected = index as usize;
+
+		//scroll so that the selection is on screen
+		let visible_rows = (HEIGHT as f32 / CELL_HEIGHT) as usize;
+		let row = self.selected / COLUMNS;
+		if row < self.first_row {
+			self.first_row = row;
+		} else if row >= self.first_row + visible_rows {
+			self.first_row = row + 1 - visible_rows;
+		}
+		self._update_title();
+	}
+
+	fn _update_title(&self) {
+		if let Some(window) = self.display.get_window() {
+			let entry = &self.library.entries[self.selected];
+			window.set_title(&format!("CHIP8 - {}", entry.title));
+		}
+	}
+
+	fn _entry_at(&self, (x, y): (i32, i32)) -> Option<usize> {
+		if x < 0 || y < 0 {
+			return None;
+		}
+		let column = (x as f32 / CELL_WIDTH) as usize;
+		let row = (y as f32 / CELL_HEIGHT) as usize + self.first_row;
+		let i = row * COLUMNS + column;
+		if column < COLUMNS && i < self.library.entries.len() { Some(i) } else { None }
+	}
+
+	fn _draw(&mut self) {
+		let mut vertices = Vec::new();
+		let highlight = [1.0, 0.8, 0.2];
+		let frame = [0.25, 0.25, 0.25];
+		for (i, entry) in self.library.entries.iter().enumerate() {
+			let row = i / COLUMNS;
+			if row < self.first_row {
+				continue;
+			}
+			let left = (i % COLUMNS) as f32 * CELL_WIDTH + (CELL_WIDTH - 64.0 * THUMBNAIL_SCALE) / 2.0;
+			let top = (row - self.first_row) as f32 * CELL_HEIGHT + 24.0;
+			if top > HEIGHT as f32 {
+				break;
+			}
+
+			let palette = entry.info.as_ref().and_then(|info| info.palette.clone()).unwrap_or_default();
+			let border = if i == self.selected { highlight } else { frame };
+			_push_rect(&mut vertices, left - 4.0, top - 4.0,
+				64.0 * THUMBNAIL_SCALE + 8.0, 32.0 * THUMBNAIL_SCALE + 8.0, border);
+			_push_rect(&mut vertices, left, top, 64.0 * THUMBNAIL_SCALE, 32.0 * THUMBNAIL_SCALE,
+				Palette::rgb(palette.background()));
+			let foreground = Palette::rgb(palette.foreground());
+			for (y, line) in entry.thumbnail.iter().enumerate() {
+				for (x, &pixel) in line.iter().enumerate() {
+					if pixel != 0 {
+						_push_rect(&mut vertices, left + x as f32 * THUMBNAIL_SCALE,
+							top + y as f32 * THUMBNAIL_SCALE, THUMBNAIL_SCALE, THUMBNAIL_SCALE,
+							foreground);
+					}
+				}
+			}
+			let label = if i == self.selected { highlight } else { [0.8, 0.8, 0.8] };
+			_push_label(&mut vertices, &entry.title, (i % COLUMNS) as f32 * CELL_WIDTH + CELL_WIDTH / 2.0,
+				top + 32.0 * THUMBNAIL_SCALE + 10.0, label);
+		}
+
+		let vertex_buffer = glium::VertexBuffer::new(&self.display, &vertices).unwrap();
+		let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+		let mut target = self.display.draw();
+		target.clear_color(0.05, 0.05, 0.05, 1.0);
+		target.draw(&vertex_buffer, indices, &self.program, &glium::uniforms::EmptyUniforms,
+			&Default::default()).unwrap();
+		target.finish().unwrap();
+	}
+}
+
+/// Adds `text` centred on `centre` with its top at `top`, cut short to fit
+/// under a thumbnail.
+fn _push_label(vertices: &mut Vec<Vertex>, text: &str, centre: f32, top: f32, color: [f32; 3]) {
+	let advance = 4.0 * LABEL_SCALE;
+	let max_chars = (64.0 * THUMBNAIL_SCALE / advance) as usize;
+	let chars : Vec<char> = text.chars().take(max_chars).collect();
+	let left = centre - (chars.len() as f32 * advance - LABEL_SCALE) / 2.0;
+	for (n, &c) in chars.iter().enumerate() {
+		let x = left + n as f32 * advance;
+		for (y, row) in label_glyph(c).iter().enumerate() {
+			for column in 0..3 {
+				if row & (0b100 >> column) != 0 {
+					_push_rect(vertices, x + column as f32 * LABEL_SCALE, top + y as f32 * LABEL_SCALE,
+						LABEL_SCALE, LABEL_SCALE, color);
+				}
+			}
+		}
+	}
+}
+
+/// Adds two triangles covering a rectangle given in window pixels.
+fn _push_rect(vertices: &mut Vec<Vertex>, x: f32, y: f32, width: f32, height: f32, color: [f32; 3]) {
+	let to_gl = |px: f32, py: f32| Vertex {
+		position: [px / WIDTH as f32 * 2.0 - 1.0, 1.0 - py / HEIGHT as f32 * 2.0],
+		color
+	};
+	let (x2, y2) = (x + width, y + height);
+	vertices.extend_from_slice(&[
+		to_gl(x, y), to_gl(x2, y), to_gl(x, y2),
+		to_gl(x2, y), to_gl(x2, y2), to_gl(x, y2)
+	]);
+}
+
+const VERTEX_SHADER_SRC: &str = r#"
+    #version 140
+
+    in vec2 position;
+    in vec3 color;
+    out vec3 v_color;
+
+    void main() {
+        v_color = color;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const FRAGMENT_SHADER_SRC: &str = r#"
+    #version 140
+
+    in vec3 v_color;
+    out vec4 color;
+
+    void main() {
+        color = vec4(v_color, 1.0);
+    }
+"#;
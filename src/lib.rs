@@ -0,0 +1,33 @@
+extern crate rand;
+
+pub mod vm;
+pub mod instruction;
+pub mod lockstep;
+pub mod block_cache;
+pub mod recompiler;
+pub mod config;
+pub mod config_file;
+pub mod conformance;
+pub mod error;
+pub mod memory;
+pub mod font;
+pub mod fuzz;
+pub mod golden;
+pub mod gym;
+pub mod headless;
+pub mod machine_code;
+pub mod audio;
+pub mod display;
+pub mod keymap;
+pub mod library;
+pub mod palette;
+pub mod png;
+pub mod quirks;
+pub mod rom_db;
+pub mod sha1;
+pub mod spec;
+pub mod stack;
+pub mod timing;
+pub mod tty;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,221 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use config::Config;
+use headless;
+use memory::{RAM_SIZE, PROGRAM_START};
+use rom_db::{RomDatabase, RomInfo};
+use vm::GFX;
+
+/// How long ROMs run for before their screen is taken as the thumbnail.
+pub const THUMBNAIL_FRAMES : u32 = 120;
+pub const MAX_RECENT_GAMES : usize = 8;
+
+/// A ROM found by `Library::scan`.
+#[derive(Clone)]
+pub struct RomEntry {
+	pub path: String,
+	pub title: String,
+	pub info: Option<RomInfo>,
+	pub thumbnail: GFX
+}
+
+/// The ROMs in a directory, for picking a game to play.
+pub struct Library {
+	pub entries: Vec<RomEntry>
+}
+
+impl Library {
+	/// Reads every file in `dir` that fits in memory as a ROM, titled from
+	/// `db` where it knows the ROM and by file name otherwise. Files that
+	/// can't be read are skipped with a message, so one bad file doesn't
+	/// empty the library.
+	pub fn scan(dir: &str, db: &RomDatabase) -> io::Result<Library> {
+		let mut entries = Vec::new();
+		for entry in fs::read_dir(dir)? {
+			let path = match entry {
+				Ok(entry) => entry.path(),
+				Err(e) => {
+					println!("Skipping an entry in {}: {}", dir, e);
+					continue;
+				}
+			};
+			let name = match path.file_name().and_then(|name| name.to_str()) {
+				Some(name) if !name.starts_with('.') => name.to_string(),
+				_ => continue
+			};
+			if !path.is_file() {
+				continue;
+			}
+			let data = match _read_file(&path) {
+				Ok(data) => data,
+				Err(e) => {
+					println!("Skipping {}: {}", path.display(), e);
+					continue;
+				}
+			};
+			if data.is_empty() || data.len() > RAM_SIZE - PROGRAM_START {
+				continue;
+			}
+
+			let info = db.lookup(&data).cloned();
+			let title = info.as_ref().and_then(|info| info.title.clone()).unwrap_or(name);
+			entries.push(RomEntry {
+				path: _canonical(&path.to_string_lossy()),
+				title,
+				thumbnail: render_thumbnail(&data, info.as_ref(), THUMBNAIL_FRAMES),
+				info
+			});
+		}
+		entries.sort_by(|a, b| a.title.cmp(&b.title));
+		Ok(Library {
+			entries
+		})
+	}
+
+	/// Moves recently played games to the front, most recent first.
+	pub fn order_by_recent(&mut self, recent: &RecentGames) {
+		for game in recent.games().iter().rev() {
+			if let Some(i) = self.entries.iter().position(|entry| &entry.path == game) {
+				let entry = self.entries.remove(i);
+				self.entries.insert(0, entry);
+			}
+		}
+	}
+}
+
+/// Runs a ROM headlessly for `frames` 60 Hz frames with nothing pressed and
+/// returns what ends up on screen. A ROM that stops early keeps the screen
+/// it stopped with.
+pub fn render_thumbnail(rom: &[u8], info: Option<&RomInfo>, frames: u32) -> GFX {
+	let mut config = Config::default();
+	if let Some(info) = info {
+		info.apply(&mut config);
+	}
+	let mut thumbnail = [[0; 64]; 32];
+	let _ = headless::run_frames(rom, config, frames, |gfx| thumbnail = *gfx);
+	thumbnail
+}
+
+/// A 3x5 glyph for drawing `c` in a game title, a row of three bits for each
+/// line with the leftmost pixel in the high bit. Letters are drawn in upper
+/// case, and characters without a glyph come out as `?`.
+pub fn label_glyph(c: char) -> [u8; 5] {
+	match c.to_ascii_uppercase() {
+		'0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+		'1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+		'2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+		'3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+		'4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+		'5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+		'6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+		'7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+		'8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+		'9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+		'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+		'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+		'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+		'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+		'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+		'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+		'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+		'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+		'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+		'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+		'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+		'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+		'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+		'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+		'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+		'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+		'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+		'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+		'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+		'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+		'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+		'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+		'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+		'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+		'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+		'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+		' ' => [0, 0, 0, 0, 0],
+		'-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+		'.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+		',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+		':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+		'\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+		'!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+		_ => [0b110, 0b001, 0b010, 0b000, 0b010]
+	}
+}
+
+/// The games played most recently, kept in a file with one path per line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecentGames {
+	games: Vec<String>
+}
+
+impl RecentGames {
+	pub fn new() -> RecentGames {
+		RecentGames {
+			games: Vec::new()
+		}
+	}
+
+	/// Reads the list from `file`; a missing file is an empty list.
+	pub fn load(file: &str) -> io::Result<RecentGames> {
+		let mut text = String::new();
+		match fs::File::open(file) {
+			Ok(mut f) => { f.read_to_string(&mut text)?; },
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+			Err(e) => return Err(e)
+		}
+		Ok(RecentGames {
+			games: text.lines().filter(|line| !line.is_empty())
+				.take(MAX_RECENT_GAMES).map(|line| line.to_string()).collect()
+		})
+	}
+
+	pub fn save(&self, file: &str) -> io::Result<()> {
+		let mut f = fs::File::create(file)?;
+		for game in self.games.iter() {
+			writeln!(f, "{}", game)?;
+		}
+		Ok(())
+	}
+
+	/// Puts `game` at the top of the list.
+	pub fn add(&mut self, game: &str) {
+		let game = _canonical(game);
+		self.games.retain(|g| g != &game);
+		self.games.insert(0, game);
+		self.games.truncate(MAX_RECENT_GAMES);
+	}
+
+	pub fn games(&self) -> &[String] {
+		&self.games
+	}
+}
+
+impl Default for RecentGames {
+	fn default() -> RecentGames {
+		RecentGames::new()
+	}
+}
+
+/// Paths are compared in canonical form, so the same ROM reached two
+/// different ways is still the same game.
+fn _canonical(path: &str) -> String {
+	match fs::canonicalize(Path::new(path)) {
+		Ok(path) => path.to_string_lossy().into_owned(),
+		Err(_) => path.to_string()
+	}
+}
+
+fn _read_file(path: &Path) -> io::Result<Vec<u8>> {
+	let mut data = Vec::new();
+	fs::File::open(path)?.read_to_end(&mut data)?;
+	Ok(data)
+}
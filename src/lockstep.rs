@@ -0,0 +1,347 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+use block_cache::MAX_BLOCK_LENGTH;
+use config::Config;
+use error::CpuError;
+use headless;
+use headless::InputScript;
+use instruction::Instruction;
+use quirks::Quirks;
+use vm::{CPU, Snapshot};
+
+/// The ways a CPU can run a program, which should all agree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+	/// One instruction at a time with `CPU::step`, the reference.
+	Interpreter,
+	Cached,
+	Recompiled
+}
+
+impl Backend {
+	pub fn by_name(name: &str) -> Option<Backend> {
+		match name {
+			"interpreter" => Some(Backend::Interpreter),
+			"cached" => Some(Backend::Cached),
+			"recompiled" => Some(Backend::Recompiled),
+			_ => None
+		}
+	}
+
+	/// Runs up to `max_instructions` instructions and returns how many ran.
+	/// The interpreter runs one at a time; the cached and recompiled
+	/// backends run up to a block's worth, as they do in use.
+	pub fn run(&self, cpu: &mut CPU, max_instructions: usize) -> Result<usize, CpuError> {
+		match *self {
+			Backend::Interpreter => cpu.step().map(|_| 1),
+			Backend::Cached => cpu.run_cached(max_instructions.min(MAX_BLOCK_LENGTH)),
+			Backend::Recompiled => cpu.run_recompiled(max_instructions.min(MAX_BLOCK_LENGTH))
+		}
+	}
+}
+
+/// A backend with the settings it runs under.
+#[derive(Clone, Debug)]
+pub struct Core {
+	pub name: String,
+	pub backend: Backend,
+	pub config: Config
+}
+
+impl Core {
+	/// Reads a core as `BACKEND`, `BACKEND@QUIRKS` or `@QUIRKS`, such as
+	/// `recompiled@vip`. Whatever isn't given is taken from `config` and
+	/// the interpreter.
+	pub fn parse(spec: &str, config: &Config) -> Result<Core, String> {
+		let mut parts = spec.splitn(2, '@');
+		let backend = match parts.next().unwrap() {
+			"" => Backend::Interpreter,
+			name => Backend::by_name(name).ok_or_else(|| format!("unknown backend '{}'", name))?
+		};
+		let mut config = config.clone();
+		if let Some(name) = parts.next() {
+			config.quirks = Quirks::by_name(name).ok_or_else(|| format!("unknown quirk profile '{}'", name))?;
+		}
+		Ok(Core {
+			name: spec.to_string(),
+			backend,
+			config
+		})
+	}
+}
+
+/// How far two runs went together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Agreement {
+	pub instructions: usize,
+	/// The fault both stopped with, if they did.
+	pub fault: Option<CpuError>
+}
+
+/// Where two runs first stopped agreeing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+	pub reference: String,
+	pub other: String,
+	/// Instructions that ran before the one that diverged.
+	pub instructions: usize,
+	pub pc: u16,
+	pub instruction: Option<Instruction>,
+	/// What differs, with the reference's value first.
+	pub differences: Vec<String>
+}
+
+impl fmt::Display for Divergence {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} and {} diverged after {} instructions, at {:03X}", self.reference,
+			self.other, self.instructions, self.pc)?;
+		if let Some(instruction) = self.instruction {
+			write!(f, " ({:?})", instruction)?;
+		}
+		writeln!(f, ", {} vs {}:", self.reference, self.other)?;
+		for line in self.differences.iter() {
+			writeln!(f, "  {}", line)?;
+		}
+		Ok(())
+	}
+}
+
+/// Runs `rom` on two cores side by side with the same keys and seed,
+/// comparing their full state after every instruction, or every block
+/// when `other` is the cached or recompiled backend. Frames are as long as the
+/// reference's clock speed makes them, whatever the timing model. Stops
+/// after `max_instructions`, at a fault both cores agree on, or at the
+/// first difference.
+pub fn run(rom: &[u8], reference: &Core, other: &Core, script: &InputScript, max_instructions: usize)
+	-> Result<Agreement, Divergence> {
+	let mut expected = headless::machine(rom, reference.config.clone(), 0);
+	let mut actual = headless::machine(rom, other.config.clone(), 0);
+	let mut frame = 0;
+	let mut events = script.events.iter().peekable();
+	let mut executed = 0;
+	let mut in_frame = 0;
+	while executed < max_instructions {
+		while let Some(&&(_, key, pressed)) = events.peek().filter(|event| event.0 <= frame) {
+			expected.cpu.set_key(key, pressed);
+			actual.cpu.set_key(key, pressed);
+			events.next();
+		}
+		if in_frame >= expected.instructions_per_frame() || expected.cpu.waiting_for_vblank() {
+			expected.tick();
+			actual.tick();
+			frame += 1;
+			in_frame = 0;
+			continue;
+		}
+
+		let pc = expected.cpu.pc;
+		let instruction = expected.cpu.next_instruction().ok();
+		let divergence = move |differences: Vec<String>| Divergence {
+			reference: reference.name.clone(),
+			other: other.name.clone(),
+			instructions: executed,
+			pc,
+			instruction,
+			differences
+		};
+		let budget = (max_instructions - executed).min(expected.instructions_per_frame() - in_frame);
+		let n = match other.backend.run(&mut actual.cpu, budget) {
+			Ok(n) => n,
+			Err(e) => {
+				//the reference has to fault on the same instruction
+				for _ in 0..budget {
+					if let Err(reference_error) = reference.backend.run(&mut expected.cpu, 1) {
+						let mut differences = expected.cpu.snapshot().diff(&actual.cpu.snapshot());
+						if reference_error == e && differences.is_empty() {
+							return Ok(Agreement { instructions: executed, fault: Some(e) });
+						}
+						differences.insert(0, format!("fault: {} vs {}", reference_error, e));
+						return Err(divergence(differences));
+					}
+				}
+				return Err(divergence(vec![format!("fault: none vs {}", e)]));
+			}
+		};
+		for _ in 0..n {
+			if let Err(e) = reference.backend.run(&mut expected.cpu, 1) {
+				return Err(divergence(vec![format!("fault: {} vs none", e)]));
+			}
+		}
+		executed += n;
+		in_frame += n;
+
+		let differences = expected.cpu.snapshot().diff(&actual.cpu.snapshot());
+		if !differences.is_empty() {
+			return Err(divergence(differences));
+		}
+	}
+	Ok(Agreement { instructions: executed, fault: None })
+}
+
+/// The state before one instruction of a trace. Anything the trace doesn't
+/// give isn't checked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceState {
+	pub pc: Option<u16>,
+	pub index: Option<u16>,
+	pub registers: [Option<u8>; 16],
+	pub delay_timer: Option<u8>,
+	pub sound_timer: Option<u8>
+}
+
+/// A trace of a run made elsewhere, read from text with the state before
+/// each instruction on a line,
+///
+/// ```text
+/// pc=200 i=000 v0=00 v1=1F ... vf=00 dt=00 st=00
+/// ```
+///
+/// with every value in hex and any field left out if the other emulator
+/// doesn't know it. Blank lines and `#` comments are skipped.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+	pub states: Vec<TraceState>
+}
+
+impl Trace {
+	pub fn load(path: &str) -> Result<Trace, String> {
+		let mut text = String::new();
+		File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+			.map_err(|e| format!("{}: {}", path, e))?;
+		Trace::parse(&text).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn parse(text: &str) -> Result<Trace, String> {
+		let mut states = Vec::new();
+		for (number, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() {
+				continue;
+			}
+			let mut state = TraceState::default();
+			for field in line.split_whitespace() {
+				let error = || format!("line {}: can't make sense of '{}'", number + 1, field);
+				let mut parts = field.splitn(2, '=');
+				let key = parts.next().unwrap().to_lowercase();
+				let value = parts.next().and_then(|value| u16::from_str_radix(value, 16).ok())
+					.ok_or_else(error)?;
+				let byte = || if value <= 0xFF { Ok(value as u8) } else { Err(error()) };
+				match key.as_str() {
+					"pc" => state.pc = Some(value),
+					"i" => state.index = Some(value),
+					"dt" => state.delay_timer = Some(byte()?),
+					"st" => state.sound_timer = Some(byte()?),
+					_ => match key.strip_prefix('v').and_then(|x| usize::from_str_radix(x, 16).ok()) {
+						Some(x) if x < 16 && key.len() == 2 => state.registers[x] = Some(byte()?),
+						_ => return Err(error())
+					}
+				}
+			}
+			states.push(state);
+		}
+		Ok(Trace { states })
+	}
+}
+
+/// A line of a trace with everything in `snapshot` that a trace has.
+pub fn trace_line(snapshot: &Snapshot) -> String {
+	let mut line = format!("pc={:03X} i={:03X}", snapshot.pc, snapshot.index);
+	for (x, v) in snapshot.registers.iter().enumerate() {
+		line.push_str(&format!(" v{:x}={:02X}", x, v));
+	}
+	line.push_str(&format!(" dt={:02X} st={:02X}", snapshot.delay_timer, snapshot.sound_timer));
+	line
+}
+
+/// Runs `rom` on `core` one instruction at a time, checking its state
+/// against every line of `trace` before running the instruction. Frames
+/// are counted as in `run`. Stops at the end of the trace or the first
+/// difference, with the trace's value first.
+pub fn run_against_trace(rom: &[u8], core: &Core, script: &InputScript, trace: &Trace)
+	-> Result<Agreement, Divergence> {
+	let mut chip8 = headless::machine(rom, core.config.clone(), 0);
+	let mut frame = 0;
+	let mut events = script.events.iter().peekable();
+	let mut in_frame = 0;
+	for (executed, state) in trace.states.iter().enumerate() {
+		while in_frame >= chip8.instructions_per_frame() || chip8.cpu.waiting_for_vblank() {
+			chip8.tick();
+			frame += 1;
+			in_frame = 0;
+		}
+		while let Some(&&(_, key, pressed)) = events.peek().filter(|event| event.0 <= frame) {
+			chip8.cpu.set_key(key, pressed);
+			events.next();
+		}
+
+		let differences = _compare_trace(state, &chip8.cpu.snapshot());
+		if !differences.is_empty() {
+			return Err(Divergence {
+				reference: "trace".to_string(),
+				other: core.name.clone(),
+				instructions: executed,
+				pc: chip8.cpu.pc,
+				instruction: chip8.cpu.next_instruction().ok(),
+				differences
+			});
+		}
+		if let Err(e) = core.backend.run(&mut chip8.cpu, 1) {
+			return Ok(Agreement { instructions: executed, fault: Some(e) });
+		}
+		in_frame += 1;
+	}
+	Ok(Agreement { instructions: trace.states.len(), fault: None })
+}
+
+/// Runs `rom` on `core` for up to `max_instructions`, framed as in `run`,
+/// and returns its trace, which ends early if the program faults.
+pub fn record_trace(rom: &[u8], core: &Core, script: &InputScript, max_instructions: usize)
+	-> (String, Option<CpuError>) {
+	let mut chip8 = headless::machine(rom, core.config.clone(), 0);
+	let mut frame = 0;
+	let mut events = script.events.iter().peekable();
+	let mut in_frame = 0;
+	let mut trace = String::new();
+	for _ in 0..max_instructions {
+		while in_frame >= chip8.instructions_per_frame() || chip8.cpu.waiting_for_vblank() {
+			chip8.tick();
+			frame += 1;
+			in_frame = 0;
+		}
+		while let Some(&&(_, key, pressed)) = events.peek().filter(|event| event.0 <= frame) {
+			chip8.cpu.set_key(key, pressed);
+			events.next();
+		}
+		trace.push_str(&trace_line(&chip8.cpu.snapshot()));
+		trace.push('\n');
+		if let Err(e) = core.backend.run(&mut chip8.cpu, 1) {
+			return (trace, Some(e));
+		}
+		in_frame += 1;
+	}
+	(trace, None)
+}
+
+fn _compare_trace(state: &TraceState, snapshot: &Snapshot) -> Vec<String> {
+	let mut differences = Vec::new();
+	if let Some(pc) = state.pc.filter(|&pc| pc != snapshot.pc) {
+		differences.push(format!("pc: {:03X} vs {:03X}", pc, snapshot.pc));
+	}
+	if let Some(index) = state.index.filter(|&index| index != snapshot.index) {
+		differences.push(format!("I: {:03X} vs {:03X}", index, snapshot.index));
+	}
+	for (x, register) in state.registers.iter().enumerate() {
+		if let Some(v) = register.filter(|&v| v != snapshot.registers[x]) {
+			differences.push(format!("V{:X}: {:02X} vs {:02X}", x, v, snapshot.registers[x]));
+		}
+	}
+	if let Some(dt) = state.delay_timer.filter(|&dt| dt != snapshot.delay_timer) {
+		differences.push(format!("delay timer: {} vs {}", dt, snapshot.delay_timer));
+	}
+	if let Some(st) = state.sound_timer.filter(|&st| st != snapshot.sound_timer) {
+		differences.push(format!("sound timer: {} vs {}", st, snapshot.sound_timer));
+	}
+	differences
+}
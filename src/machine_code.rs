@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use error::CpuError;
+use vm::CPU;
+
+/// What to do with 0NNN, which called an RCA 1802 machine code routine on
+/// the original hardware.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MachineCodePolicy {
+	/// Treat the call as a no-op.
+	Ignore,
+	/// Stop with `CpuError::MachineCodeCall`.
+	Error,
+	/// Hand the call to the handler installed with
+	/// `CPU::set_machine_code_handler`, stopping if there isn't one.
+	Dispatch
+}
+
+/// Stands in for the machine code routines a program calls. The pc still
+/// points at the 0NNN instruction while the handler runs and moves on to
+/// the next instruction once it returns.
+pub trait MachineCodeHandler: Send {
+	fn call(&mut self, address: u16, cpu: &mut CPU) -> Result<(), CpuError>;
+}
+
+pub type Routine = Box<dyn FnMut(&mut CPU) + Send>;
+
+/// A handler made of Rust replacements for individual routines, looked up
+/// by the address they are called at.
+pub struct Routines {
+	routines: HashMap<u16, Routine>
+}
+
+impl Routines {
+	pub fn new() -> Routines {
+		Routines {
+			routines: HashMap::new()
+		}
+	}
+
+	pub fn add(&mut self, address: u16, routine: Routine) {
+		self.routines.insert(address, routine);
+	}
+}
+
+impl Default for Routines {
+	fn default() -> Routines {
+		Routines::new()
+	}
+}
+
+impl MachineCodeHandler for Routines {
+	fn call(&mut self, address: u16, cpu: &mut CPU) -> Result<(), CpuError> {
+		match self.routines.get_mut(&address) {
+			Some(routine) => {
+				routine(cpu);
+				Ok(())
+			},
+			None => Err(CpuError::MachineCodeCall(address))
+		}
+	}
+}
@@ -1,56 +1,714 @@
 #[macro_use]
 extern crate glium;
-extern crate rand;
 extern crate glutin;
+extern crate rodio;
+extern crate chip8;
 
-mod vm;
+mod speaker;
 mod ui;
+mod launcher;
 mod gfx;
-mod tests;
 
 use std::env;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::panic;
+use std::process;
 use std::sync::mpsc;
 use std::thread;
 
-use vm::{Chip8};
+use chip8::{fuzz, golden, headless, lockstep, png};
+use chip8::audio::{Waveform, WavSink};
+use chip8::config::Config;
+use chip8::font::Font;
+use chip8::fuzz::Fuzzer;
+use chip8::golden::Outcome;
+use chip8::headless::InputScript;
+use chip8::config_file::Table;
+use chip8::conformance::{Status, Suite};
+use chip8::display::{Effects, Filter, Presentation, Renderer, Scaling};
+use chip8::keymap::Keymap;
+use launcher::Launcher;
+use chip8::library::{Library, RecentGames};
+use chip8::lockstep::{Core, Trace};
+use chip8::machine_code::MachineCodePolicy;
+use chip8::palette::Palette;
+use chip8::quirks::Quirks;
+use chip8::rom_db::RomDatabase;
+use chip8::timing::Timing;
+use chip8::vm::{Chip8};
+use chip8::tty::{BellSink, TtyFrontend};
 use ui::{Chip8UI};
+use speaker::SpeakerSink;
+
+/// Where the ROM database is looked for when `--db` isn't given.
+const DEFAULT_ROM_DATABASE : &str = "roms.toml";
+/// Where the game picker looks for games when `--library` isn't given.
+const DEFAULT_LIBRARY : &str = "games";
+/// Where `--golden` keeps the golden frames and key scripts.
+const GOLDEN_DIR : &str = "golden";
+/// Where `--golden` writes diffs of frames that don't match.
+const GOLDEN_DIFF_DIR : &str = "target/golden";
+/// Where `--conformance` writes diffs and screens of tests that don't pass.
+const CONFORMANCE_DIFF_DIR : &str = "target/conformance";
+/// How many frames a ROM runs for before `--screenshot` takes its picture.
+const DEFAULT_SCREENSHOT_FRAMES : u32 = 300;
+/// Image pixels per screen pixel in screenshots.
+const SCREENSHOT_SCALE : u32 = 8;
+/// How many instructions `--lockstep` and `--write-trace` run for when
+/// `--steps` isn't given.
+const DEFAULT_LOCKSTEP_STEPS : usize = 1_000_000;
+/// How many inputs `--fuzz` tries when `--runs` isn't given.
+const DEFAULT_FUZZ_RUNS : usize = 100_000;
 
 pub fn main() {
 	let args_vec : Vec<_> = env::args().collect();
-	if args_vec.len() == 0 {
-		println!("Please specify a path to a game file");
-		return;
+
+	let mut config = Config::default();
+	let mut game_path = None;
+	let mut wav_path = None;
+	let mut keymap_arg = None;
+	let mut db_path = None;
+	let mut library_dir = DEFAULT_LIBRARY.to_string();
+	let mut quirks = None;
+	let mut clock_speed = None;
+	let mut timing = None;
+	let mut mute = false;
+	let mut tty = false;
+	let mut scaling = Scaling::default();
+	let mut palette = None;
+	let mut effects = Effects::default();
+	let mut screenshot_path = None;
+	let mut frames = DEFAULT_SCREENSHOT_FRAMES;
+	let mut golden = false;
+	let mut bless = false;
+	let mut conformance_dir = None;
+	let mut lockstep = None;
+	let mut trace_path = None;
+	let mut write_trace_path = None;
+	let mut keys_path = None;
+	let mut steps = DEFAULT_LOCKSTEP_STEPS;
+	let mut fuzz_dir = None;
+	let mut fuzz_core = None;
+	let mut runs = DEFAULT_FUZZ_RUNS;
+	let mut args = args_vec[1..].iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--font" => {
+				let name = args.next().map(|s| s.as_str()).unwrap_or("");
+				config.font = match Font::by_name(name) {
+					Some(font) => font,
+					None => match Font::from_file(name) {
+						Ok(font) => font,
+						Err(e) => {
+							println!("Failed to load font {}: {}", name, e);
+							return;
+						}
+					}
+				};
+			},
+			"--font-address" => {
+				let address = args.next().map(|s| s.trim_start_matches("0x"));
+				match address.and_then(|a| u16::from_str_radix(a, 16).ok()) {
+					Some(a) => config.font_address = a,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--machine-code" => {
+				config.machine_code = match args.next().map(|s| s.as_str()) {
+					Some("ignore") => MachineCodePolicy::Ignore,
+					Some("error") => MachineCodePolicy::Error,
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				};
+			},
+			"--tone" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(frequency) => config.buzzer.frequency = frequency,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--volume" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(volume) => config.buzzer.volume = volume,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--waveform" => {
+				match args.next().and_then(|s| Waveform::by_name(s)) {
+					Some(waveform) => config.buzzer.waveform = waveform,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--wav" => wav_path = args.next().cloned(),
+			"--mute" => mute = true,
+			"--tty" => tty = true,
+			"--scale" => {
+				match args.next().and_then(|s| Scaling::by_name(s)) {
+					Some(mode) => scaling = mode,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--palette" => {
+				match args.next().map(|s| Palette::parse(s)) {
+					Some(Ok(p)) => palette = Some(p),
+					Some(Err(e)) => {
+						println!("Bad palette: {}", e);
+						return;
+					},
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--persistence" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(p) if (0.0..1.0).contains(&p) => effects.persistence = p,
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--filter" => {
+				match args.next().and_then(|s| Filter::by_name(s)) {
+					Some(filter) => effects.filter = filter,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--present" => {
+				match args.next().and_then(|s| Presentation::by_name(s)) {
+					Some(presentation) => effects.presentation = presentation,
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--blend" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(weight) if (0.0..=1.0).contains(&weight) => {
+						effects.presentation = Presentation::Blend(weight);
+					},
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--screenshot" => screenshot_path = args.next().cloned(),
+			"--frames" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(n) if n > 0 => frames = n,
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--golden" => golden = true,
+			"--bless" => bless = true,
+			"--conformance" => match args.next() {
+				Some(dir) => conformance_dir = Some(dir.clone()),
+				None => {
+					print_usage(&args_vec[0]);
+					return;
+				}
+			},
+			"--lockstep" => match args.next() {
+				Some(cores) => lockstep = Some(cores.clone()),
+				None => {
+					print_usage(&args_vec[0]);
+					return;
+				}
+			},
+			"--trace" => trace_path = args.next().cloned(),
+			"--write-trace" => write_trace_path = args.next().cloned(),
+			"--keys" => keys_path = args.next().cloned(),
+			"--steps" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(n) if n > 0 => steps = n,
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--fuzz" => match args.next() {
+				Some(dir) => fuzz_dir = Some(dir.clone()),
+				None => {
+					print_usage(&args_vec[0]);
+					return;
+				}
+			},
+			"--core" => fuzz_core = args.next().cloned(),
+			"--runs" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(n) if n > 0 => runs = n,
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--keymap" => keymap_arg = args.next().cloned(),
+			"--db" => db_path = args.next().cloned(),
+			"--library" => match args.next() {
+				Some(dir) => library_dir = dir.clone(),
+				None => {
+					print_usage(&args_vec[0]);
+					return;
+				}
+			},
+			"--quirks" => {
+				match args.next().and_then(|s| Quirks::by_name(s)) {
+					Some(profile) => quirks = Some(profile),
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--clock" => {
+				match args.next().and_then(|s| s.parse().ok()) {
+					Some(speed) if speed > 0 => clock_speed = Some(speed),
+					_ => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			"--timing" => {
+				match args.next().and_then(|s| Timing::by_name(s)) {
+					Some(model) => timing = Some(model),
+					None => {
+						print_usage(&args_vec[0]);
+						return;
+					}
+				}
+			},
+			path => game_path = Some(path.to_string())
+		}
 	}
-	else if args_vec.len() != 2 {
-		println!("Usage: {}: GAME_PATH", args_vec[0]);
+	if let Err(e) = config.font.check_address(config.font_address) {
+		println!("{}", e);
 		return;
 	}
-	println!("Loading game at {}...", args_vec[1]);
-	let game_path = &args_vec[1];
-	let mut f = File::open(game_path).unwrap();
+	let db_path = db_path.or_else(|| if Path::new(DEFAULT_ROM_DATABASE).exists() {
+		Some(DEFAULT_ROM_DATABASE.to_string())
+	} else {
+		None
+	});
+	let db = match db_path {
+		Some(ref path) => match RomDatabase::load(path) {
+			Ok(db) => db,
+			Err(e) => {
+				println!("Failed to load ROM database {}", e);
+				return;
+			}
+		},
+		None => RomDatabase::new()
+	};
+
+	if let Some(dir) = fuzz_dir {
+		let mut config = config.clone();
+		config.quirks = quirks.unwrap_or_default();
+		config.clock_speed = clock_speed.unwrap_or(fuzz::DEFAULT_FUZZ_CLOCK_SPEED);
+		let core = fuzz_core.unwrap_or_else(|| "interpreter".to_string());
+		process::exit(_fuzz(&dir, &library_dir, &config, &core, runs));
+	}
+
+	if let Some(dir) = conformance_dir {
+		process::exit(_run_conformance(&dir));
+	}
+
+	//compare every game in the library with its golden frame
+	if golden || bless {
+		process::exit(_check_golden(&library_dir, &db, bless));
+	}
+
+	//without a game to play, let the user pick one
+	let windowless = screenshot_path.is_some() || lockstep.is_some() || trace_path.is_some()
+		|| write_trace_path.is_some();
+	let recent_path = _recent_games_path();
+	let mut recent = RecentGames::load(&recent_path).unwrap_or_default();
+	let game_path = match game_path {
+		Some(path) => path,
+		None if tty || windowless => {
+			print_usage(&args_vec[0]);
+			return;
+		},
+		None => {
+			let mut library = match Library::scan(&library_dir, &db) {
+				Ok(library) => library,
+				Err(e) => {
+					println!("Failed to read games from {}: {}", library_dir, e);
+					print_usage(&args_vec[0]);
+					return;
+				}
+			};
+			library.order_by_recent(&recent);
+			match Launcher::new(library).choose() {
+				Some(path) => path,
+				None => return
+			}
+		}
+	};
+	if !windowless {
+		recent.add(&game_path);
+		if let Err(e) = recent.save(&recent_path) {
+			println!("Failed to save recent games to {}: {}", recent_path, e);
+		}
+	}
+
+	println!("Loading game at {}...", game_path);
 	let mut data = Vec::new();
+	println!("Reading program data...");
+	if let Err(e) = File::open(&game_path).and_then(|mut f| f.read_to_end(&mut data)) {
+		println!("Failed to read data: {}", e);
+		return;
+	}
+
+	//settings from the ROM database go under anything given on the command line
+	let rom_info = db.lookup(&data).cloned();
+	if let Some(ref info) = rom_info {
+		println!("Found {} in the ROM database", info.title.as_ref().unwrap_or(&game_path));
+		info.apply(&mut config);
+	}
+	if let Some(quirks) = quirks {
+		config.quirks = quirks;
+	}
+	if let Some(clock_speed) = clock_speed {
+		config.clock_speed = clock_speed;
+	}
+	if let Some(timing) = timing {
+		config.timing = timing;
+	}
+	let title = rom_info.as_ref().and_then(|info| info.title.clone())
+		.unwrap_or_else(|| "CHIP8".to_string());
+	let palette = palette.or_else(|| rom_info.as_ref().and_then(|info| info.palette.clone()))
+		.unwrap_or_default();
+	let mut renderer = Renderer::new(palette.clone(), effects);
+
+	//cores are compared, and traces written, without a window either
+	if lockstep.is_some() || trace_path.is_some() || write_trace_path.is_some() {
+		let script = match keys_path {
+			Some(ref path) => match InputScript::load(path) {
+				Ok(script) => script,
+				Err(e) => {
+					println!("Failed to load keys {}", e);
+					return;
+				}
+			},
+			None => InputScript::new()
+		};
+		let cores = lockstep.unwrap_or_else(|| "interpreter".to_string());
+		process::exit(_run_lockstep(&data, &config, &cores, trace_path, write_trace_path,
+			&script, steps));
+	}
 
-	//set up the chip8 with channels
+	//screenshots are taken without a window, keys or sound
+	if let Some(path) = screenshot_path {
+		let mut image = None;
+		let mut frame = 0;
+		let result = headless::run_frames(&data, config, frames, |gfx| {
+			frame += 1;
+			if frame == frames {
+				image = Some(renderer.render_image(gfx, SCREENSHOT_SCALE));
+			} else {
+				renderer.frame(gfx);
+			}
+		});
+		if let Err(e) = result {
+			println!("The program stopped after {} frames: {:?}", frame, e);
+		}
+		let image = image.unwrap_or_else(|| renderer.render_image(&[[0; 64]; 32], SCREENSHOT_SCALE));
+		match png::write(&path, &image) {
+			Ok(()) => println!("Saved screenshot to {}", path),
+			Err(e) => println!("Failed to write {}: {}", path, e)
+		}
+		return;
+	}
+
+	//a keymap file can override keys for a ROM by its file name
+	let rom_keys = rom_info.as_ref().and_then(|info| info.keys.as_ref());
+	let keymap = match keymap_arg.as_ref().map(|arg| (arg, Keymap::by_name(arg))) {
+		Some((_, Some(preset))) => _with_rom_keys(preset, rom_keys),
+		None => _with_rom_keys(Keymap::default(), rom_keys),
+		Some((path, None)) => {
+			let rom = Path::new(&game_path).file_name().and_then(|name| name.to_str());
+			Keymap::load(path, rom, rom_keys)
+		}
+	};
+	let keymap = match keymap {
+		Ok(keymap) => keymap,
+		Err(e) => {
+			println!("Failed to load keymap {}", e);
+			return;
+		}
+	};
+
+	//set up the chip8 with channels, sending frames at vertical blank if the
+	//renderer combines or fades them
+	config.present_at_vblank = renderer.effects.at_vblank();
 	let (key_tx, key_rx) = mpsc::channel();
 	let (gfx_tx, gfx_rx) = mpsc::channel();
-	let mut chip8 = Chip8::new(key_rx, gfx_tx);
+	let mut chip8 = Chip8::with_config(key_rx, gfx_tx, config);
+	//the log would scroll the screen away
+	chip8.cpu.logging = !tty;
+	let sample_rate = chip8.audio.sample_rate();
+	if !mute {
+		if tty {
+			chip8.audio.add_sink(Box::new(BellSink::new()));
+		} else {
+			chip8.audio.add_sink(Box::new(SpeakerSink::new(sample_rate)));
+		}
+	}
+	if let Some(path) = wav_path {
+		match WavSink::create(&path, sample_rate) {
+			Ok(sink) => chip8.audio.add_sink(Box::new(sink)),
+			Err(e) => println!("Failed to create {}: {}", path, e)
+		}
+	}
 
 	//load the actual cartridge
-	println!("Reading program data...");
-	if let Err(e) = f.read_to_end(&mut data) {
-		println!("Failed to read data: {}", e);
-	}
 	chip8.load(&data);
 	
 	println!("Program data loaded.");
 
 	println!("Starting emulator");
-	thread::spawn(move || chip8.run());
+	let emulator = thread::spawn(move || chip8.run());
 	println!("Emulator running.");
 
 	println!("Starting session...");
-	let session = Chip8UI::new(key_tx, gfx_rx);
-	session.start_session();
-}
\ No newline at end of file
+	if tty {
+		TtyFrontend::new(key_tx, gfx_rx, keymap, &palette).start_session();
+	} else {
+		let session = Chip8UI::new(key_tx, gfx_rx, keymap, &title, renderer, scaling);
+		session.start_session();
+	}
+	//the session has dropped its end of the key channel, which stops the
+	//emulator, so its sinks get to finish
+	let _ = emulator.join();
+}
+
+fn print_usage(program: &str) {
+	println!("Usage: {}: [--font vip|chip48|schip|octo|FONT_FILE] [--font-address HEX] \
+		[--machine-code ignore|error] [--tone HZ] [--volume 0-1] \
+		[--waveform square|sine|triangle|sawtooth] [--wav FILE] [--mute] \
+		[--keymap qwerty|azerty|dvorak|hex|KEYMAP_FILE] [--db ROM_DATABASE] \
+		[--quirks vip|schip|xochip|modern] [--clock HZ] [--timing uniform|vip] [--library DIR] \
+		[--tty] [--scale fit|integer|stretch] [--palette NAME|HEX,HEX,...] \
+		[--persistence 0-1] [--filter none|scanlines|crt] \
+		[--present immediate|vblank|or|blend] [--blend 0-1] [--screenshot FILE.png] \
+		[--frames N] [--golden [--bless]] [--conformance DIR] \
+		[--lockstep CORE[,CORE] | --trace FILE | --write-trace FILE] [--keys FILE] [--steps N] \
+		[--fuzz CORPUS_DIR [--core CORE] [--runs N]] [GAME_PATH]\n\
+		where a CORE is interpreter|cached|recompiled, optionally followed by @QUIRKS", program);
+}
+
+/// Runs the test ROMs in a conformance suite under each quirk profile,
+/// printing a line for every test and profile, and returns the exit code:
+/// 1 if a test failed, or else 3 if one couldn't be checked.
+fn _run_conformance(dir: &str) -> i32 {
+	let suite = match Suite::load(dir) {
+		Ok(suite) => suite,
+		Err(e) => {
+			println!("Failed to load conformance suite: {}", e);
+			return 2;
+		}
+	};
+	let (mut failed, mut missing) = (0, 0);
+	for (test, profile, status) in suite.run(CONFORMANCE_DIFF_DIR) {
+		match status {
+			Status::Passed => println!("{:<16} {:<8} pass", test, profile),
+			Status::Failed(detail) => {
+				println!("{:<16} {:<8} FAIL ({})", test, profile, detail);
+				failed += 1;
+			},
+			Status::Missing(reason) => {
+				println!("{:<16} {:<8} NOT CHECKED: {}", test, profile, reason);
+				missing += 1;
+			}
+		}
+	}
+	if failed > 0 {
+		1
+	} else if missing > 0 {
+		println!("{} tests couldn't be checked", missing);
+		3
+	} else {
+		0
+	}
+}
+
+/// Runs a game on two cores side by side, or one core against a trace,
+/// printing where they first differ, or writes a trace of one core.
+/// Returns the exit code.
+fn _run_lockstep(rom: &[u8], config: &Config, cores: &str, trace_path: Option<String>,
+	write_trace_path: Option<String>, script: &InputScript, steps: usize) -> i32 {
+	let cores : Result<Vec<Core>, String> = cores.split(',').map(|spec| Core::parse(spec, config))
+		.collect();
+	let cores = match cores {
+		Ok(cores) => cores,
+		Err(e) => {
+			println!("Bad core: {}", e);
+			return 2;
+		}
+	};
+	if let Some(path) = write_trace_path {
+		let (trace, fault) = lockstep::record_trace(rom, &cores[0], script, steps);
+		if let Some(e) = fault {
+			println!("The program stopped: {}", e);
+		}
+		return match File::create(&path).and_then(|mut f| f.write_all(trace.as_bytes())) {
+			Ok(()) => {
+				println!("Wrote trace to {}", path);
+				0
+			},
+			Err(e) => {
+				println!("Failed to write {}: {}", path, e);
+				2
+			}
+		};
+	}
+	let result = match trace_path {
+		Some(path) => match Trace::load(&path) {
+			Ok(trace) => lockstep::run_against_trace(rom, &cores[0], script, &trace),
+			Err(e) => {
+				println!("Failed to load trace {}", e);
+				return 2;
+			}
+		},
+		None if cores.len() == 2 => lockstep::run(rom, &cores[0], &cores[1], script, steps),
+		None => {
+			println!("--lockstep needs two cores, like interpreter,recompiled");
+			return 2;
+		}
+	};
+	match result {
+		Ok(agreement) => {
+			print!("No differences in {} instructions", agreement.instructions);
+			match agreement.fault {
+				Some(e) => println!(", then both stopped: {}", e),
+				None => println!()
+			}
+			0
+		},
+		Err(divergence) => {
+			print!("{}", divergence);
+			1
+		}
+	}
+}
+
+/// Fuzzes the CPU from the corpus in `dir`, seeding it from the library
+/// the first time, and prints what it found. Returns the exit code.
+fn _fuzz(dir: &str, library_dir: &str, config: &Config, core: &str, runs: usize) -> i32 {
+	let core = match Core::parse(core, config) {
+		Ok(core) => core,
+		Err(e) => {
+			println!("Bad core: {}", e);
+			return 2;
+		}
+	};
+	match fuzz::seed_corpus(dir, library_dir) {
+		Ok(0) => (),
+		Ok(n) => println!("Seeded {} with {} games from {}", dir, n, library_dir),
+		Err(e) => {
+			println!("Failed to seed the corpus: {}", e);
+			return 2;
+		}
+	}
+	//panics are what's being looked for, so keep them from filling the terminal
+	panic::set_hook(Box::new(|_| ()));
+	let mut fuzzer = Fuzzer::new(core, 0);
+	let report = fuzzer.load_corpus(dir).and_then(|_| fuzzer.run(runs, Some(dir)));
+	let _ = panic::take_hook();
+	let report = match report {
+		Ok(report) => report,
+		Err(e) => {
+			println!("Fuzzing failed: {}", e);
+			return 2;
+		}
+	};
+	println!("{} runs, {} ended in a fault, {} edges covered, {} inputs in the corpus",
+		report.runs, report.faults, report.edges, report.corpus);
+	for crash in report.crashes.iter() {
+		println!("CRASH: {} (a {} byte rom, saved in {}/{})", crash.message, crash.rom.len(), dir,
+			fuzz::CRASH_DIR);
+	}
+	if report.crashes.is_empty() { 0 } else { 1 }
+}
+
+/// Runs the golden frame checks, printing how each game did, and returns
+/// the exit code.
+fn _check_golden(library_dir: &str, db: &RomDatabase, bless: bool) -> i32 {
+	let outcomes = match golden::check(library_dir, GOLDEN_DIR, GOLDEN_DIFF_DIR, db, bless) {
+		Ok(outcomes) => outcomes,
+		Err(e) => {
+			println!("Failed to check golden frames: {}", e);
+			return 2;
+		}
+	};
+	let mut failed = 0;
+	for (name, fault, outcome) in outcomes {
+		match outcome {
+			Outcome::Matched => match fault {
+				Some(e) => println!("{}: ok, stopped as before: {}", name, e),
+				None => println!("{}: ok", name)
+			},
+			Outcome::Blessed => println!("{}: blessed", name),
+			Outcome::Missing => {
+				println!("{}: no golden frame, run with --bless to make one", name);
+				failed += 1;
+			},
+			Outcome::Differed(path, pixels) => {
+				println!("{}: {} pixels differ, see {}", name, pixels, path);
+				failed += 1;
+			},
+			Outcome::FaultDiffered(expected, actual) => {
+				println!("{}: expected \"{}\" but got \"{}\"", name, expected, actual);
+				failed += 1;
+			}
+		}
+	}
+	if failed > 0 { 1 } else { 0 }
+}
+
+/// The recent games list lives in the home directory, if there is one.
+fn _recent_games_path() -> String {
+	match env::var("HOME") {
+		Ok(home) => Path::new(&home).join(".chip8_recent").to_string_lossy().into_owned(),
+		Err(_) => ".chip8_recent".to_string()
+	}
+}
+
+fn _with_rom_keys(mut keymap: Keymap, rom_keys: Option<&Table>) -> Result<Keymap, String> {
+	if let Some(table) = rom_keys {
+		keymap.apply(table)?;
+	}
+	Ok(keymap)
+}
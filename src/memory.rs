@@ -0,0 +1,105 @@
+use error::CpuError;
+
+pub const RAM_SIZE : usize = 4096;
+/// Programs are loaded here; everything below belonged to the interpreter
+/// on the original machines and now holds the font.
+pub const PROGRAM_START : usize = 0x200;
+
+/// What happens when a program touches an address past the end of RAM.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AddressMode {
+	/// Only the low 12 bits of the address are used, as on the COSMAC VIP.
+	Wrap,
+	/// The access is reported as a `CpuError::AddressOutOfRange`.
+	Error
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+	Read,
+	Write
+}
+
+/// Called with every data access a program makes (instruction fetches are
+/// not included), after the address has been resolved.
+pub type MemoryHook = Box<dyn FnMut(Access, usize, u8) + Send>;
+
+/// The 4K address space of the machine.
+pub struct Memory {
+	ram: [u8; RAM_SIZE],
+	address_mode: AddressMode,
+	protect_interpreter_area: bool,
+	hooks: Vec<MemoryHook>
+}
+
+impl Memory {
+	pub fn new(address_mode: AddressMode, protect_interpreter_area: bool) -> Memory {
+		Memory {
+			ram: [0; RAM_SIZE],
+			address_mode,
+			protect_interpreter_area,
+			hooks: Vec::new()
+		}
+	}
+
+	pub fn add_hook(&mut self, hook: MemoryHook) {
+		self.hooks.push(hook);
+	}
+
+	pub fn resolve(&self, address: usize) -> Result<usize, CpuError> {
+		if address < RAM_SIZE {
+			return Ok(address);
+		}
+		match self.address_mode {
+			AddressMode::Wrap => Ok(address % RAM_SIZE),
+			AddressMode::Error => Err(CpuError::AddressOutOfRange(address))
+		}
+	}
+
+	pub fn read(&mut self, address: usize) -> Result<u8, CpuError> {
+		let address = self.resolve(address)?;
+		let value = self.ram[address];
+		for hook in self.hooks.iter_mut() {
+			hook(Access::Read, address, value);
+		}
+		Ok(value)
+	}
+
+	/// Writes `bytes` starting at `address`. Nothing is written unless the
+	/// whole range is valid.
+	pub fn write(&mut self, address: usize, bytes: &[u8]) -> Result<(), CpuError> {
+		for i in 0..bytes.len() {
+			let resolved = self.resolve(address + i)?;
+			if self.protect_interpreter_area && resolved < PROGRAM_START {
+				return Err(CpuError::ProtectedWrite(resolved));
+			}
+		}
+		for (i, &value) in bytes.iter().enumerate() {
+			let resolved = self.resolve(address + i)?;
+			self.ram[resolved] = value;
+			for hook in self.hooks.iter_mut() {
+				hook(Access::Write, resolved, value);
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads the two bytes of the instruction at `address`.
+	pub fn fetch(&self, address: usize) -> Result<u16, CpuError> {
+		let high = self.ram[self.resolve(address)?] as u16;
+		let low = self.ram[self.resolve(address + 1)?] as u16;
+		Ok(high << 8 | low)
+	}
+
+	/// Writes on behalf of the host (loading fonts and programs), so neither
+	/// protection nor hooks apply. Addresses always wrap.
+	pub fn load(&mut self, address: usize, bytes: &[u8]) {
+		for (i, &value) in bytes.iter().enumerate() {
+			self.ram[(address + i) % RAM_SIZE] = value;
+		}
+	}
+
+	pub fn as_slice(&self) -> &[u8] {
+		&self.ram
+	}
+}
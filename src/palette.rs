@@ -0,0 +1,94 @@
+use config_file::Value;
+
+/// The colours the screen is drawn in, as 0xRRGGBB, indexed by pixel value.
+/// The first is the background and the second the colour of lit pixels.
+/// XO-CHIP screens have two planes, so their pixels take four values: the
+/// third colour is for the second plane and the fourth for both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+	pub colors: Vec<u32>
+}
+
+impl Palette {
+	/// Takes the background colour and at least the foreground after it.
+	pub fn new(colors: &[u32]) -> Result<Palette, String> {
+		if colors.len() < 2 {
+			return Err("A palette needs at least two colours".to_string());
+		}
+		Ok(Palette {
+			colors: colors.to_vec()
+		})
+	}
+
+	pub fn by_name(name: &str) -> Option<Palette> {
+		let colors : &[u32] = match name {
+			"classic" => &[0x000000, 0xFFFFFF],
+			"amber" => &[0x1A0F00, 0xFFB000],
+			"green" => &[0x001A00, 0x33FF66],
+			"lcd" => &[0x9BBC0F, 0x0F380F],
+			"octo" => &[0x996600, 0xFFCC00, 0xFF6600, 0x662200],
+			_ => return None
+		};
+		Palette::new(colors).ok()
+	}
+
+	/// Reads a palette given on the command line: either a name or hex
+	/// colours separated by commas, background first.
+	pub fn parse(text: &str) -> Result<Palette, String> {
+		if let Some(palette) = Palette::by_name(text) {
+			return Ok(palette);
+		}
+		let mut values = Vec::new();
+		for color in text.split(',') {
+			let color = color.trim().trim_start_matches('#').trim_start_matches("0x");
+			let value = i64::from_str_radix(color, 16)
+				.map_err(|_| format!("'{}' isn't a palette or a colour", color))?;
+			values.push(Value::Integer(value));
+		}
+		Palette::from_values(&values)
+	}
+
+	/// Reads a palette from an array of colours, background first.
+	pub fn from_values(values: &[Value]) -> Result<Palette, String> {
+		let mut colors = Vec::new();
+		for value in values {
+			match value.as_integer() {
+				Some(color) if (0..=0xFFFFFF).contains(&color) => colors.push(color as u32),
+				_ => return Err(format!("{:?} isn't a colour", value))
+			}
+		}
+		Palette::new(&colors)
+	}
+
+	pub fn background(&self) -> u32 {
+		self.colors[0]
+	}
+
+	pub fn foreground(&self) -> u32 {
+		self.colors[1]
+	}
+
+	/// The colour of a pixel with the given value. Palettes with fewer
+	/// colours than that show the pixel as lit.
+	pub fn color(&self, value: u8) -> u32 {
+		match value {
+			0 => self.colors[0],
+			v => *self.colors.get(v as usize).unwrap_or(&self.colors[1])
+		}
+	}
+
+	/// A colour split into red, green and blue from 0 to 1.
+	pub fn rgb(color: u32) -> [f32; 3] {
+		[
+			(color >> 16 & 0xFF) as f32 / 255.0,
+			(color >> 8 & 0xFF) as f32 / 255.0,
+			(color & 0xFF) as f32 / 255.0
+		]
+	}
+}
+
+impl Default for Palette {
+	fn default() -> Palette {
+		Palette::by_name("classic").unwrap()
+	}
+}
@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io;
+use std::io::Write;
+
+use display::Image;
+
+/// Encodes an image as a PNG. The image data is stored without
+/// compression, which keeps the encoder small; screenshots are tiny anyway.
+pub fn encode(image: &Image) -> Vec<u8> {
+	let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+	let mut header = Vec::new();
+	header.extend_from_slice(&_be32(image.width));
+	header.extend_from_slice(&_be32(image.height));
+	header.extend_from_slice(&[8, 2, 0, 0, 0]); //8 bit RGB, no interlacing
+	_chunk(&mut png, b"IHDR", &header);
+
+	//every row starts with filter type 0 (none)
+	let row_bytes = image.width as usize * 3;
+	let mut raw = Vec::with_capacity((row_bytes + 1) * image.height as usize);
+	for row in image.data.chunks(row_bytes) {
+		raw.push(0);
+		raw.extend_from_slice(row);
+	}
+	_chunk(&mut png, b"IDAT", &_zlib_stored(&raw));
+	_chunk(&mut png, b"IEND", &[]);
+	png
+}
+
+pub fn write(path: &str, image: &Image) -> io::Result<()> {
+	File::create(path)?.write_all(&encode(image))
+}
+
+fn _chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+	png.extend_from_slice(&_be32(data.len() as u32));
+	let start = png.len();
+	png.extend_from_slice(kind);
+	png.extend_from_slice(data);
+	let crc = _crc32(&png[start..]);
+	png.extend_from_slice(&_be32(crc));
+}
+
+/// A zlib stream made of uncompressed deflate blocks.
+fn _zlib_stored(data: &[u8]) -> Vec<u8> {
+	let mut out = vec![0x78, 0x01];
+	let mut blocks = data.chunks(0xFFFF).peekable();
+	if blocks.peek().is_none() {
+		out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+	}
+	while let Some(block) = blocks.next() {
+		let last = if blocks.peek().is_none() { 1 } else { 0 };
+		let len = block.len() as u16;
+		out.push(last);
+		out.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+		out.extend_from_slice(block);
+	}
+	out.extend_from_slice(&_be32(_adler32(data)));
+	out
+}
+
+fn _crc32(data: &[u8]) -> u32 {
+	let mut crc = 0xFFFFFFFFu32;
+	for &byte in data {
+		crc ^= byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+		}
+	}
+	!crc
+}
+
+fn _adler32(data: &[u8]) -> u32 {
+	let (mut a, mut b) = (1u32, 0u32);
+	for &byte in data {
+		a = (a + byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+	b << 16 | a
+}
+
+fn _be32(value: u32) -> [u8; 4] {
+	[(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
@@ -0,0 +1,104 @@
+use config_file::Table;
+
+/// Behaviours that CHIP-8 interpreters disagree on. Programs are usually
+/// written against one interpreter and only run properly with its quirks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+	/// 8XY6/8XYE shift VY into VX, instead of shifting VX in place.
+	pub shift_uses_vy: bool,
+	/// FX55/FX65 leave I pointing past the last register stored or loaded.
+	pub load_store_increments_index: bool,
+	/// BNNN jumps to XNN plus VX, instead of NNN plus V0.
+	pub jump_uses_vx: bool,
+	/// 8XY1/8XY2/8XY3 clear VF.
+	pub logic_resets_vf: bool,
+	/// Sprites are cut off at the edges of the screen instead of wrapping.
+	pub clip_sprites: bool,
+	/// DXYN waits for vertical blank, so at most one sprite is drawn a frame.
+	pub display_wait: bool
+}
+
+impl Quirks {
+	/// The original COSMAC VIP interpreter.
+	pub fn vip() -> Quirks {
+		Quirks {
+			shift_uses_vy: true,
+			load_store_increments_index: true,
+			jump_uses_vx: false,
+			logic_resets_vf: true,
+			clip_sprites: true,
+			display_wait: true
+		}
+	}
+
+	/// SUPER-CHIP 1.1 on the HP48.
+	pub fn schip() -> Quirks {
+		Quirks {
+			shift_uses_vy: false,
+			load_store_increments_index: false,
+			jump_uses_vx: true,
+			logic_resets_vf: false,
+			clip_sprites: true,
+			display_wait: false
+		}
+	}
+
+	/// XO-CHIP as implemented by Octo.
+	pub fn xochip() -> Quirks {
+		Quirks {
+			shift_uses_vy: true,
+			load_store_increments_index: true,
+			jump_uses_vx: false,
+			logic_resets_vf: false,
+			clip_sprites: false,
+			display_wait: false
+		}
+	}
+
+	/// None of the quirks, which is how this emulator has always behaved and
+	/// what most programs written for CHIP-48 and later expect.
+	pub fn modern() -> Quirks {
+		Quirks {
+			shift_uses_vy: false,
+			load_store_increments_index: false,
+			jump_uses_vx: false,
+			logic_resets_vf: false,
+			clip_sprites: false,
+			display_wait: false
+		}
+	}
+
+	pub fn by_name(name: &str) -> Option<Quirks> {
+		match name {
+			"vip" | "cosmac-vip" => Some(Quirks::vip()),
+			"schip" => Some(Quirks::schip()),
+			"xochip" | "xo-chip" => Some(Quirks::xochip()),
+			"modern" => Some(Quirks::modern()),
+			_ => None
+		}
+	}
+
+	/// Turns individual quirks on or off by their field names.
+	pub fn apply(&mut self, table: &Table) -> Result<(), String> {
+		for (name, value) in table.iter() {
+			let enabled = value.as_bool()
+				.ok_or_else(|| format!("Quirk '{}' has to be true or false", name))?;
+			match name.as_str() {
+				"shift_uses_vy" => self.shift_uses_vy = enabled,
+				"load_store_increments_index" => self.load_store_increments_index = enabled,
+				"jump_uses_vx" => self.jump_uses_vx = enabled,
+				"logic_resets_vf" => self.logic_resets_vf = enabled,
+				"clip_sprites" => self.clip_sprites = enabled,
+				"display_wait" => self.display_wait = enabled,
+				_ => return Err(format!("Unknown quirk '{}'", name))
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Default for Quirks {
+	fn default() -> Quirks {
+		Quirks::modern()
+	}
+}
@@ -0,0 +1,193 @@
+use block_cache::{Block, MAX_BLOCK_LENGTH};
+use config::Config;
+use error::CpuError;
+use headless;
+use instruction::Instruction;
+use vm::{Chip8, CPU};
+
+/// How many times a block has to be executed before it is compiled.
+pub const HOT_BLOCK_THRESHOLD : u32 = 16;
+
+pub type Op = Box<dyn Fn(&mut CPU) -> Result<(), CpuError> + Send + Sync>;
+
+/// A basic block translated into a chain of pre-bound closures. Plain
+/// register operations are compiled to closures that touch the CPU
+/// directly and leave the pc alone; everything else is handed back to
+/// `CPU::execute` with the pc set to where the instruction lives.
+pub struct CompiledBlock {
+	pub start: u16,
+	pub end: u16,
+	pub len: usize,
+	ops: Vec<Op>
+}
+
+impl CompiledBlock {
+	pub fn run(&self, cpu: &mut CPU) -> Result<(), CpuError> {
+		for op in self.ops.iter() {
+			op(cpu)?;
+		}
+		Ok(())
+	}
+}
+
+pub fn compile(block: &Block) -> CompiledBlock {
+	let mut ops : Vec<Op> = Vec::with_capacity(block.instructions.len() + 1);
+	let mut pc_is_current = true;
+	for (i, &instruction) in block.instructions.iter().enumerate() {
+		let address = block.start + 2 * i as u16;
+		match _compile_op(instruction) {
+			Some(op) => {
+				ops.push(op);
+				pc_is_current = false;
+			},
+			None => {
+				ops.push(Box::new(move |cpu: &mut CPU| {
+					cpu.pc = address;
+					cpu.execute(instruction)
+				}));
+				pc_is_current = true;
+			}
+		}
+	}
+
+	//the last instruction was a plain one, so nobody has moved the pc yet
+	if !pc_is_current {
+		let end = block.end;
+		ops.push(Box::new(move |cpu: &mut CPU| {
+			cpu.pc = end;
+			Ok(())
+		}));
+	}
+
+	CompiledBlock {
+		start: block.start,
+		end: block.end,
+		len: block.instructions.len(),
+		ops
+	}
+}
+
+fn _compile_op(instruction: Instruction) -> Option<Op> {
+	let op : Op = match instruction {
+		Instruction::LoadByte(x, n) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] = n;
+			Ok(())
+		}),
+		Instruction::AddByte(x, n) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] = cpu.registers[x].wrapping_add(n);
+			Ok(())
+		}),
+		Instruction::Move(x, y) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] = cpu.registers[y];
+			Ok(())
+		}),
+		Instruction::Or(x, y) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] |= cpu.registers[y];
+			if cpu.quirks.logic_resets_vf {
+				cpu.registers[0xF] = 0;
+			}
+			Ok(())
+		}),
+		Instruction::And(x, y) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] &= cpu.registers[y];
+			if cpu.quirks.logic_resets_vf {
+				cpu.registers[0xF] = 0;
+			}
+			Ok(())
+		}),
+		Instruction::Xor(x, y) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] ^= cpu.registers[y];
+			if cpu.quirks.logic_resets_vf {
+				cpu.registers[0xF] = 0;
+			}
+			Ok(())
+		}),
+		Instruction::LoadIndex(address) => Box::new(move |cpu: &mut CPU| {
+			cpu.index = address;
+			Ok(())
+		}),
+		Instruction::LoadDelay(x) => Box::new(move |cpu: &mut CPU| {
+			cpu.registers[x] = cpu.delay_timer;
+			Ok(())
+		}),
+		Instruction::SetDelay(x) => Box::new(move |cpu: &mut CPU| {
+			cpu.delay_timer = cpu.registers[x];
+			Ok(())
+		}),
+		Instruction::SetSound(x) => Box::new(move |cpu: &mut CPU| {
+			cpu.sound_timer = cpu.registers[x];
+			Ok(())
+		}),
+		_ => return None
+	};
+	Some(op)
+}
+
+/// What `verify_against_interpreter` checked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Verification {
+	pub instructions: usize,
+	/// How many times a compiled block ran rather than the interpreter.
+	pub compiled_runs: u64
+}
+
+/// Runs `cartridge` on the recompiler and on the reference interpreter
+/// side by side, comparing the full machine state after every block the
+/// recompiler runs. Returns what was executed, or a description of the
+/// first point where the two disagree.
+pub fn verify_against_interpreter(cartridge: &[u8], seed: u64, max_instructions: usize)
+	-> Result<Verification, String> {
+	let mut reference = headless::machine(cartridge, Config::default(), seed);
+	let mut recompiled = headless::machine(cartridge, Config::default(), seed);
+
+	let mut executed = 0;
+	while executed < max_instructions {
+		let pc = recompiled.cpu.pc;
+		//a block's worth at a time, so compiled blocks always fit
+		let budget = (max_instructions - executed).min(MAX_BLOCK_LENGTH);
+		let n = match recompiled.cpu.run_recompiled(budget) {
+			Ok(n) => n,
+			Err(e) => {
+				//the interpreter has to fault on the same instruction
+				for _ in 0..budget {
+					if let Err(reference_error) = reference.cpu.step() {
+						if reference_error == e && reference.cpu.snapshot() == recompiled.cpu.snapshot() {
+							return Ok(_verification(executed, &recompiled));
+						}
+						return Err(format!("Backends faulted differently after the block at {:X}: \
+							interpreter {} at {:X}, recompiler {} at {:X}",
+							pc, reference_error, reference.cpu.pc, e, recompiled.cpu.pc));
+					}
+				}
+				return Err(format!("Recompiler faulted after the block at {:X} but the interpreter \
+					did not: {}", pc, e));
+			}
+		};
+		for _ in 0..n {
+			if let Err(e) = reference.cpu.step() {
+				return Err(format!("Interpreter faulted at {:X} but the recompiler did not: {}",
+					reference.cpu.pc, e));
+			}
+		}
+		executed += n;
+		if n == 0 {
+			//waiting for vblank, which only a new frame ends
+			reference.tick();
+			recompiled.tick();
+		}
+
+		let differences = reference.cpu.snapshot().diff(&recompiled.cpu.snapshot());
+		if !differences.is_empty() {
+			return Err(format!("Backends diverged after {} instructions, after the block at {:X}, \
+				interpreter vs recompiler:\n  {}", executed, pc, differences.join("\n  ")));
+		}
+	}
+	Ok(_verification(executed, &recompiled))
+}
+
+fn _verification(instructions: usize, recompiled: &Chip8) -> Verification {
+	Verification {
+		instructions,
+		compiled_runs: recompiled.cpu.block_cache().compiled_runs()
+	}
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use config::Config;
+use config_file::{ConfigFile, Table};
+use gym::RewardHook;
+use palette::Palette;
+use quirks::Quirks;
+use sha1;
+use stack::MAX_STACK_DEPTH;
+use timing::Timing;
+
+/// What a ROM needs to run properly. Anything left out is up to the
+/// user's own settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomInfo {
+	pub title: Option<String>,
+	pub quirks: Option<Quirks>,
+	/// Instructions per second.
+	pub clock_speed: Option<u32>,
+	pub timing: Option<Timing>,
+	/// How many return addresses fit on the call stack.
+	pub stack_depth: Option<usize>,
+	/// Key bindings applied over the user's keymap, in keymap file form.
+	pub keys: Option<Table>,
+	pub palette: Option<Palette>,
+	/// Numbers in RAM whose changes reward an agent playing the game.
+	pub rewards: Vec<RewardHook>
+}
+
+impl RomInfo {
+	/// Copies the settings that belong to the machine into `config`.
+	pub fn apply(&self, config: &mut Config) {
+		if let Some(quirks) = self.quirks {
+			config.quirks = quirks;
+		}
+		if let Some(clock_speed) = self.clock_speed {
+			config.clock_speed = clock_speed;
+		}
+		if let Some(timing) = self.timing {
+			config.timing = timing;
+		}
+		if let Some(depth) = self.stack_depth {
+			config.stack_depth = depth;
+		}
+	}
+}
+
+/// ROM settings keyed by the SHA-1 of the ROM, read from a file like
+///
+/// ```toml
+/// [0123456789abcdef0123456789abcdef01234567]
+/// title = "Pong"
+/// quirks = "vip"
+/// clock_speed = 500
+/// timing = "vip"
+/// stack_depth = 12
+/// palette = [0x000000, 0xFFFFFF]
+///
+/// [0123456789abcdef0123456789abcdef01234567.keys]
+/// Up = "1"
+///
+/// [0123456789abcdef0123456789abcdef01234567.quirks]
+/// clip_sprites = false
+///
+/// [0123456789abcdef0123456789abcdef01234567.rewards.score]
+/// address = 0x314
+/// digits = 3
+/// ```
+///
+/// where `quirks` names a profile and the `.quirks` table adjusts it, and
+/// each `.rewards` table is a `RewardHook`.
+pub struct RomDatabase {
+	roms: HashMap<String, RomInfo>
+}
+
+impl RomDatabase {
+	pub fn new() -> RomDatabase {
+		RomDatabase {
+			roms: HashMap::new()
+		}
+	}
+
+	pub fn load(path: &str) -> Result<RomDatabase, String> {
+		let file = ConfigFile::read(path)?;
+		RomDatabase::from_config(&file).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn from_config(file: &ConfigFile) -> Result<RomDatabase, String> {
+		let mut db = RomDatabase::new();
+		for name in file.table_names() {
+			if name.is_empty() || name.contains('.') {
+				continue;
+			}
+			let info = _parse_entry(file, name).map_err(|e| format!("[{}]: {}", name, e))?;
+			db.roms.insert(name.to_lowercase(), info);
+		}
+		Ok(db)
+	}
+
+	/// Looks a ROM up by its contents.
+	pub fn lookup(&self, rom: &[u8]) -> Option<&RomInfo> {
+		self.get(&sha1::hex_digest(rom))
+	}
+
+	pub fn get(&self, hash: &str) -> Option<&RomInfo> {
+		self.roms.get(&hash.to_lowercase())
+	}
+
+	pub fn len(&self) -> usize {
+		self.roms.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.roms.is_empty()
+	}
+}
+
+impl Default for RomDatabase {
+	fn default() -> RomDatabase {
+		RomDatabase::new()
+	}
+}
+
+fn _parse_entry(file: &ConfigFile, name: &str) -> Result<RomInfo, String> {
+	let table = file.table(name).unwrap();
+	let mut info = RomInfo {
+		title: None,
+		quirks: None,
+		clock_speed: None,
+		timing: None,
+		stack_depth: None,
+		keys: file.table(&format!("{}.keys", name)).cloned(),
+		palette: None,
+		rewards: Vec::new()
+	};
+
+	for (key, value) in table.iter() {
+		match key.as_str() {
+			"title" => info.title = Some(value.as_str()
+				.ok_or("title has to be a string")?.to_string()),
+			"quirks" => {
+				let profile = value.as_str().unwrap_or("");
+				info.quirks = Some(Quirks::by_name(profile)
+					.ok_or_else(|| format!("Unknown quirk profile '{}'", profile))?);
+			},
+			"clock_speed" => match value.as_integer() {
+				Some(speed) if speed > 0 && speed <= u32::MAX as i64 => info.clock_speed = Some(speed as u32),
+				_ => return Err(format!("clock_speed has to be from 1 to {}", u32::MAX))
+			},
+			"timing" => {
+				let model = value.as_str().unwrap_or("");
+				info.timing = Some(Timing::by_name(model)
+					.ok_or_else(|| format!("Unknown timing '{}'", model))?);
+			},
+			"stack_depth" => match value.as_integer() {
+				Some(depth) if depth > 0 && depth as usize <= MAX_STACK_DEPTH =>
+					info.stack_depth = Some(depth as usize),
+				_ => return Err(format!("stack_depth has to be from 1 to {}", MAX_STACK_DEPTH))
+			},
+			"palette" => {
+				let colors = value.as_array().ok_or("palette has to be an array of colours")?;
+				info.palette = Some(Palette::from_values(colors)?);
+			},
+			_ => return Err(format!("Unknown setting '{}'", key))
+		}
+	}
+
+	if let Some(overrides) = file.table(&format!("{}.quirks", name)) {
+		let mut quirks = info.quirks.unwrap_or_default();
+		quirks.apply(overrides)?;
+		info.quirks = Some(quirks);
+	}
+
+	let prefix = format!("{}.rewards.", name);
+	for table_name in file.table_names() {
+		if table_name.starts_with(&prefix) {
+			let reward = &table_name[prefix.len()..];
+			info.rewards.push(RewardHook::from_table(reward, file.table(table_name).unwrap())
+				.map_err(|e| format!("reward '{}': {}", reward, e))?);
+		}
+	}
+	Ok(info)
+}
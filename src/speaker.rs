@@ -0,0 +1,50 @@
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use rodio;
+use rodio::buffer::SamplesBuffer;
+
+use chip8::audio::AudioSink;
+
+/// Plays audio on the default output device. The output stream can't leave
+/// the thread that opened it, so it lives on its own thread and samples
+/// are sent over to it.
+pub struct SpeakerSink {
+	samples: Sender<Vec<f32>>
+}
+
+impl SpeakerSink {
+	pub fn new(sample_rate: u32) -> SpeakerSink {
+		let (tx, rx) = mpsc::channel::<Vec<f32>>();
+		thread::spawn(move || {
+			let (_stream, handle) = match rodio::OutputStream::try_default() {
+				Ok(output) => output,
+				Err(e) => {
+					println!("No audio output available: {}", e);
+					return;
+				}
+			};
+			let sink = match rodio::Sink::try_new(&handle) {
+				Ok(sink) => sink,
+				Err(e) => {
+					println!("Failed to open audio output: {}", e);
+					return;
+				}
+			};
+			for samples in rx {
+				sink.append(SamplesBuffer::new(1, sample_rate, samples));
+			}
+		});
+		SpeakerSink {
+			samples: tx
+		}
+	}
+}
+
+impl AudioSink for SpeakerSink {
+	fn write(&mut self, samples: &[f32]) {
+		//the audio thread may have given up on the device, that's fine
+		let _ = self.samples.send(samples.to_vec());
+	}
+}
@@ -0,0 +1,434 @@
+use audio::PATTERN_BYTES;
+use config::Config;
+use error::CpuError;
+use font::{SMALL_GLYPH_SIZE, LARGE_GLYPH_SIZE};
+use memory::RAM_SIZE;
+use quirks::Quirks;
+use vm::Snapshot;
+
+/// The parts of an opcode its pattern leaves open.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Operands {
+	pub x: usize,
+	pub y: usize,
+	pub n: usize,
+	pub nn: u8,
+	pub nnn: u16
+}
+
+impl Operands {
+	pub fn of(opcode: u16) -> Operands {
+		Operands {
+			x: (opcode >> 8 & 0xF) as usize,
+			y: (opcode >> 4 & 0xF) as usize,
+			n: (opcode & 0xF) as usize,
+			nn: (opcode & 0xFF) as u8,
+			nnn: opcode & 0xFFF
+		}
+	}
+}
+
+/// What an instruction does besides the machine state depends on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+	pub quirks: Quirks,
+	pub font_address: u16,
+	/// Where the large digits are, if the font has any.
+	pub large_font_address: Option<u16>,
+	pub stack_depth: usize,
+	/// The next byte from the random number generator, which CXNN masks.
+	pub random: u8
+}
+
+impl Environment {
+	pub fn new(config: &Config, random: u8) -> Environment {
+		Environment {
+			quirks: config.quirks,
+			font_address: config.font_address,
+			large_font_address: config.font.large.as_ref()
+				.map(|_| config.font_address + config.font.small.len() as u16),
+			stack_depth: config.stack_depth,
+			random
+		}
+	}
+}
+
+/// What an instruction does to the machine, with the pc already moved past
+/// it. Faults leave the machine as it was.
+pub type Effect = fn(&mut Snapshot, Operands, &Environment) -> Result<(), CpuError>;
+
+/// One instruction of the CHIP-8 instruction set.
+pub struct OpcodeSpec {
+	/// The opcode in hex, with `X`, `Y` and `N` for the nibbles operands are
+	/// taken from, as in `8XY4`.
+	pub pattern: &'static str,
+	pub summary: &'static str,
+	/// The `Quirks` fields that change what it does.
+	pub quirks: &'static [&'static str],
+	pub effect: Effect
+}
+
+impl OpcodeSpec {
+	/// The bits of an opcode the pattern fixes, and what they are.
+	pub fn mask(&self) -> (u16, u16) {
+		let mut mask = 0;
+		let mut bits = 0;
+		for c in self.pattern.chars() {
+			mask <<= 4;
+			bits <<= 4;
+			if let Some(digit) = c.to_digit(16) {
+				mask |= 0xF;
+				bits |= digit as u16;
+			}
+		}
+		(mask, bits)
+	}
+
+	pub fn matches(&self, opcode: u16) -> bool {
+		let (mask, bits) = self.mask();
+		opcode & mask == bits
+	}
+
+	/// The opcode this pattern makes with its operands taken from `operands`.
+	pub fn opcode(&self, operands: u16) -> u16 {
+		let (mask, bits) = self.mask();
+		bits | operands & !mask
+	}
+}
+
+/// Every instruction, in the order opcodes are matched against them. The
+/// effects are those of the interpreters the quirk profiles are named
+/// after; 0NNN is taken to be a machine code routine that can't be run.
+pub static SPEC: &[OpcodeSpec] = &[
+	OpcodeSpec {
+		pattern: "00E0", summary: "clear the screen", quirks: &[],
+		effect: |s, _, _| {
+			s.gfx = [[0; 64]; 32];
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "00EE", summary: "return from a subroutine", quirks: &[],
+		effect: |s, _, _| {
+			s.pc = s.stack.pop().ok_or(CpuError::StackUnderflow)?;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "0NNN", summary: "call the machine code routine at NNN", quirks: &[],
+		effect: |_, o, _| Err(CpuError::MachineCodeCall(o.nnn))
+	},
+	OpcodeSpec {
+		pattern: "1NNN", summary: "jump to NNN", quirks: &[],
+		effect: |s, o, _| {
+			s.pc = o.nnn;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "2NNN", summary: "call the subroutine at NNN", quirks: &[],
+		effect: |s, o, e| {
+			if s.stack.len() >= e.stack_depth {
+				return Err(CpuError::StackOverflow(e.stack_depth));
+			}
+			s.stack.push(s.pc);
+			s.pc = o.nnn;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "3XNN", summary: "skip if VX == NN", quirks: &[],
+		effect: |s, o, _| _skip_if(s, s.registers[o.x] == o.nn)
+	},
+	OpcodeSpec {
+		pattern: "4XNN", summary: "skip if VX != NN", quirks: &[],
+		effect: |s, o, _| _skip_if(s, s.registers[o.x] != o.nn)
+	},
+	OpcodeSpec {
+		pattern: "5XY0", summary: "skip if VX == VY", quirks: &[],
+		effect: |s, o, _| _skip_if(s, s.registers[o.x] == s.registers[o.y])
+	},
+	OpcodeSpec {
+		pattern: "6XNN", summary: "VX = NN", quirks: &[],
+		effect: |s, o, _| {
+			s.registers[o.x] = o.nn;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "7XNN", summary: "VX += NN, without touching VF", quirks: &[],
+		effect: |s, o, _| {
+			s.registers[o.x] = s.registers[o.x].wrapping_add(o.nn);
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "8XY0", summary: "VX = VY", quirks: &[],
+		effect: |s, o, _| {
+			s.registers[o.x] = s.registers[o.y];
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "8XY1", summary: "VX |= VY", quirks: &["logic_resets_vf"],
+		effect: |s, o, e| _logic(s, o, e, s.registers[o.x] | s.registers[o.y])
+	},
+	OpcodeSpec {
+		pattern: "8XY2", summary: "VX &= VY", quirks: &["logic_resets_vf"],
+		effect: |s, o, e| _logic(s, o, e, s.registers[o.x] & s.registers[o.y])
+	},
+	OpcodeSpec {
+		pattern: "8XY3", summary: "VX ^= VY", quirks: &["logic_resets_vf"],
+		effect: |s, o, e| _logic(s, o, e, s.registers[o.x] ^ s.registers[o.y])
+	},
+	OpcodeSpec {
+		pattern: "8XY4", summary: "VX += VY, VF = carry", quirks: &[],
+		effect: |s, o, _| {
+			let sum = s.registers[o.x] as u16 + s.registers[o.y] as u16;
+			_with_flag(s, o.x, sum as u8, (sum > 0xFF) as u8)
+		}
+	},
+	OpcodeSpec {
+		pattern: "8XY5", summary: "VX -= VY, VF = no borrow", quirks: &[],
+		effect: |s, o, _| {
+			let (vx, vy) = (s.registers[o.x], s.registers[o.y]);
+			_with_flag(s, o.x, vx.wrapping_sub(vy), (vx >= vy) as u8)
+		}
+	},
+	OpcodeSpec {
+		pattern: "8XY6", summary: "VX = VY >> 1 or VX >> 1, VF = the bit shifted out", quirks: &["shift_uses_vy"],
+		effect: |s, o, e| {
+			let value = s.registers[if e.quirks.shift_uses_vy { o.y } else { o.x }];
+			_with_flag(s, o.x, value >> 1, value & 1)
+		}
+	},
+	OpcodeSpec {
+		pattern: "8XY7", summary: "VX = VY - VX, VF = no borrow", quirks: &[],
+		effect: |s, o, _| {
+			let (vx, vy) = (s.registers[o.x], s.registers[o.y]);
+			_with_flag(s, o.x, vy.wrapping_sub(vx), (vy >= vx) as u8)
+		}
+	},
+	OpcodeSpec {
+		pattern: "8XYE", summary: "VX = VY << 1 or VX << 1, VF = the bit shifted out", quirks: &["shift_uses_vy"],
+		effect: |s, o, e| {
+			let value = s.registers[if e.quirks.shift_uses_vy { o.y } else { o.x }];
+			_with_flag(s, o.x, value << 1, value >> 7)
+		}
+	},
+	OpcodeSpec {
+		pattern: "9XY0", summary: "skip if VX != VY", quirks: &[],
+		effect: |s, o, _| _skip_if(s, s.registers[o.x] != s.registers[o.y])
+	},
+	OpcodeSpec {
+		pattern: "ANNN", summary: "I = NNN", quirks: &[],
+		effect: |s, o, _| {
+			s.index = o.nnn;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "BNNN", summary: "jump to NNN + V0, or XNN + VX", quirks: &["jump_uses_vx"],
+		effect: |s, o, e| {
+			s.pc = o.nnn + s.registers[if e.quirks.jump_uses_vx { o.x } else { 0 }] as u16;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "CXNN", summary: "VX = a random byte & NN", quirks: &[],
+		effect: |s, o, e| {
+			s.registers[o.x] = e.random & o.nn;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "DXYN", summary: "draw N rows of sprite from I at VX, VY, VF = collision",
+		quirks: &["clip_sprites", "display_wait"],
+		effect: |s, o, e| {
+			let (left, top) = (s.registers[o.x] as usize % 64, s.registers[o.y] as usize % 32);
+			let mut collision = 0;
+			for row in 0..o.n {
+				let bits = s.ram[(s.index as usize + row) % RAM_SIZE];
+				for column in 0..8 {
+					let (x, y) = (left + column, top + row);
+					let clipped = e.quirks.clip_sprites && (x >= 64 || y >= 32);
+					if bits << column & 0x80 != 0 && !clipped {
+						collision |= s.gfx[y % 32][x % 64];
+						s.gfx[y % 32][x % 64] ^= 1;
+					}
+				}
+			}
+			s.registers[0xF] = collision;
+			s.waiting_for_vblank = e.quirks.display_wait;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "EX9E", summary: "skip if the key in the low nibble of VX is down", quirks: &[],
+		effect: |s, o, _| _skip_if(s, s.keypad[s.registers[o.x] as usize & 0xF] != 0)
+	},
+	OpcodeSpec {
+		pattern: "EXA1", summary: "skip if the key in the low nibble of VX is up", quirks: &[],
+		effect: |s, o, _| _skip_if(s, s.keypad[s.registers[o.x] as usize & 0xF] == 0)
+	},
+	OpcodeSpec {
+		pattern: "F002", summary: "load the 16 byte audio pattern from I", quirks: &[],
+		effect: |s, _, _| {
+			let mut pattern = [0; PATTERN_BYTES];
+			for (i, byte) in pattern.iter_mut().enumerate() {
+				*byte = s.ram[(s.index as usize + i) % RAM_SIZE];
+			}
+			s.audio_pattern = Some(pattern);
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX07", summary: "VX = delay timer", quirks: &[],
+		effect: |s, o, _| {
+			s.registers[o.x] = s.delay_timer;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX0A", summary: "wait for a key to be pressed and let go of, VX = the key", quirks: &[],
+		effect: |s, o, _| {
+			if !s.waiting_for_key {
+				s.waiting_for_key = true;
+				s.released_key = None;
+			}
+			match s.released_key.take() {
+				Some(key) => {
+					s.registers[o.x] = key;
+					s.waiting_for_key = false;
+				},
+				None => s.pc -= 2
+			}
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX15", summary: "delay timer = VX", quirks: &[],
+		effect: |s, o, _| {
+			s.delay_timer = s.registers[o.x];
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX18", summary: "sound timer = VX", quirks: &[],
+		effect: |s, o, _| {
+			s.sound_timer = s.registers[o.x];
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX1E", summary: "I += VX, without touching VF", quirks: &[],
+		effect: |s, o, _| {
+			s.index = s.index.wrapping_add(s.registers[o.x] as u16);
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX29", summary: "I = the small digit in the low nibble of VX", quirks: &[],
+		effect: |s, o, e| {
+			s.index = e.font_address + (s.registers[o.x] & 0xF) as u16 * SMALL_GLYPH_SIZE as u16;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX30", summary: "I = the large digit in the low nibble of VX", quirks: &[],
+		effect: |s, o, e| {
+			let address = e.large_font_address
+				.ok_or(CpuError::UnknownInstruction(0xF030 | (o.x as u16) << 8))?;
+			s.index = address + (s.registers[o.x] & 0xF) as u16 * LARGE_GLYPH_SIZE as u16;
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX33", summary: "store the decimal digits of VX at I, I + 1 and I + 2", quirks: &[],
+		effect: |s, o, _| {
+			let value = s.registers[o.x];
+			_store(s, &[value / 100, value / 10 % 10, value % 10]);
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX3A", summary: "audio pitch = VX", quirks: &[],
+		effect: |s, o, _| {
+			s.audio_pitch = s.registers[o.x];
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX55", summary: "store V0 to VX at I", quirks: &["load_store_increments_index"],
+		effect: |s, o, e| {
+			let registers = s.registers;
+			_store(s, &registers[..o.x + 1]);
+			if e.quirks.load_store_increments_index {
+				s.index = s.index.wrapping_add(o.x as u16 + 1);
+			}
+			Ok(())
+		}
+	},
+	OpcodeSpec {
+		pattern: "FX65", summary: "load V0 to VX from I", quirks: &["load_store_increments_index"],
+		effect: |s, o, e| {
+			for x in 0..o.x + 1 {
+				s.registers[x] = s.ram[(s.index as usize + x) % RAM_SIZE];
+			}
+			if e.quirks.load_store_increments_index {
+				s.index = s.index.wrapping_add(o.x as u16 + 1);
+			}
+			Ok(())
+		}
+	}
+];
+
+/// The instruction an opcode is, if it is one.
+pub fn lookup(opcode: u16) -> Option<&'static OpcodeSpec> {
+	SPEC.iter().find(|spec| spec.matches(opcode))
+}
+
+/// Runs the instruction at the pc as the spec says, with memory addresses
+/// wrapping. Nothing happens while the machine waits for vertical blank.
+pub fn step(state: &mut Snapshot, environment: &Environment) -> Result<(), CpuError> {
+	if state.waiting_for_vblank {
+		return Ok(());
+	}
+	let pc = state.pc as usize % RAM_SIZE;
+	let opcode = (state.ram[pc] as u16) << 8 | state.ram[(pc + 1) % RAM_SIZE] as u16;
+	let spec = lookup(opcode).ok_or(CpuError::UnknownInstruction(opcode))?;
+	let mut next = state.clone();
+	next.pc = pc as u16 + 2;
+	(spec.effect)(&mut next, Operands::of(opcode), environment)?;
+	*state = next;
+	Ok(())
+}
+
+fn _skip_if(state: &mut Snapshot, condition: bool) -> Result<(), CpuError> {
+	if condition {
+		state.pc += 2;
+	}
+	Ok(())
+}
+
+fn _logic(state: &mut Snapshot, operands: Operands, environment: &Environment, result: u8)
+	-> Result<(), CpuError> {
+	state.registers[operands.x] = result;
+	if environment.quirks.logic_resets_vf {
+		state.registers[0xF] = 0;
+	}
+	Ok(())
+}
+
+/// VF is written after the result, so the flag is what's left when X is F.
+fn _with_flag(state: &mut Snapshot, x: usize, result: u8, flag: u8) -> Result<(), CpuError> {
+	state.registers[x] = result;
+	state.registers[0xF] = flag;
+	Ok(())
+}
+
+fn _store(state: &mut Snapshot, bytes: &[u8]) {
+	for (i, &byte) in bytes.iter().enumerate() {
+		state.ram[(state.index as usize + i) % RAM_SIZE] = byte;
+	}
+}
@@ -0,0 +1,57 @@
+use error::CpuError;
+
+/// Depth of the call stack on most interpreters.
+pub const DEFAULT_STACK_DEPTH : usize = 16;
+/// The original COSMAC VIP interpreter only had room for 12 return
+/// addresses.
+pub const VIP_STACK_DEPTH : usize = 12;
+/// The deepest stack a ROM can ask for.
+pub const MAX_STACK_DEPTH : usize = 256;
+
+/// The call stack used by 2NNN and 00EE, bounded the way the hardware is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stack {
+	entries: Vec<u16>,
+	depth: usize
+}
+
+impl Stack {
+	pub fn new(depth: usize) -> Stack {
+		Stack {
+			entries: Vec::with_capacity(depth),
+			depth
+		}
+	}
+
+	pub fn push(&mut self, address: u16) -> Result<(), CpuError> {
+		if self.entries.len() >= self.depth {
+			return Err(CpuError::StackOverflow(self.depth));
+		}
+		self.entries.push(address);
+		Ok(())
+	}
+
+	pub fn pop(&mut self) -> Result<u16, CpuError> {
+		match self.entries.pop() {
+			Some(address) => Ok(address),
+			None => Err(CpuError::StackUnderflow)
+		}
+	}
+
+	/// Return addresses currently on the stack, oldest first.
+	pub fn entries(&self) -> &[u16] {
+		&self.entries
+	}
+
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+}
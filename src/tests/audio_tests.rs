@@ -0,0 +1,137 @@
+use audio::{Buzzer, BuzzerSettings, Pattern, PatternPlayer, Waveform, WavSink, DEFAULT_PITCH};
+use config::Config;
+use headless;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+
+#[test]
+fn test_buzzer_renders_square_wave() {
+	let mut buzzer = Buzzer::new(BuzzerSettings {
+		frequency: 100.0,
+		volume: 0.5,
+		waveform: Waveform::Square,
+		sample_rate: 400
+	});
+	let mut out = [0.0; 6];
+	buzzer.render(true, &mut out);
+	assert_eq!([0.5, 0.5, -0.5, -0.5, 0.5, 0.5], out);
+	buzzer.render(false, &mut out);
+	assert_eq!([0.0; 6], out);
+}
+
+#[test]
+fn test_timers_count_down_once_per_tick() {
+	let mut chip8 = headless::machine(&[], Config::default(), 0);
+	chip8.cpu.delay_timer = 2;
+	chip8.cpu.sound_timer = 1;
+	chip8.tick();
+	assert_eq!((1, 0), (chip8.cpu.delay_timer, chip8.cpu.sound_timer));
+	chip8.tick();
+	chip8.tick();
+	assert_eq!((0, 0), (chip8.cpu.delay_timer, chip8.cpu.sound_timer));
+}
+
+#[test]
+fn test_sound_timer_renders_to_wav() {
+	let path = env::temp_dir().join("chip8_audio_tests.wav");
+	let path = path.to_str().unwrap();
+	{
+		//10 samples per frame
+		let buzzer = BuzzerSettings { sample_rate: 600, ..BuzzerSettings::default() };
+		let mut chip8 = headless::machine(&[], Config { buzzer, ..Config::default() }, 0);
+		chip8.audio.add_sink(Box::new(WavSink::create(path, 600).unwrap()));
+		chip8.load(&[
+			0x60, 0x03, //V0 = 3
+			0xF0, 0x18  //sound timer = V0
+		]);
+		chip8.cpu.run_cached(2).unwrap();
+		for _ in 0..5 {
+			chip8.tick();
+		}
+	}
+
+	let mut wav = Vec::new();
+	File::open(path).unwrap().read_to_end(&mut wav).unwrap();
+	assert_eq!(b"RIFF", &wav[0..4]);
+	assert_eq!(&[100, 0, 0, 0], &wav[40..44]);
+	let samples = wav[44..].chunks(2)
+		.map(|b| (b[0] as u16 | (b[1] as u16) << 8) as i16)
+		.collect::<Vec<_>>();
+	assert_eq!(50, samples.len());
+	assert!(samples[0..30].iter().all(|&s| s != 0));
+	assert!(samples[30..50].iter().all(|&s| s == 0));
+}
+
+#[test]
+fn test_audio_pattern_and_pitch_0xf002_0xfx3a() {
+	let mut chip8 = headless::machine(&[
+		0xA2, 0x08, //I = 0x208
+		0xF0, 0x02, //load the pattern at I
+		0xF1, 0x3A, //pitch = V1
+		0x12, 0x06,
+		0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00,
+		0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F
+	], Config::default(), 0);
+	chip8.cpu.registers[1] = 112;
+	assert_eq!(None, chip8.cpu.audio_pattern);
+	chip8.cpu.run_cached(3).unwrap();
+	assert_eq!(Some([0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00,
+		0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F]), chip8.cpu.audio_pattern);
+	assert_eq!(112, chip8.cpu.audio_pitch);
+	assert_eq!(8000.0, Pattern { bits: [0; 16], pitch: 112 }.playback_rate());
+}
+
+#[test]
+fn test_pattern_plays_one_bit_per_sample_at_matching_rate() {
+	let mut player = PatternPlayer::new(0.5, 4000);
+	let pattern = Pattern { bits: [0xF0; 16], pitch: DEFAULT_PITCH };
+	let mut out = [0.0; 8];
+	player.render(true, &pattern, &mut out);
+	assert_eq!([0.5, 0.5, 0.5, 0.5, -0.5, -0.5, -0.5, -0.5], out);
+}
+
+#[test]
+fn test_pattern_is_filtered_when_resampled() {
+	//alternating bits at 1.5 bits per sample would alias to full-scale
+	//noise if they were point sampled
+	let mut player = PatternPlayer::new(1.0, 8000 / 3);
+	let pattern = Pattern { bits: [0xAA; 16], pitch: DEFAULT_PITCH };
+	let mut out = [0.0; 200];
+	player.render(true, &pattern, &mut out);
+	assert!(out.iter().all(|s| s.abs() < 0.34), "{:?}", &out[..]);
+}
+
+#[test]
+fn test_audio_pattern_renders_to_wav() {
+	let render = |name: &str| {
+		let path = env::temp_dir().join(name);
+		let path = path.to_str().unwrap().to_string();
+		{
+			let mut chip8 = headless::machine(&[], Config::default(), 0);
+			chip8.audio.add_sink(Box::new(WavSink::create(&path, 44100).unwrap()));
+			chip8.load(&[
+				0xA2, 0x0A, //I = 0x20A
+				0xF0, 0x02, //load the pattern at I
+				0x60, 0x02, //V0 = 2
+				0xF0, 0x18, //sound timer = V0
+				0x12, 0x08,
+				0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0,
+				0x0F, 0xED, 0xCB, 0xA9, 0x87, 0x65, 0x43, 0x21
+			]);
+			chip8.cpu.run_cached(4).unwrap();
+			chip8.tick();
+			chip8.tick();
+			chip8.tick();
+		}
+		let mut wav = Vec::new();
+		File::open(&path).unwrap().read_to_end(&mut wav).unwrap();
+		wav
+	};
+	let first = render("chip8_pattern_tests_1.wav");
+	let second = render("chip8_pattern_tests_2.wav");
+	assert_eq!(44 + 3 * 735 * 2, first.len());
+	assert!(first[44..44 + 2 * 735 * 2].iter().any(|&b| b != 0));
+	assert!(first[44 + 2 * 735 * 2..].iter().all(|&b| b == 0));
+	assert_eq!(first, second);
+}
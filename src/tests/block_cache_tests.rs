@@ -0,0 +1,71 @@
+use config::Config;
+use headless;
+
+const COUNTING_LOOP : [u8; 10] = [
+	0x60, 0x00, //V0 = 0
+	0x70, 0x01, //V0 += 1
+	0x30, 0x0A, //skip if V0 == 10
+	0x12, 0x02, //jump back to the add
+	0x12, 0x08  //halt
+];
+
+#[test]
+fn test_cached_run_matches_interpreter() {
+	let mut interpreted = headless::machine(&COUNTING_LOOP, Config::default(), 0);
+	for _ in 0..40 {
+		interpreted.cpu.emulate_cycle().unwrap();
+	}
+
+	let mut cached = headless::machine(&COUNTING_LOOP, Config::default(), 0);
+	assert_eq!(Ok(40), cached.cpu.run_cached(40));
+
+	assert_eq!(interpreted.cpu.pc, cached.cpu.pc);
+	assert_eq!(interpreted.cpu.registers, cached.cpu.registers);
+	assert_eq!(10, cached.cpu.registers[0]);
+}
+
+#[test]
+fn test_self_modifying_code_invalidates_cache() {
+	let mut chip8 = headless::machine(&[
+		0x60, 0x61, //V0 = 0x61
+		0xA2, 0x08, //I = 0x208
+		0x12, 0x08, //jump to 0x208
+		0x00, 0x00,
+		0x61, 0x01, //0x208: V1 = 1, rewritten to V1 = 2 below
+		0x31, 0x01, //skip if V1 == 1
+		0x12, 0x0C, //halt
+		0x61, 0x02, //V1 = 2
+		0xF1, 0x55, //store V0..V1 at 0x208
+		0x12, 0x08  //jump to 0x208
+	], Config::default(), 0);
+	chip8.cpu.run_cached(100).unwrap();
+	assert_eq!(0x20C, chip8.cpu.pc);
+	assert_eq!(2, chip8.cpu.registers[1]);
+}
+
+#[test]
+fn test_write_memory_invalidates_cache() {
+	let mut chip8 = headless::machine(&[
+		0x60, 0x01, //V0 = 1
+		0x12, 0x00  //jump to start
+	], Config::default(), 0);
+	chip8.cpu.run_cached(2).unwrap();
+	chip8.cpu.write_memory(&[0x60, 0x07], 0x200);
+	chip8.cpu.run_cached(1).unwrap();
+	assert_eq!(7, chip8.cpu.registers[0]);
+}
+
+#[test]
+fn test_block_rewriting_itself_is_decoded_again() {
+	let mut chip8 = headless::machine(&[
+		0xA2, 0x00, //I = 0x200
+		0x60, 0x61, //V0 = 0x61
+		0x61, 0x07, //V1 = 7
+		0xF1, 0x55, //store V0..V1 at 0x200, making the first line V1 = 7
+		0x12, 0x00  //jump to start
+	], Config::default(), 0);
+	assert_eq!(Ok(5), chip8.cpu.run_cached(5));
+	chip8.cpu.registers[1] = 0;
+	assert_eq!(Ok(1), chip8.cpu.run_cached(1));
+	assert_eq!(7, chip8.cpu.registers[1]);
+}
@@ -0,0 +1,80 @@
+use conformance::{Status, Suite};
+use golden;
+use headless;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+//draw the hex digit in VF at the top left, then spin
+fn show_vf(setup: &[u8]) -> Vec<u8> {
+	let mut rom = setup.to_vec();
+	let end = 0x200 + rom.len() as u16 + 6;
+	rom.extend_from_slice(&[0xFF, 0x29, 0x62, 0x00, 0xD2, 0x25, (0x10 | end >> 8) as u8, end as u8]);
+	rom
+}
+
+fn screen_of(rom: &[u8]) -> String {
+	let mut screen = [[0; 64]; 32];
+	headless::run_frames(rom, Default::default(), 5, |gfx| screen = *gfx).unwrap();
+	golden::format_frame(&screen)
+}
+
+#[test]
+fn test_suite_reports_each_profile() {
+	let dir = env::temp_dir().join("chip8_conformance_test");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	let write = |name: &str, data: &[u8]| fs::write(dir.join(name), data).unwrap();
+	//V0 = FF + 1 carries into VF
+	write("carry.ch8", &show_vf(&[0x60, 0xFF, 0x61, 0x01, 0x80, 0x14]));
+	write("carry.pass", screen_of(&show_vf(&[0x6F, 0x01])).as_bytes());
+	write("carry.fail", screen_of(&show_vf(&[0x6F, 0x00])).as_bytes());
+	//V0 = 1 - 2 borrows, so VF is 0 and the known failing screen is shown
+	write("borrow.ch8", &show_vf(&[0x60, 0x01, 0x61, 0x02, 0x80, 0x15]));
+	write("suite.toml", b"[carry]\nrom = \"carry.ch8\"\npass = \"carry.pass\"\nfail = [\"carry.fail\"]\n\
+		profiles = [\"vip\", \"modern\"]\n\n\
+		[borrow]\nrom = \"borrow.ch8\"\npass = \"carry.pass\"\nfail = [\"carry.fail\"]\nprofiles = [\"modern\"]\n\n\
+		[unchecked]\nrom = \"carry.ch8\"\nprofiles = [\"schip\"]\n\n\
+		[absent]\nrom = \"absent.ch8\"\npass = \"carry.pass\"\nprofiles = [\"modern\"]\n\n\
+		[absent.modern]\nframes = 10\n");
+
+	let suite = Suite::load(dir.to_str().unwrap()).unwrap();
+	assert_eq!(4, suite.tests.len());
+	let diffs = dir.join("diffs");
+	let results = suite.run(diffs.to_str().unwrap());
+	let statuses : Vec<(&str, &str, &Status)> = results.iter()
+		.map(|(test, profile, status)| (test.as_str(), profile.as_str(), status)).collect();
+	assert_eq!(("borrow", "modern", &Status::Failed("carry.fail".to_string())), statuses[1]);
+	assert_eq!(("carry", "vip", &Status::Passed), statuses[2]);
+	assert_eq!(("carry", "modern", &Status::Passed), statuses[3]);
+	match statuses[0] {
+		("absent", "modern", &Status::Missing(_)) => (),
+		_ => panic!("{:?}", statuses[0])
+	}
+	match statuses[4] {
+		("unchecked", "schip", &Status::Missing(_)) => (),
+		_ => panic!("{:?}", statuses[4])
+	}
+	assert!(Path::new(&diffs.join("unchecked-schip.screen")).exists());
+}
+
+#[test]
+fn test_suite_rejects_unknown_profiles() {
+	let dir = env::temp_dir().join("chip8_conformance_profiles_test");
+	fs::create_dir_all(&dir).unwrap();
+	fs::write(dir.join("suite.toml"), b"[test]\nrom = \"a.ch8\"\nprofiles = [\"chip-9\"]\n").unwrap();
+	assert!(Suite::load(dir.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn test_shipped_suite_has_readable_pass_screens() {
+	let suite = Suite::load("conformance").unwrap();
+	let passes : Vec<&str> = suite.tests.iter()
+		.filter_map(|test| test.settings.get("pass").and_then(|value| value.as_str())).collect();
+	assert!(passes.contains(&"ibm-logo.pass"));
+	for pass in passes {
+		let text = fs::read_to_string(Path::new("conformance").join(pass)).unwrap();
+		let screen = golden::parse_frame(&text).unwrap();
+		assert!(screen.iter().any(|row| row.iter().any(|&pixel| pixel != 0)), "{}", pass);
+	}
+}
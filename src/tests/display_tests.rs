@@ -0,0 +1,116 @@
+use display::{Effects, Filter, Presentation, Renderer, Scaling, SCANLINE_LEVEL};
+use headless;
+use palette::Palette;
+use png;
+
+#[test]
+fn test_fit_keeps_aspect() {
+	assert_eq!([1.0, 2.0 / 3.0], Scaling::Fit.extent(1024, 768));
+	assert_eq!([0.5, 1.0], Scaling::Fit.extent(1024, 256));
+	assert_eq!([1.0, 1.0], Scaling::Stretch.extent(1024, 768));
+}
+
+#[test]
+fn test_integer_scaling() {
+	//1000 / 64 = 15.6, so the screen is 15 * 64 = 960 pixels wide
+	assert_eq!([960.0 / 1000.0, 480.0 / 1000.0], Scaling::Integer.extent(1000, 1000));
+	//too small a window still shows one window pixel per screen pixel
+	assert_eq!([2.0, 2.0], Scaling::Integer.extent(32, 16));
+	assert_eq!(Some(Scaling::Integer), Scaling::by_name("integer"));
+}
+
+#[test]
+fn test_phosphor_fades() {
+	let mut renderer = Renderer::new(Palette::default(), Effects {
+		persistence: 0.5,
+		..Default::default()
+	});
+	let mut gfx = [[0; 64]; 32];
+	gfx[0][0] = 1;
+	assert_eq!([1.0; 3], renderer.frame(&gfx)[0]);
+	gfx[0][0] = 0;
+	assert_eq!([0.5; 3], renderer.frame(&gfx)[0]);
+	assert_eq!([0.25; 3], renderer.frame(&gfx)[0]);
+	//without persistence pixels go dark at once
+	renderer.effects.persistence = 0.0;
+	assert_eq!([0.0; 3], renderer.frame(&gfx)[0]);
+}
+
+#[test]
+fn test_render_image_with_scanlines() {
+	let mut renderer = Renderer::new(Palette::default(), Effects {
+		filter: Filter::Scanlines,
+		..Default::default()
+	});
+	let mut gfx = [[0; 64]; 32];
+	gfx[0][1] = 1;
+	let image = renderer.render_image(&gfx, 2);
+	assert_eq!((128, 64), (image.width, image.height));
+	let pixel = |x: usize, y: usize| &image.data[(y * 128 + x) * 3..(y * 128 + x) * 3 + 3];
+	assert_eq!(&[0, 0, 0], pixel(0, 0));
+	assert_eq!(&[255, 255, 255], pixel(2, 0));
+	let dim = (255.0 * SCANLINE_LEVEL).round() as u8;
+	assert_eq!(&[dim, dim, dim], pixel(3, 1));
+	//the grille keeps one channel in every three columns at full strength
+	assert_eq!([1.0, 0.7, 0.7], Filter::Crt.shade(0, 0.0));
+	assert_eq!([0.7 * 0.6, 0.6, 0.7 * 0.6], Filter::Crt.shade(4, 0.5));
+}
+
+#[test]
+fn test_or_shows_both_frames() {
+	let mut renderer = Renderer::new(Palette::default(), Effects {
+		presentation: Presentation::Or,
+		..Default::default()
+	});
+	let (mut erased, mut drawn) = ([[0; 64]; 32], [[0; 64]; 32]);
+	erased[0][0] = 1;
+	drawn[0][1] = 1;
+	renderer.frame(&erased);
+	let colors = renderer.frame(&drawn);
+	assert_eq!([1.0; 3], colors[0]);
+	assert_eq!([1.0; 3], colors[1]);
+	assert_eq!([0.0; 3], renderer.frame(&drawn)[0]);
+	assert!(renderer.effects.at_vblank());
+	assert!(!Effects::default().at_vblank());
+}
+
+#[test]
+fn test_blend_mixes_frames() {
+	let mut renderer = Renderer::new(Palette::default(), Effects {
+		presentation: Presentation::Blend(0.75),
+		..Default::default()
+	});
+	let mut gfx = [[0; 64]; 32];
+	gfx[0][0] = 1;
+	assert_eq!([0.75; 3], renderer.frame(&gfx)[0]);
+	assert_eq!([1.0; 3], renderer.frame(&gfx)[0]);
+	gfx[0][0] = 0;
+	assert_eq!([0.25; 3], renderer.frame(&gfx)[0]);
+	assert_eq!([0.0; 3], renderer.frame(&gfx)[0]);
+	assert_eq!(Some(Presentation::Blend(0.5)), Presentation::by_name("blend"));
+}
+
+#[test]
+fn test_png_encoding() {
+	let mut renderer = Renderer::new(Palette::default(), Effects::default());
+	let data = png::encode(&renderer.render_image(&[[1; 64]; 32], 1));
+	assert_eq!(b"\x89PNG\r\n\x1a\n", &data[0..8]);
+	assert_eq!(b"IHDR", &data[12..16]);
+	assert_eq!(&[0, 0, 0, 64, 0, 0, 0, 32, 8, 2], &data[16..26]);
+	assert_eq!(b"IEND", &data[data.len() - 8..data.len() - 4]);
+}
+
+#[test]
+fn test_headless_runs_are_repeatable() {
+	//draw a random sprite at a random place forever
+	let rom = [0xC0, 0x3F, 0xC1, 0x1F, 0xD0, 0x15, 0x12, 0x00];
+	let run = || {
+		let mut frames = Vec::new();
+		headless::run_frames(&rom, Default::default(), 10, |gfx| frames.push(*gfx)).unwrap();
+		frames
+	};
+	let frames = run();
+	assert_eq!(10, frames.len());
+	assert!(frames[9].iter().any(|row| row.iter().any(|&p| p != 0)));
+	assert!(frames == run());
+}
@@ -0,0 +1,50 @@
+use config::Config;
+use headless;
+use font::{Font, FONT_ADDRESS_COMMON};
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+#[test]
+fn test_font_placed_at_configured_address() {
+	let mut chip8 = headless::machine(&[0xF0, 0x29], Config {
+		font: Font::cosmac_vip(),
+		font_address: FONT_ADDRESS_COMMON,
+		..Config::default()
+	}, 0);
+	chip8.cpu.registers[0] = 4;
+	chip8.cpu.step().unwrap();
+	assert_eq!(0x50 + 4 * 5, chip8.cpu.index);
+	assert_eq!(&[0xA0, 0xA0, 0xF0, 0x20, 0x20], chip8.cpu.read_memory(0x64, 0x69));
+	assert_eq!(&[0; 5], chip8.cpu.read_memory(0, 5));
+}
+
+#[test]
+fn test_large_font_follows_small_font_0xfx30() {
+	let mut chip8 = headless::machine(&[0xF0, 0x30], Config { font: Font::schip(), ..Config::default() }, 0);
+	chip8.cpu.registers[0] = 2;
+	chip8.cpu.step().unwrap();
+	assert_eq!(80 + 2 * 10, chip8.cpu.index);
+	assert_eq!(&[0x3E, 0x7F], chip8.cpu.read_memory(100, 102));
+}
+
+#[test]
+fn test_custom_font_from_file() {
+	let path = env::temp_dir().join("chip8_font_tests.bin");
+	let glyphs = (0..80).collect::<Vec<u8>>();
+	File::create(&path).unwrap().write_all(&glyphs).unwrap();
+
+	let font = Font::from_file(path.to_str().unwrap()).unwrap();
+	assert_eq!(glyphs, font.small);
+	assert_eq!(None, font.large);
+	assert!(Font::from_bytes(&[0; 81]).is_err());
+}
+
+#[test]
+fn test_font_has_to_fit_below_the_program() {
+	assert!(Font::cosmac_vip().check_address(FONT_ADDRESS_COMMON).is_ok());
+	assert!(Font::cosmac_vip().check_address(0x200 - 80).is_ok());
+	assert!(Font::cosmac_vip().check_address(0x200 - 79).is_err());
+	assert!(Font::schip().check_address(0x1A0).is_err());
+	assert!(Font::cosmac_vip().check_address(0xFFFF).is_err());
+}
@@ -0,0 +1,119 @@
+use config::Config;
+use headless;
+use instruction::Instruction;
+use quirks::Quirks;
+use timing;
+use timing::{Timing, VIP_FETCH_CYCLES, VIP_PROGRAM_CYCLES};
+use tests::machine_with_screen;
+use vm::GFX;
+
+//draws the 0 glyph over and over
+const DRAW_LOOP : [u8; 6] = [0xA0, 0x00, 0xD0, 0x05, 0x12, 0x02];
+
+#[test]
+fn test_clock_speed_is_spread_over_frames() {
+	let config = Config { clock_speed: 100, ..Config::default() };
+	let mut chip8 = headless::machine(&[0x12, 0x00], config, 0);
+	let mut total = 0;
+	for _ in 0..60 {
+		let executed = chip8.run_frame().unwrap();
+		assert!(executed == 1 || executed == 2);
+		total += executed;
+	}
+	assert_eq!(100, total);
+}
+
+#[test]
+fn test_every_draw_is_sent() {
+	let config = Config { clock_speed: 600, ..Config::default() };
+	let (mut chip8, gfx) = machine_with_screen(&DRAW_LOOP, config);
+	assert_eq!(10, chip8.run_frame().unwrap());
+	//the first draw and then every other instruction
+	assert_eq!(5, gfx.try_iter().count());
+	assert!(!chip8.cpu.waiting_for_vblank());
+}
+
+#[test]
+fn test_vblank_presentation_sends_one_screen_a_frame() {
+	let config = Config { clock_speed: 600, present_at_vblank: true, ..Config::default() };
+	let (mut chip8, gfx) = machine_with_screen(&DRAW_LOOP, config);
+	assert_eq!(10, chip8.run_frame().unwrap());
+	let frames : Vec<GFX> = gfx.try_iter().collect();
+	assert_eq!(1, frames.len());
+	assert_eq!(chip8.cpu.gfx, frames[0]);
+	//it's sent even when nothing was drawn, so fading can go on
+	let config = Config { present_at_vblank: true, ..Config::default() };
+	let (mut chip8, gfx) = machine_with_screen(&[0x12, 0x00], config);
+	chip8.run_frame().unwrap();
+	chip8.run_frame().unwrap();
+	assert_eq!(2, gfx.try_iter().count());
+}
+
+#[test]
+fn test_display_wait_allows_one_draw_a_frame() {
+	let config = Config {
+		clock_speed: 600,
+		quirks: Quirks { display_wait: true, ..Quirks::modern() },
+		..Config::default()
+	};
+	let (mut chip8, gfx) = machine_with_screen(&DRAW_LOOP, config);
+	assert_eq!(2, chip8.run_frame().unwrap());
+	assert_eq!(0x204, chip8.cpu.pc);
+	assert_eq!(1, gfx.try_iter().count());
+	//the next frame goes on from the jump
+	assert_eq!(2, chip8.run_frame().unwrap());
+	assert_eq!(0x204, chip8.cpu.pc);
+	assert_eq!(1, gfx.try_iter().count());
+	assert!(Quirks::vip().display_wait);
+}
+
+#[test]
+fn test_display_wait_stops_cached_runs() {
+	let config = Config {
+		quirks: Quirks { display_wait: true, ..Quirks::modern() },
+		..Config::default()
+	};
+	let mut chip8 = headless::machine(&DRAW_LOOP, config, 0);
+	assert_eq!(2, chip8.cpu.run_cached(100).unwrap());
+	assert_eq!(0, chip8.cpu.run_recompiled(100).unwrap());
+	chip8.tick();
+	assert_eq!(2, chip8.cpu.run_recompiled(100).unwrap());
+}
+
+#[test]
+fn test_vip_draw_cost_depends_on_alignment() {
+	let mut registers = [0; 16];
+	let draw = Instruction::Draw(0, 1, 5);
+	let aligned = timing::vip_cycles(draw, &registers, false);
+	registers[0] = 3;
+	assert!(timing::vip_cycles(draw, &registers, false) > aligned);
+	//rows off the bottom of the screen aren't drawn
+	registers[1] = 30;
+	assert!(timing::vip_cycles(draw, &registers, false) < aligned);
+	let skip = Instruction::SkipEqualByte(0, 3);
+	assert!(timing::vip_cycles(skip, &registers, true) > timing::vip_cycles(skip, &registers, false));
+}
+
+#[test]
+fn test_vip_timing_fills_frames_with_cycles() {
+	let config = Config { timing: Timing::Vip, ..Config::default() };
+	//V0 = 0, then add one to it forever
+	let mut chip8 = headless::machine(&[0x60, 0x00, 0x70, 0x01, 0x12, 0x02], config, 0);
+	//6XNN costs 46 cycles, then every 7XNN and 1NNN pair 102, so 25 pairs
+	//leave 2 cycles to spare and one more 7XNN uses them up
+	assert_eq!(VIP_PROGRAM_CYCLES, 46 + 25 * 102 + 2);
+	assert_eq!(1 + 25 * 2 + 1, chip8.run_frame().unwrap());
+	//the clock speed doesn't come into it
+	assert_eq!(VIP_FETCH_CYCLES + 10, timing::vip_cycles(Instruction::AddByte(0, 1), &[0; 16], false));
+}
+
+#[test]
+fn test_vip_clear_screen_runs_into_next_frame() {
+	let config = Config { timing: Timing::Vip, ..Config::default() };
+	let mut chip8 = headless::machine(&[0x00, 0xE0, 0x00, 0xE0, 0x12, 0x04], config, 0);
+	assert_eq!(1, chip8.run_frame().unwrap());
+	assert_eq!(1, chip8.run_frame().unwrap());
+	assert_eq!(0x204, chip8.cpu.pc);
+	let executed = chip8.run_frame().unwrap();
+	assert!(executed > 1 && executed < 100);
+}
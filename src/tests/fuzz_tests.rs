@@ -0,0 +1,60 @@
+use config::Config;
+use error::CpuError;
+use fuzz;
+use fuzz::Fuzzer;
+use headless;
+use headless::InputScript;
+use lockstep::Core;
+
+fn fuzzer(spec: &str) -> Fuzzer {
+	let config = Config {
+		clock_speed: fuzz::DEFAULT_FUZZ_CLOCK_SPEED,
+		..Config::default()
+	};
+	Fuzzer::new(Core::parse(spec, &config).unwrap(), 1)
+}
+
+#[test]
+fn test_fuzzing_bundled_games_finds_no_crashes() {
+	for spec in &["@vip", "@schip", "@xochip", "recompiled@modern"] {
+		let mut fuzzer = fuzzer(spec);
+		fuzzer.frames = 10;
+		assert_eq!(Ok(23), fuzzer.load_corpus("games"));
+		let report = fuzzer.run(300, None).unwrap();
+		assert_eq!(300, report.runs);
+		assert!(report.crashes.is_empty(), "{}: {:?}", spec, report.crashes);
+		assert!(report.corpus > 23, "{}: no mutation covered anything new", spec);
+	}
+}
+
+#[test]
+fn test_fuzzer_reports_panics() {
+	let mut fuzzer = fuzzer("interpreter");
+	//a CPU can't be made with a clock period of 1/0 seconds
+	fuzzer.core.config.clock_speed = 0;
+	let (result, _) = fuzzer.execute(&[0x12, 0x00], &InputScript::new());
+	assert!(result.unwrap_err().starts_with("panicked: "));
+}
+
+#[test]
+fn test_unknown_instruction_is_a_fault() {
+	let result = headless::run_frames(&[0x60, 0x01, 0x50, 0x01], Config::default(), 3, |_| ());
+	assert_eq!(Err(CpuError::UnknownInstruction(0x5001)), result);
+}
+
+#[test]
+fn test_index_and_key_arithmetic_does_not_overflow() {
+	let program = [
+		0x60, 0xFF, //V0 = 0xFF
+		0xF0, 0x1E, //I += V0, forever
+		0xE0, 0x9E, //skip if the key in V0's low nibble is down
+		0xF0, 0x65, //load V0 from I
+		0x60, 0xFF, //V0 = 0xFF
+		0x12, 0x02  //again
+	];
+	let config = Config {
+		clock_speed: 60 * 1000,
+		..Config::default()
+	};
+	assert_eq!(Ok(()), headless::run_frames(&program, config, 10, |_| ()));
+}
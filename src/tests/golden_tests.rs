@@ -0,0 +1,108 @@
+use error::CpuError;
+use golden;
+use golden::Outcome;
+use headless::InputScript;
+use rom_db::RomDatabase;
+use vm::Key;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_bundled_games_match_golden_frames() {
+	let db = RomDatabase::load("roms.toml").unwrap();
+	let diffs = env::temp_dir().join("chip8_golden_diffs");
+	let outcomes = golden::check("games", "golden", diffs.to_str().unwrap(), &db, false).unwrap();
+	assert_eq!(23, outcomes.len());
+	let failures : Vec<_> = outcomes.iter().filter(|(_, _, outcome)| *outcome != Outcome::Matched)
+		.collect();
+	assert!(failures.is_empty(), "run with --golden --bless if these changes are right: {:?}", failures);
+}
+
+#[test]
+fn test_frame_text_round_trip() {
+	let mut gfx = [[0; 64]; 32];
+	gfx[0][0] = 1;
+	gfx[31][63] = 1;
+	let text = golden::format_frame(&gfx);
+	assert!(text.starts_with("#..."));
+	assert_eq!(Ok(gfx), golden::parse_frame(&text));
+	assert!(golden::parse_frame("#.\n").is_err());
+}
+
+#[test]
+fn test_diff_marks_changed_pixels() {
+	let expected = [[0; 64]; 32];
+	let mut actual = expected;
+	assert_eq!(None, golden::diff(&expected, &actual));
+	actual[1][2] = 1;
+	let (pixels, text) = golden::diff(&expected, &actual).unwrap();
+	assert_eq!(1, pixels);
+	let lines : Vec<&str> = text.lines().collect();
+	assert!(lines[0].starts_with("1 pixels differ"));
+	assert_eq!("..+.", &lines[2][0..4]);
+	let (_, text) = golden::diff(&actual, &expected).unwrap();
+	assert_eq!("..-.", &text.lines().nth(2).unwrap()[0..4]);
+}
+
+#[test]
+fn test_input_script() {
+	let script = InputScript::parse("frames 90 # a short one\n\n0 press 5\n30 release a\n").unwrap();
+	assert_eq!(Some(90), script.frames);
+	assert_eq!(vec![(0, Key::K5, true), (30, Key::A, false)], script.events);
+	assert!(InputScript::parse("30 press 5\n0 release 5").is_err());
+	assert!(InputScript::parse("0 hold 5").is_err());
+	assert!(InputScript::parse("0 press 10").is_err());
+}
+
+#[test]
+fn test_scripted_keys_reach_the_program() {
+	//V0 = 5, wait for it to be pressed, then draw the 0 glyph
+	let rom = [0x60, 0x05, 0xE0, 0x9E, 0x12, 0x02, 0xD0, 0x05, 0x12, 0x08];
+	let db = RomDatabase::new();
+	let mut script = InputScript::parse("frames 10").unwrap();
+	assert_eq!([[0; 64]; 32], golden::run(&rom, &db, &script).0);
+	script.events.push((5, Key::K5, true));
+	assert!(golden::run(&rom, &db, &script).0 != [[0; 64]; 32]);
+}
+
+#[test]
+fn test_faults_are_part_of_golden_files() {
+	//00EE with nothing on the stack
+	let (gfx, fault) = golden::run(&[0x00, 0xEE], &RomDatabase::new(), &InputScript::new());
+	assert_eq!(Some(CpuError::StackUnderflow), fault);
+	let text = golden::format_golden(&gfx, fault.as_ref());
+	assert!(text.starts_with("fault: StackUnderflow\n"));
+	assert_eq!(Ok(("fault: StackUnderflow".to_string(), gfx)), golden::parse_golden(&text));
+	let text = golden::format_golden(&gfx, None);
+	assert_eq!(Ok(("fault: none".to_string(), gfx)), golden::parse_golden(&text));
+	assert!(golden::parse_golden(&golden::format_frame(&gfx)).is_err());
+
+	let games = env::temp_dir().join("chip8_golden_fault_games");
+	let frames = env::temp_dir().join("chip8_golden_fault_frames");
+	let _ = fs::remove_dir_all(&games);
+	let _ = fs::remove_dir_all(&frames);
+	fs::create_dir_all(&games).unwrap();
+	fs::create_dir_all(&frames).unwrap();
+	fs::write(games.join("UNDERFLOW"), [0x00, 0xEE]).unwrap();
+	fs::write(frames.join("UNDERFLOW.txt"), golden::format_golden(&gfx, None)).unwrap();
+	let outcomes = golden::check(games.to_str().unwrap(), frames.to_str().unwrap(),
+		frames.to_str().unwrap(), &RomDatabase::new(), false).unwrap();
+	assert_eq!(vec![("UNDERFLOW".to_string(), Some(CpuError::StackUnderflow),
+		Outcome::FaultDiffered("fault: none".to_string(), "fault: StackUnderflow".to_string()))], outcomes);
+	fs::remove_dir_all(&games).unwrap();
+	fs::remove_dir_all(&frames).unwrap();
+}
+
+#[test]
+fn test_scripted_keys_complete_key_waits() {
+	//wait for a key into V3, then draw its glyph and spin
+	let rom = [0xF3, 0x0A, 0xF3, 0x29, 0xD0, 0x05, 0x12, 0x06];
+	let seven = [0x63, 0x07, 0xF3, 0x29, 0xD0, 0x05, 0x12, 0x06];
+	let db = RomDatabase::new();
+	let (expected, _) = golden::run(&seven, &db, &InputScript::parse("frames 5").unwrap());
+	//still waiting while the key is held
+	let script = InputScript::parse("frames 8\n5 press 7\n").unwrap();
+	assert_eq!(([[0; 64]; 32], None), golden::run(&rom, &db, &script));
+	let script = InputScript::parse("frames 12\n5 press 7\n8 release 7\n").unwrap();
+	assert_eq!((expected, None), golden::run(&rom, &db, &script));
+}
@@ -0,0 +1,127 @@
+use config::Config;
+use config_file::ConfigFile;
+use gym::{Encoding, Env, Observation, RewardHook};
+use rom_db::RomDatabase;
+use sha1;
+use tests::read_game;
+
+fn make_env() -> Env {
+	Env::new(Config::default(), RomDatabase::load("roms.toml").unwrap())
+}
+
+//keeps the BRIX paddle moving left and right so the ball gets somewhere
+fn action(step: u32) -> u16 {
+	if (step / 8).is_multiple_of(2) { 1 << 4 } else { 1 << 6 }
+}
+
+#[test]
+fn test_episodes_are_deterministic() {
+	let brix = read_game("BRIX");
+	let (mut a, mut b) = (make_env(), make_env());
+	assert_eq!(a.reset(&brix, 7).bytes(), b.reset(&brix, 7).bytes());
+	for step in 0..100 {
+		let (seen_a, info_a) = a.step(action(step), 4);
+		let (seen_b, info_b) = b.step(action(step), 4);
+		assert_eq!(seen_a.bits(), seen_b.bits());
+		assert_eq!(info_a, info_b);
+	}
+	assert_eq!(a.ram(), b.ram());
+}
+
+#[test]
+fn test_restored_state_replays_the_same_steps() {
+	let mut env = make_env();
+	env.reset(&read_game("BRIX"), 3);
+	for step in 0..30 {
+		env.step(action(step), 4);
+	}
+	let state = env.clone_state();
+	let first : Vec<_> = (0..50).map(|step| env.step(action(step), 4)).map(|(seen, info)| (seen.bytes(), info)).collect();
+	env.restore_state(&state);
+	let again : Vec<_> = (0..50).map(|step| env.step(action(step), 4)).map(|(seen, info)| (seen.bytes(), info)).collect();
+	assert!(first == again);
+}
+
+#[test]
+fn test_brix_score_is_rewarded() {
+	let mut env = make_env();
+	env.reset(&read_game("BRIX"), 0);
+	assert_eq!(1, env.hooks().len());
+	let mut total = 0;
+	for step in 0..300 {
+		let (_, info) = env.step(action(step), 4);
+		assert!(!info.done);
+		total += info.reward;
+		assert_eq!(vec![("score".to_string(), total)], info.values);
+	}
+	assert!(total > 0);
+}
+
+#[test]
+fn test_observation_encodings() {
+	let mut screen = [[0; 64]; 32];
+	screen[0][0] = 1;
+	screen[0][9] = 1;
+	screen[31][63] = 1;
+	let seen = Observation { screen };
+	let bytes = seen.bytes();
+	assert_eq!(64 * 32, bytes.len());
+	assert_eq!(3, bytes.iter().filter(|&&pixel| pixel == 1).count());
+	assert_eq!(1, bytes[9]);
+	let bits = seen.bits();
+	assert_eq!(256, bits.len());
+	assert_eq!(&[0x80, 0x40, 0], &bits[0..3]);
+	assert_eq!(0x01, bits[255]);
+}
+
+#[test]
+fn test_reward_hooks_in_database() {
+	let file = ConfigFile::parse("[ABCDEF]\n\
+		[ABCDEF.rewards.score]\n\
+		address = 0x300\n\
+		digits = 3\n\
+		[ABCDEF.rewards.lives]\n\
+		address = 0x310\n\
+		bytes = 2\n\
+		weight = -100\n").unwrap();
+	let db = RomDatabase::from_config(&file).unwrap();
+	let rewards = &db.get("abcdef").unwrap().rewards;
+	assert_eq!(2, rewards.len());
+	let score = rewards.iter().find(|hook| hook.name == "score").unwrap();
+	let lives = rewards.iter().find(|hook| hook.name == "lives").unwrap();
+	assert_eq!(RewardHook { name: "score".to_string(), address: 0x300, encoding: Encoding::Bcd(3), weight: 1 }, *score);
+	assert_eq!(Encoding::Binary(2), lives.encoding);
+	assert_eq!(-100, lives.weight);
+
+	let mut ram = vec![0; 0x1000];
+	ram[0x300..0x303].copy_from_slice(&[1, 2, 5]);
+	ram[0x310..0x312].copy_from_slice(&[0x01, 0x02]);
+	assert_eq!(125, score.read(&ram));
+	assert_eq!(0x102, lives.read(&ram));
+
+	for table in ["digits = 3", "address = 0x1000", "address = 0xFFF\ndigits = 2",
+		"address = 0\ndigits = 2\nbytes = 2", "address = 0\nbytes = 0", "address = 0\nscale = 2"].iter() {
+		let file = ConfigFile::parse(&format!("[ABCDEF]\n[ABCDEF.rewards.score]\n{}\n", table)).unwrap();
+		assert!(RomDatabase::from_config(&file).is_err(), "{}", table);
+	}
+}
+
+#[test]
+fn test_huge_rewards_saturate() {
+	//fill 0x300-0x311 with FF, which isn't a BCD digit, then spin
+	let mut rom = vec![0xA3, 0x00];
+	for x in 0..9 {
+		rom.extend_from_slice(&[0x60 | x, 0xFF]);
+	}
+	rom.extend_from_slice(&[0xF8, 0x55, 0xA3, 0x09, 0xF8, 0x55, 0x12, 0x1A]);
+	let file = ConfigFile::parse(&format!("[{}]\n\
+		[{}.rewards.score]\n\
+		address = 0x300\n\
+		digits = 18\n\
+		weight = {}\n", sha1::hex_digest(&rom), sha1::hex_digest(&rom), i64::MAX)).unwrap();
+	let mut env = Env::new(Config::default(), RomDatabase::from_config(&file).unwrap());
+	env.reset(&rom, 0);
+	let (_, info) = env.step(0, 10);
+	assert_eq!(vec![("score".to_string(), 999_999_999_999_999_999)], info.values);
+	assert_eq!(i64::MAX, info.reward);
+}
@@ -0,0 +1,75 @@
+use config_file::{ConfigFile, Value};
+use keymap::Keymap;
+use vm::Key;
+
+#[test]
+fn test_config_file_values() {
+	let file = ConfigFile::parse("title = \"Pong # 2\" # comment\n\
+		[game]\n\
+		speed = 0x10\n\
+		quirks = [true, -3, \"a\"]\n").unwrap();
+	assert_eq!(file.get("", "title"), Some(&Value::String("Pong # 2".to_string())));
+	assert_eq!(file.get("game", "speed").and_then(|v| v.as_integer()), Some(16));
+	assert_eq!(file.get("game", "quirks"), Some(&Value::Array(vec![
+		Value::Boolean(true), Value::Integer(-3), Value::String("a".to_string())
+	])));
+}
+
+#[test]
+fn test_config_file_errors() {
+	assert!(ConfigFile::parse("[keys\nA = \"1\"").is_err());
+	assert!(ConfigFile::parse("A \"1\"").is_err());
+	assert!(ConfigFile::parse("A = \"1").is_err());
+	assert!(ConfigFile::parse("A = 1 2").is_err());
+}
+
+#[test]
+fn test_presets_cover_keypad() {
+	for name in ["qwerty", "azerty", "dvorak", "hex"].iter() {
+		let keymap = Keymap::by_name(name).unwrap();
+		let names = ["Key0", "Key1", "Key2", "Key3", "Key4", "Key5", "Key6", "Key7", "Key8", "Key9",
+			"A", "B", "C", "D", "E", "F", "Q", "W", "R", "S", "V", "X", "Z", "O", "U", "P", "J", "K",
+			"Comma", "Period", "Apostrophe", "Semicolon"];
+		let mut seen = [false; 16];
+		for n in names.iter() {
+			if let Some(key) = keymap.get(n) {
+				seen[key.to_byte() as usize] = true;
+			}
+		}
+		assert!(seen.iter().all(|&s| s), "{} doesn't reach every key", name);
+	}
+	let qwerty = Keymap::qwerty();
+	assert_eq!(qwerty.get("Key4"), Some(Key::C));
+	assert_eq!(qwerty.get("X"), Some(Key::K0));
+	assert_eq!(qwerty.get("V"), Some(Key::F));
+	assert_eq!(Keymap::azerty().get("Z"), Some(Key::K5));
+}
+
+#[test]
+fn test_rom_overrides() {
+	let file = ConfigFile::parse("preset = \"azerty\"\n\
+		[keys]\n\
+		Space = \"5\"\n\
+		[roms.PONG]\n\
+		Up = 1\n\
+		A = \"\"\n\
+		[roms.TETRIS]\n\
+		preset = \"hex\"\n").unwrap();
+
+	let base = Keymap::from_config(&file, None, None).unwrap();
+	assert_eq!(base.get("Space"), Some(Key::K5));
+	assert_eq!(base.get("A"), Some(Key::K4));
+	assert_eq!(base.get("Up"), None);
+
+	let pong = Keymap::from_config(&file, Some("PONG"), None).unwrap();
+	assert_eq!(pong.get("Up"), Some(Key::K1));
+	assert_eq!(pong.get("A"), None);
+	assert_eq!(pong.get("Space"), Some(Key::K5));
+
+	let tetris = Keymap::from_config(&file, Some("TETRIS"), None).unwrap();
+	assert_eq!(tetris.get("A"), Some(Key::A));
+	assert_eq!(tetris.get("Space"), None);
+
+	let bad = ConfigFile::parse("[keys]\nQ = \"G\"").unwrap();
+	assert!(Keymap::from_config(&bad, None, None).is_err());
+}
@@ -0,0 +1,85 @@
+use library::{label_glyph, render_thumbnail, Library, RecentGames, MAX_RECENT_GAMES};
+use rom_db::RomDatabase;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_thumbnail_shows_screen() {
+	//A000 D015 1204: draw the 0 glyph at the top left and spin
+	let gfx = render_thumbnail(&[0xA0, 0x00, 0xD0, 0x15, 0x12, 0x04], None, 2);
+	assert!(gfx.iter().any(|line| line.iter().any(|&pixel| pixel != 0)));
+	assert_eq!(gfx, render_thumbnail(&[0xA0, 0x00, 0xD0, 0x15, 0x12, 0x04], None, 2));
+}
+
+#[test]
+fn test_scan_bundled_games() {
+	let db = RomDatabase::load("roms.toml").unwrap();
+	let library = Library::scan("games", &db).unwrap();
+	assert_eq!(23, library.entries.len());
+	assert!(library.entries.iter().any(|entry| entry.title == "Space Invaders"));
+	let titles : Vec<_> = library.entries.iter().map(|entry| entry.title.clone()).collect();
+	let mut sorted = titles.clone();
+	sorted.sort();
+	assert_eq!(sorted, titles);
+}
+
+#[test]
+fn test_scan_skips_bad_files() {
+	let dir = env::temp_dir().join("chip8_library_scan_test");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(dir.join("subdir")).unwrap();
+	fs::write(dir.join("GOOD"), [0x12, 0x00]).unwrap();
+	fs::write(dir.join("EMPTY"), []).unwrap();
+	fs::write(dir.join("HUGE"), vec![0; 4096]).unwrap();
+	fs::write(dir.join("LOCKED"), [0x12, 0x00]).unwrap();
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		fs::set_permissions(dir.join("LOCKED"), fs::Permissions::from_mode(0o000)).unwrap();
+	}
+
+	let library = Library::scan(dir.to_str().unwrap(), &RomDatabase::new()).unwrap();
+	assert!(library.entries.iter().any(|entry| entry.title == "GOOD"));
+	assert!(library.entries.iter().all(|entry| entry.title == "GOOD" || entry.title == "LOCKED"));
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_label_glyphs() {
+	let db = RomDatabase::load("roms.toml").unwrap();
+	let library = Library::scan("games", &db).unwrap();
+	let unknown = label_glyph('~');
+	for entry in &library.entries {
+		assert!(entry.title.chars().all(|c| label_glyph(c) != unknown), "{}", entry.title);
+	}
+	assert_eq!(label_glyph('a'), label_glyph('A'));
+	assert_eq!([0; 5], label_glyph(' '));
+}
+
+#[test]
+fn test_recent_games() {
+	let file = env::temp_dir().join("chip8_recent_games_test");
+	let file = file.to_str().unwrap();
+	let _ = fs::remove_file(file);
+
+	let mut recent = RecentGames::load(file).unwrap();
+	assert!(recent.games().is_empty());
+	for i in 0..MAX_RECENT_GAMES + 2 {
+		recent.add(&format!("game{}", i));
+	}
+	recent.add("game5");
+	assert_eq!(MAX_RECENT_GAMES, recent.games().len());
+	assert_eq!("game5", recent.games()[0]);
+	assert_eq!("game9", recent.games()[1]);
+	recent.save(file).unwrap();
+	assert_eq!(recent, RecentGames::load(file).unwrap());
+	fs::remove_file(file).unwrap();
+
+	let mut library = Library::scan("games", &RomDatabase::new()).unwrap();
+	let mut recent = RecentGames::new();
+	recent.add("games/TETRIS");
+	recent.add("games/PONG");
+	library.order_by_recent(&recent);
+	assert_eq!("PONG", library.entries[0].title);
+	assert_eq!("TETRIS", library.entries[1].title);
+}
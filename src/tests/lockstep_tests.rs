@@ -0,0 +1,75 @@
+use config::Config;
+use headless;
+use headless::InputScript;
+use lockstep;
+use lockstep::{Backend, Core, Trace};
+use tests::read_game;
+
+fn core(spec: &str) -> Core {
+	Core::parse(spec, &Config::default()).unwrap()
+}
+
+#[test]
+fn test_backends_agree_in_lockstep_on_bundled_games() {
+	let script = InputScript::parse("0 press 4\n100 release 4\n100 press 6\n").unwrap();
+	for game in &["BRIX", "PONG", "TETRIS"] {
+		for other in &["cached", "recompiled"] {
+			let result = lockstep::run(&read_game(game), &core("interpreter"), &core(other), &script, 20000);
+			assert_eq!(Ok(20000), result.map(|agreement| agreement.instructions), "{} on {}", game, other);
+		}
+	}
+}
+
+#[test]
+fn test_block_backends_run_many_instructions_at_once() {
+	//V0 = 1, V1 = 2, V2 = 3, then spin
+	let program = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x12, 0x06];
+	for backend in &[Backend::Cached, Backend::Recompiled] {
+		let mut chip8 = headless::machine(&program, Config::default(), 0);
+		assert!(backend.run(&mut chip8.cpu, 100).unwrap() > 1, "{:?}", backend);
+		assert!(backend.run(&mut chip8.cpu, 2).unwrap() <= 2, "{:?}", backend);
+	}
+	let mut chip8 = headless::machine(&program, Config::default(), 0);
+	assert_eq!(1, Backend::Interpreter.run(&mut chip8.cpu, 100).unwrap());
+}
+
+#[test]
+fn test_lockstep_reports_first_divergence() {
+	let program = [
+		0x60, 0x01, //V0 = 1
+		0x61, 0x05, //V1 = 5
+		0x80, 0x16, //V0 = V1 >> 1 on the VIP, V0 >> 1 otherwise
+		0x12, 0x06  //halt
+	];
+	let divergence = lockstep::run(&program, &core("@vip"), &core("@modern"), &InputScript::new(), 100)
+		.unwrap_err();
+	assert_eq!(2, divergence.instructions);
+	assert_eq!(0x204, divergence.pc);
+	assert_eq!(vec!["V0: 02 vs 00".to_string()], divergence.differences);
+	assert!(divergence.to_string().contains("@vip and @modern diverged after 2 instructions, at 204"));
+}
+
+#[test]
+fn test_trace_round_trip() {
+	let rom = read_game("UFO");
+	let (text, fault) = lockstep::record_trace(&rom, &core("interpreter"), &InputScript::new(), 5000);
+	assert_eq!(None, fault);
+	let trace = Trace::parse(&text).unwrap();
+	assert_eq!(5000, trace.states.len());
+	assert_eq!(Some(0x200), trace.states[0].pc);
+	let result = lockstep::run_against_trace(&rom, &core("recompiled"), &InputScript::new(), &trace);
+	assert_eq!(Ok(5000), result.map(|agreement| agreement.instructions));
+}
+
+#[test]
+fn test_trace_divergence() {
+	let rom = read_game("UFO");
+	let trace = Trace::parse("pc=200 i=000\n# the first instruction loads I\npc=202 v0=00\npc=204 i=ABC\n").unwrap();
+	let divergence = lockstep::run_against_trace(&rom, &core("interpreter"), &InputScript::new(), &trace)
+		.unwrap_err();
+	assert_eq!(2, divergence.instructions);
+	assert_eq!("trace", divergence.reference);
+	assert!(divergence.differences[0].starts_with("I: ABC vs "));
+	assert!(Trace::parse("pc=200 v10=00").is_err());
+	assert!(Trace::parse("pc=200 v1=100").is_err());
+}
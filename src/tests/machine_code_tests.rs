@@ -0,0 +1,40 @@
+use config::Config;
+use error::CpuError;
+use machine_code::{MachineCodePolicy, Routines};
+use headless;
+use vm::CPU;
+
+//0340 calls machine code, 6005 sets V0 = 5
+const MACHINE_CALL : [u8; 4] = [0x03, 0x40, 0x60, 0x05];
+
+#[test]
+fn test_machine_code_call_stops_by_default() {
+	let mut chip8 = headless::machine(&MACHINE_CALL, Config::default(), 0);
+	assert_eq!(Err(CpuError::MachineCodeCall(0x340)), chip8.cpu.step());
+	assert_eq!(0x200, chip8.cpu.pc);
+}
+
+#[test]
+fn test_machine_code_call_can_be_ignored() {
+	let mut chip8 = headless::machine(&MACHINE_CALL, Config { machine_code: MachineCodePolicy::Ignore, ..Config::default() }, 0);
+	chip8.cpu.run_cached(2).unwrap();
+	assert_eq!(5, chip8.cpu.registers[0]);
+}
+
+#[test]
+fn test_machine_code_call_dispatches_to_routine() {
+	let mut chip8 = headless::machine(&MACHINE_CALL, Config { machine_code: MachineCodePolicy::Dispatch, ..Config::default() }, 0);
+	let mut routines = Routines::new();
+	routines.add(0x340, Box::new(|cpu: &mut CPU| cpu.registers[0xA] = 0x42));
+	chip8.cpu.set_machine_code_handler(Box::new(routines));
+	chip8.cpu.step().unwrap();
+	assert_eq!(0x42, chip8.cpu.registers[0xA]);
+	assert_eq!(0x202, chip8.cpu.pc);
+}
+
+#[test]
+fn test_machine_code_call_without_routine_stops() {
+	let mut chip8 = headless::machine(&MACHINE_CALL, Config { machine_code: MachineCodePolicy::Dispatch, ..Config::default() }, 0);
+	chip8.cpu.set_machine_code_handler(Box::new(Routines::new()));
+	assert_eq!(Err(CpuError::MachineCodeCall(0x340)), chip8.cpu.step());
+}
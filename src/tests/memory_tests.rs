@@ -0,0 +1,55 @@
+use config::Config;
+use headless;
+use error::CpuError;
+use memory::{Access, AddressMode};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_store_past_end_of_memory_wraps() {
+	let mut chip8 = headless::machine(&[0xF2, 0x55], Config::default(), 0);
+	chip8.cpu.registers[0..3].copy_from_slice(&[7, 8, 9]);
+	chip8.cpu.index = 0xFFF;
+	chip8.cpu.step().unwrap();
+	assert_eq!(&[7], chip8.cpu.read_memory(0xFFF, 0x1000));
+	assert_eq!(&[8, 9], chip8.cpu.read_memory(0, 2));
+}
+
+#[test]
+fn test_load_past_end_of_memory_errors() {
+	let mut chip8 = headless::machine(&[0xF2, 0x65], Config { address_mode: AddressMode::Error, ..Config::default() }, 0);
+	chip8.cpu.index = 0xFFE;
+	assert_eq!(Err(CpuError::AddressOutOfRange(0x1000)), chip8.cpu.step());
+	assert_eq!(0x200, chip8.cpu.pc);
+}
+
+#[test]
+fn test_interpreter_area_write_protection() {
+	let mut chip8 = headless::machine(&[0xF0, 0x33], Config { protect_interpreter_area: true, ..Config::default() }, 0);
+	chip8.cpu.registers[0] = 123;
+	chip8.cpu.index = 0x1FF;
+	assert_eq!(Err(CpuError::ProtectedWrite(0x1FF)), chip8.cpu.step());
+	//nothing of the partially protected write may have landed
+	assert_eq!(&[0, 0xF0, 0x33], chip8.cpu.read_memory(0x1FF, 0x202));
+}
+
+#[test]
+fn test_memory_hooks_see_program_accesses() {
+	let mut chip8 = headless::machine(&[
+		0xF1, 0x55, //store V0..V1 at I
+		0xF0, 0x65  //load V0 from I
+	], Config::default(), 0);
+	let accesses = Arc::new(Mutex::new(Vec::new()));
+	let recorded = accesses.clone();
+	chip8.cpu.add_memory_hook(Box::new(move |access, address, value| {
+		recorded.lock().unwrap().push((access, address, value));
+	}));
+	chip8.cpu.registers[0..2].copy_from_slice(&[4, 5]);
+	chip8.cpu.index = 0x300;
+	chip8.cpu.step().unwrap();
+	chip8.cpu.step().unwrap();
+	assert_eq!(vec![
+		(Access::Write, 0x300, 4),
+		(Access::Write, 0x301, 5),
+		(Access::Read, 0x300, 4)
+	], *accesses.lock().unwrap());
+}
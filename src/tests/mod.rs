@@ -1 +1,47 @@
-mod vm_tests;
\ No newline at end of file
+mod vm_tests;
+mod block_cache_tests;
+mod recompiler_tests;
+mod stack_tests;
+mod memory_tests;
+mod font_tests;
+mod machine_code_tests;
+mod audio_tests;
+mod keymap_tests;
+mod rom_db_tests;
+mod library_tests;
+mod tty_tests;
+mod display_tests;
+mod palette_tests;
+mod frame_tests;
+mod golden_tests;
+mod conformance_tests;
+mod lockstep_tests;
+mod fuzz_tests;
+mod spec_tests;
+mod gym_tests;
+
+use config::Config;
+use vm::{Chip8, GFX};
+use std::fs;
+use std::io::Read;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+
+/// Like `headless::machine`, but keeps the receiving end of the screen
+/// channel for tests that look at the frames sent.
+pub fn machine_with_screen(rom: &[u8], config: Config) -> (Chip8, Receiver<GFX>) {
+	let (_, key_rx) = mpsc::channel();
+	let (gfx_tx, gfx_rx) = mpsc::channel();
+	let mut chip8 = Chip8::with_config(key_rx, gfx_tx, config);
+	chip8.cpu.logging = false;
+	chip8.load(rom);
+	(chip8, gfx_rx)
+}
+
+/// The bytes of one of the bundled games.
+pub fn read_game(name: &str) -> Vec<u8> {
+	let mut data = Vec::new();
+	let mut f = fs::File::open(format!("games/{}", name)).unwrap();
+	f.read_to_end(&mut data).unwrap();
+	data
+}
@@ -0,0 +1,23 @@
+use config_file::Value;
+use palette::Palette;
+
+#[test]
+fn test_parse_names_and_colours() {
+	assert_eq!(Palette::by_name("amber"), Palette::parse("amber").ok());
+	assert_eq!(Palette::new(&[0x112233, 0xFFCC00]).unwrap(), Palette::parse("#112233, 0xffcc00").unwrap());
+	assert!(Palette::parse("sepia").is_err());
+	assert!(Palette::parse("112233").is_err());
+	assert!(Palette::new(&[]).is_err());
+	assert!(Palette::from_values(&[Value::Integer(0), Value::Integer(0x1000000)]).is_err());
+}
+
+#[test]
+fn test_color_by_pixel_value() {
+	let octo = Palette::by_name("octo").unwrap();
+	assert_eq!(0x996600, octo.color(0));
+	assert_eq!(0x662200, octo.color(3));
+	//two colours show every plane as lit
+	let classic = Palette::default();
+	assert_eq!(0xFFFFFF, classic.color(3));
+	assert_eq!([1.0, 0.2, 0.0], Palette::rgb(0xFF3300));
+}
@@ -0,0 +1,28 @@
+use recompiler;
+use tests::read_game;
+
+#[test]
+fn test_recompiler_matches_interpreter_on_counting_loop() {
+	let program = [
+		0x60, 0x00, //V0 = 0
+		0x61, 0x00, //V1 = 0
+		0x70, 0x01, //V0 += 1
+		0x81, 0x03, //V1 ^= V0
+		0xA3, 0x00, //I = 0x300
+		0x30, 0xFF, //skip if V0 == 0xFF
+		0x12, 0x04, //jump back to the add
+		0x12, 0x0E  //halt
+	];
+	let verified = recompiler::verify_against_interpreter(&program, 1, 5000).unwrap();
+	assert_eq!(5000, verified.instructions);
+	assert!(verified.compiled_runs > 0);
+}
+
+#[test]
+fn test_recompiler_matches_interpreter_on_bundled_games() {
+	for game in &["BRIX", "INVADERS", "MAZE", "PONG", "TETRIS", "UFO"] {
+		let result = recompiler::verify_against_interpreter(&read_game(game), 7, 20000);
+		assert!(result.is_ok(), "{}: {:?}", game, result);
+		assert!(result.unwrap().compiled_runs > 0, "{} never ran a compiled block", game);
+	}
+}
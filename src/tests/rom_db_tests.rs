@@ -0,0 +1,98 @@
+use config::Config;
+use config_file::ConfigFile;
+use headless;
+use quirks::Quirks;
+use rom_db::RomDatabase;
+use sha1;
+use stack::VIP_STACK_DEPTH;
+use std::fs;
+use std::io::Read;
+
+#[test]
+fn test_sha1() {
+	assert_eq!("da39a3ee5e6b4b0d3255bfef95601890afd80709", sha1::hex_digest(b""));
+	assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", sha1::hex_digest(b"abc"));
+	assert_eq!("84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+		sha1::hex_digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"));
+}
+
+#[test]
+fn test_quirks_change_execution() {
+	//V1 = 0x81, V2 = 0x03; 8126 (shift), A300, F155, B200 in that order
+	let program = [0x61, 0x81, 0x62, 0x03, 0x81, 0x26, 0xA3, 0x00, 0xF1, 0x55, 0xB2, 0x00];
+
+	let mut vip = headless::machine(&program, Config { quirks: Quirks::vip(), ..Config::default() }, 0);
+	vip.cpu.run_cached(5).unwrap();
+	assert_eq!(0x01, vip.cpu.registers[1]);
+	assert_eq!(0x302, vip.cpu.index);
+	vip.cpu.step().unwrap();
+	assert_eq!(0x200, vip.cpu.pc);
+
+	let mut schip = headless::machine(&program, Config { quirks: Quirks::schip(), ..Config::default() }, 0);
+	schip.cpu.run_cached(5).unwrap();
+	assert_eq!(0x40, schip.cpu.registers[1]);
+	assert_eq!(0x300, schip.cpu.index);
+	schip.cpu.step().unwrap();
+	assert_eq!(0x203, schip.cpu.pc);
+}
+
+#[test]
+fn test_logic_resets_vf_when_recompiled() {
+	//6F05 6103 8121 1200: a loop the recompiler compiles once it is hot
+	let program = [0x6F, 0x05, 0x61, 0x03, 0x81, 0x21, 0x12, 0x00];
+	let mut chip8 = headless::machine(&program, Config { quirks: Quirks::vip(), ..Config::default() }, 0);
+	chip8.cpu.run_recompiled(400).unwrap();
+	assert_eq!(0, chip8.cpu.registers[0xF]);
+	let mut chip8 = headless::machine(&program, Config { quirks: Quirks::modern(), ..Config::default() }, 0);
+	chip8.cpu.run_recompiled(400).unwrap();
+	assert_eq!(5, chip8.cpu.registers[0xF]);
+}
+
+#[test]
+fn test_database_entry() {
+	let file = ConfigFile::parse("[ABCDEF]\n\
+		title = \"Blitz\"\n\
+		quirks = \"vip\"\n\
+		clock_speed = 600\n\
+		stack_depth = 12\n\
+		palette = [0x000000, 0x33FF66]\n\
+		[ABCDEF.quirks]\n\
+		clip_sprites = false\n\
+		[ABCDEF.keys]\n\
+		Space = \"5\"\n").unwrap();
+	let db = RomDatabase::from_config(&file).unwrap();
+	let info = db.get("abcdef").unwrap();
+	assert_eq!(Some("Blitz".to_string()), info.title);
+	assert_eq!(Some(Quirks { clip_sprites: false, ..Quirks::vip() }), info.quirks);
+	assert_eq!(0x33FF66, info.palette.as_ref().unwrap().foreground());
+	assert!(info.keys.as_ref().unwrap().contains_key("Space"));
+
+	let mut config = Config::default();
+	info.apply(&mut config);
+	assert_eq!(600, config.clock_speed);
+	assert_eq!(12, config.stack_depth);
+
+	assert!(RomDatabase::from_config(&ConfigFile::parse("[AB]\nquirks = \"nes\"").unwrap()).is_err());
+	assert!(RomDatabase::from_config(&ConfigFile::parse("[AB]\nspeed = 1").unwrap()).is_err());
+	assert!(RomDatabase::from_config(&ConfigFile::parse("[AB]\nstack_depth = 0").unwrap()).is_err());
+	assert!(RomDatabase::from_config(&ConfigFile::parse("[AB]\nclock_speed = -1").unwrap()).is_err());
+	assert!(RomDatabase::from_config(&ConfigFile::parse("[AB]\nclock_speed = 4294967296").unwrap()).is_err());
+}
+
+#[test]
+fn test_database_covers_bundled_games() {
+	let db = RomDatabase::load("roms.toml").unwrap();
+	let mut games = 0;
+	for entry in fs::read_dir("games").unwrap() {
+		let mut data = Vec::new();
+		fs::File::open(entry.unwrap().path()).unwrap().read_to_end(&mut data).unwrap();
+		let info = db.lookup(&data).unwrap();
+		assert!(info.title.is_some() && info.quirks.is_some() && info.clock_speed.is_some());
+		if info.quirks == Some(Quirks::vip()) {
+			assert_eq!(Some(VIP_STACK_DEPTH), info.stack_depth);
+		}
+		games += 1;
+	}
+	assert_eq!(23, games);
+	assert_eq!(games, db.len());
+}
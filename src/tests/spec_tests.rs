@@ -0,0 +1,132 @@
+use config::Config;
+use config_file::{Table, Value};
+use conformance::PROFILES;
+use headless;
+use instruction::Instruction;
+use quirks::Quirks;
+use rand::{Rng, XorShiftRng};
+use spec;
+use spec::{Environment, OpcodeSpec, SPEC};
+use vm;
+use vm::Snapshot;
+
+/// Random states each instruction is tried from, under each profile.
+const CASES : usize = 64;
+
+/// A machine state with everything random, waits included, and VX often
+/// equal to NN or VY so that both sides of the skips get taken.
+fn random_state(rng: &mut XorShiftRng, opcode: u16) -> Snapshot {
+	let mut registers = [0; 16];
+	for v in registers.iter_mut() {
+		*v = match rng.gen_range(0, 4) {
+			0 => *rng.choose(&[0x00, 0x01, 0x0F, 0x7F, 0x80, 0xFF]).unwrap(),
+			_ => rng.gen()
+		};
+	}
+	let (x, y) = ((opcode >> 8 & 0xF) as usize, (opcode >> 4 & 0xF) as usize);
+	match rng.gen_range(0, 3) {
+		0 => registers[x] = opcode as u8,
+		1 => registers[x] = registers[y],
+		_ => ()
+	}
+	let mut ram = vec![0; 4096];
+	rng.fill_bytes(&mut ram);
+	let pc = rng.gen_range(0x100, 0x7FF) * 2;
+	ram[pc] = (opcode >> 8) as u8;
+	ram[pc + 1] = opcode as u8;
+	let mut gfx = [[0; 64]; 32];
+	for row in gfx.iter_mut() {
+		for pixel in row.iter_mut() {
+			*pixel = rng.gen_range(0, 2);
+		}
+	}
+	let mut keypad = [0; 16];
+	for key in keypad.iter_mut() {
+		*key = rng.gen_range(0, 2);
+	}
+	let stack_len = rng.gen_range(0, 17);
+	Snapshot {
+		pc: pc as u16,
+		index: rng.gen_range(0, 0x1000),
+		registers,
+		stack: (0..stack_len).map(|_| rng.gen_range(0, 0x800) * 2).collect(),
+		delay_timer: rng.gen(),
+		sound_timer: rng.gen(),
+		audio_pattern: if rng.gen() { Some(rng.gen()) } else { None },
+		audio_pitch: rng.gen(),
+		keypad,
+		gfx,
+		ram,
+		//mostly not waiting, so that the instruction itself gets run
+		waiting_for_vblank: rng.gen_range(0, 4) == 0,
+		waiting_for_key: rng.gen(),
+		released_key: if rng.gen() { Some(rng.gen_range(0, 16)) } else { None }
+	}
+}
+
+fn check(spec: &OpcodeSpec, profile: &str, rng: &mut XorShiftRng) {
+	let config = Config {
+		quirks: Quirks::by_name(profile).unwrap(),
+		clock_speed: 1_000_000,
+		..Config::default()
+	};
+	for case in 0..CASES {
+		let opcode = spec.opcode(rng.gen());
+		//an earlier pattern may claim it, as 00E0 does from 0NNN
+		match spec::lookup(opcode) {
+			Some(found) if found.pattern == spec.pattern => (),
+			_ => continue
+		}
+		let state = random_state(rng, opcode);
+		let seed = case as u64;
+
+		let mut expected = state.clone();
+		let environment = Environment::new(&config, vm::seeded_rng(seed).gen());
+		let expected_result = spec::step(&mut expected, &environment);
+
+		let mut chip8 = headless::machine(&[], config.clone(), seed);
+		chip8.cpu.restore(&state);
+		let result = chip8.cpu.emulate_cycle();
+
+		let context = format!("{:04X} ({}) under {} at {:03X}", opcode, spec.summary, profile, state.pc);
+		assert_eq!(expected_result, result, "{}", context);
+		let differences = expected.diff(&chip8.cpu.snapshot());
+		assert!(differences.is_empty(), "{}, spec vs cpu:\n  {}", context, differences.join("\n  "));
+	}
+}
+
+#[test]
+fn test_every_instruction_matches_the_spec_under_every_profile() {
+	let mut rng = vm::seeded_rng(47);
+	for spec in SPEC.iter() {
+		for profile in PROFILES.iter() {
+			check(spec, profile, &mut rng);
+		}
+	}
+}
+
+#[test]
+fn test_spec_and_decoder_agree_on_every_opcode() {
+	for opcode in 0..=0xFFFF {
+		let decoded = Instruction::decode(opcode);
+		let known = decoded != Instruction::Unknown(opcode);
+		assert_eq!(known, spec::lookup(opcode).is_some(), "{:04X} decodes to {:?}", opcode, decoded);
+	}
+}
+
+#[test]
+fn test_spec_patterns() {
+	for spec in SPEC.iter() {
+		assert_eq!(4, spec.pattern.len(), "{}", spec.pattern);
+		assert!(spec.matches(spec.opcode(0xFFFF)) && spec.matches(spec.opcode(0)), "{}", spec.pattern);
+		for quirk in spec.quirks.iter() {
+			let mut table = Table::new();
+			table.insert(quirk.to_string(), Value::Boolean(true));
+			assert_eq!(Ok(()), Quirks::default().apply(&table), "{}", spec.pattern);
+		}
+	}
+	assert_eq!(Some("8XY4"), spec::lookup(0x8AB4).map(|spec| spec.pattern));
+	assert_eq!(Some("00E0"), spec::lookup(0x00E0).map(|spec| spec.pattern));
+	assert_eq!(Some("0NNN"), spec::lookup(0x00E1).map(|spec| spec.pattern));
+	assert!(spec::lookup(0x5AB1).is_none());
+}
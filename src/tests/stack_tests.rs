@@ -0,0 +1,44 @@
+use config::Config;
+use headless;
+use error::CpuError;
+
+#[test]
+fn test_runaway_recursion_overflows_at_16() {
+	let mut chip8 = headless::machine(&[0x22, 0x00], Config::default(), 0); //calls itself forever
+	for _ in 0..16 {
+		chip8.cpu.step().unwrap();
+	}
+	assert_eq!(Err(CpuError::StackOverflow(16)), chip8.cpu.step());
+	assert_eq!(16, chip8.cpu.stack().len());
+	assert_eq!(0x200, chip8.cpu.pc);
+}
+
+#[test]
+fn test_stack_depth_is_configurable() {
+	let mut chip8 = headless::machine(&[0x22, 0x00], Config { stack_depth: 12, ..Config::default() }, 0);
+	assert_eq!(Err(CpuError::StackOverflow(12)), chip8.cpu.run_cached(100));
+	assert_eq!(12, chip8.cpu.stack().depth());
+}
+
+#[test]
+fn test_return_on_empty_stack_underflows() {
+	let mut chip8 = headless::machine(&[0x00, 0xEE], Config::default(), 0);
+	assert_eq!(Err(CpuError::StackUnderflow), chip8.cpu.step());
+}
+
+#[test]
+fn test_stack_entries_are_inspectable() {
+	let mut chip8 = headless::machine(&[
+		0x22, 0x04, //call 0x204
+		0x00, 0x00,
+		0x22, 0x08, //call 0x208
+		0x00, 0x00,
+		0x00, 0xEE  //return
+	], Config::default(), 0);
+	chip8.cpu.step().unwrap();
+	chip8.cpu.step().unwrap();
+	assert_eq!(&[0x202, 0x206], chip8.cpu.stack().entries());
+	chip8.cpu.step().unwrap();
+	assert_eq!(&[0x202], chip8.cpu.stack().entries());
+	assert_eq!(0x206, chip8.cpu.pc);
+}
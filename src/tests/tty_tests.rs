@@ -0,0 +1,30 @@
+use keymap::Keymap;
+use palette::Palette;
+use tty::{key_names, render, QUIT};
+use vm::Key;
+
+#[test]
+fn test_render_half_blocks() {
+	let mut gfx = [[0; 64]; 32];
+	gfx[0][0] = 1;
+	gfx[1][0] = 1;
+	gfx[0][1] = 1;
+	gfx[1][2] = 1;
+	gfx[31][63] = 1;
+	let frame = render(&gfx, &Palette::default());
+	assert!(frame.starts_with("\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m"));
+	let lines : Vec<&str> = frame.split("\r\n").collect();
+	assert_eq!(17, lines.len());
+	assert!(lines[0].ends_with(&format!("m\u{2588}\u{2580}\u{2584}{}", " ".repeat(61))));
+	assert_eq!(64, lines[1].chars().count());
+	assert!(lines[15].ends_with(" \u{2584}"));
+}
+
+#[test]
+fn test_key_names_match_keymap() {
+	let names = key_names(b"1qV \x1b[A\x1b[D\x03");
+	assert_eq!(vec!["Key1", "Q", "V", "Space", "Up", "Left", QUIT], names);
+	let keymap = Keymap::qwerty();
+	assert_eq!(Some(Key::C), keymap.get(&key_names(b"4")[0]));
+	assert_eq!(vec!["Escape"], key_names(b"\x1b"));
+}
@@ -0,0 +1,97 @@
+use instruction::Instruction;
+
+/// How long instructions take when running in real time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Timing {
+	/// Every instruction takes the same time, set by the clock speed.
+	#[default]
+	Uniform,
+	/// Every instruction takes the machine cycles it took in the COSMAC VIP
+	/// interpreter, and a frame has as many cycles as the VIP had to spare
+	/// for the program. The clock speed is ignored.
+	Vip
+}
+
+impl Timing {
+	pub fn by_name(name: &str) -> Option<Timing> {
+		match name {
+			"uniform" => Some(Timing::Uniform),
+			"vip" | "cosmac-vip" => Some(Timing::Vip),
+			_ => None
+		}
+	}
+}
+
+/// Machine cycles (8 clock pulses of the 1.76 MHz CDP1802) in a 60 Hz frame.
+pub const VIP_CYCLES_PER_FRAME : u32 = 3668;
+/// Cycles a frame spends on the display: DMA of 128 scanlines of 8 bytes
+/// plus the interrupt routine that sets it up and counts the timers down.
+pub const VIP_DISPLAY_CYCLES : u32 = 1024 + 46;
+/// What is left of a frame for the interpreter to run the program in.
+pub const VIP_PROGRAM_CYCLES : u32 = VIP_CYCLES_PER_FRAME - VIP_DISPLAY_CYCLES;
+/// Fetching and decoding, which every instruction pays for on top of
+/// executing.
+pub const VIP_FETCH_CYCLES : u32 = 40;
+
+/// The machine cycles `instruction` took on the VIP, given the registers
+/// as they were before it ran and whether it skipped the next instruction.
+/// Instructions the VIP didn't have only pay for being fetched.
+pub fn vip_cycles(instruction: Instruction, registers: &[u8; 16], skipped: bool) -> u32 {
+	let skip = if skipped { 4 } else { 0 };
+	VIP_FETCH_CYCLES + match instruction {
+		//clearing is a loop over all 256 bytes of the screen
+		Instruction::ClearScreen => 24 + 256 * 12,
+		Instruction::Return => 10,
+		Instruction::Jump(_) => 12,
+		Instruction::Call(_) => 26,
+		Instruction::SkipEqualByte(_, _) |
+		Instruction::SkipNotEqualByte(_, _) => 10 + skip,
+		Instruction::SkipEqualRegister(_, _) |
+		Instruction::SkipNotEqualRegister(_, _) |
+		Instruction::SkipKeyPressed(_) |
+		Instruction::SkipKeyNotPressed(_) => 14 + skip,
+		Instruction::LoadByte(_, _) => 6,
+		Instruction::AddByte(_, _) => 10,
+		//8XYN runs as a small machine code routine built on the stack
+		Instruction::Move(_, _) |
+		Instruction::Or(_, _) |
+		Instruction::And(_, _) |
+		Instruction::Xor(_, _) |
+		Instruction::AddRegister(_, _) |
+		Instruction::SubRegister(_, _) |
+		Instruction::ShiftRight(_, _) |
+		Instruction::SubReverse(_, _) |
+		Instruction::ShiftLeft(_, _) => 44,
+		Instruction::LoadIndex(_) => 12,
+		Instruction::JumpOffset(_) => 22,
+		Instruction::Random(_, _) => 36,
+		Instruction::Draw(x, y, height) => _draw_cycles(registers[x], registers[y], height),
+		Instruction::LoadDelay(_) |
+		Instruction::SetDelay(_) |
+		Instruction::SetSound(_) => 10,
+		Instruction::AddIndex(_) |
+		Instruction::LoadSprite(_) => 16,
+		//each digit is found by repeated subtraction
+		Instruction::StoreBcd(x) => {
+			let value = registers[x] as u32;
+			80 + 16 * (value / 100 + value / 10 % 10 + value % 10)
+		},
+		Instruction::StoreRegisters(x) |
+		Instruction::LoadRegisters(x) => 14 + 14 * (x as u32 + 1),
+		Instruction::MachineCall(_) |
+		Instruction::WaitKey(_) |
+		Instruction::LoadAudioPattern |
+		Instruction::LoadLargeSprite(_) |
+		Instruction::SetPitch(_) |
+		Instruction::Unknown(_) => 0
+	}
+}
+
+/// Sprites are drawn a row at a time. A sprite that isn't lined up with a
+/// byte of the screen has every row shifted across two bytes, which costs
+/// more, and rows past the bottom of the screen aren't drawn at all.
+fn _draw_cycles(x: u8, y: u8, height: usize) -> u32 {
+	let rows = height.min(32 - y as usize % 32) as u32;
+	let row_cycles = if x & 7 == 0 { 34 } else { 46 };
+	26 + rows * row_cycles
+}
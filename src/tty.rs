@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::mpsc::{Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use audio::AudioSink;
+use keymap::Keymap;
+use palette::Palette;
+use vm::{GFX, Key};
+
+/// Terminals only report key presses, so a key counts as held until it
+/// hasn't been repeated for this long.
+const KEY_HOLD_MILLIS : u64 = 200;
+const POLL_MILLIS : u64 = 10;
+
+/// Draws the screen in the terminal and reads keys from stdin, for when
+/// there's no window system (over SSH, say). Every character cell shows two
+/// rows of pixels with the Unicode half blocks.
+pub struct TtyFrontend {
+	key_sender: Sender<(Key, bool)>,
+	gfx_receiver: Receiver<GFX>,
+	keymap: Keymap,
+	palette: Palette
+}
+
+impl TtyFrontend {
+	pub fn new(key_sender: Sender<(Key, bool)>, gfx_receiver: Receiver<GFX>,
+		keymap: Keymap, palette: &Palette) -> TtyFrontend {
+		TtyFrontend {
+			key_sender,
+			gfx_receiver,
+			keymap,
+			palette: palette.clone()
+		}
+	}
+
+	/// Runs until Ctrl-C or the emulator stops, then puts the terminal back.
+	pub fn start_session(self) {
+		let saved = RawMode::enter();
+		let (name_tx, name_rx) = mpsc::channel();
+		thread::spawn(move || _read_keys(name_tx));
+
+		let stdout = io::stdout();
+		let mut out = stdout.lock();
+		let _ = write!(out, "\x1b[2J\x1b[?25l");
+		let mut held : HashMap<Key, Instant> = HashMap::new();
+		'session: loop {
+			match self.gfx_receiver.recv_timeout(Duration::from_millis(POLL_MILLIS)) {
+				Ok(gfx) => {
+					let _ = write!(out, "\x1b[H{}", render(&gfx, &self.palette));
+					let _ = out.flush();
+				},
+				Err(RecvTimeoutError::Timeout) => (),
+				Err(RecvTimeoutError::Disconnected) => break
+			}
+
+			while let Ok(name) = name_rx.try_recv() {
+				if name == QUIT {
+					break 'session;
+				}
+				if let Some(key) = self.keymap.get(&name) {
+					if !held.contains_key(&key) {
+						let _ = self.key_sender.send((key, true));
+					}
+					held.insert(key, Instant::now());
+				}
+			}
+
+			let hold = Duration::from_millis(KEY_HOLD_MILLIS);
+			let released : Vec<Key> = held.iter()
+				.filter(|&(_, pressed)| pressed.elapsed() >= hold)
+				.map(|(&key, _)| key).collect();
+			for key in released {
+				held.remove(&key);
+				let _ = self.key_sender.send((key, false));
+			}
+		}
+		let _ = write!(out, "\x1b[0m\x1b[?25h\r\n");
+		let _ = out.flush();
+		drop(saved);
+	}
+}
+
+/// The name `key_names` gives Ctrl-C.
+pub const QUIT : &str = "Quit";
+
+/// Renders the screen as 16 lines of half blocks in the palette's colours.
+/// Lines end in "\r\n" since the terminal is in raw mode.
+pub fn render(gfx: &GFX, palette: &Palette) -> String {
+	let fg = Palette::rgb(palette.foreground());
+	let bg = Palette::rgb(palette.background());
+	let mut out = format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+		(fg[0] * 255.0) as u8, (fg[1] * 255.0) as u8, (fg[2] * 255.0) as u8,
+		(bg[0] * 255.0) as u8, (bg[1] * 255.0) as u8, (bg[2] * 255.0) as u8);
+	for rows in gfx.chunks(2) {
+		for (&top, &bottom) in rows[0].iter().zip(rows[1].iter()) {
+			out.push(match (top != 0, bottom != 0) {
+				(true, true) => '\u{2588}',
+				(true, false) => '\u{2580}',
+				(false, true) => '\u{2584}',
+				(false, false) => ' '
+			});
+		}
+		out.push_str("\r\n");
+	}
+	out.push_str("\x1b[0m");
+	out
+}
+
+/// Turns what a read from the terminal returned into key names, the same
+/// names the window frontend uses ("Key1", "Q", "Left", ...).
+pub fn key_names(input: &[u8]) -> Vec<String> {
+	let mut names = Vec::new();
+	let mut i = 0;
+	while i < input.len() {
+		let byte = input[i];
+		i += 1;
+		let name = match byte {
+			0x03 => QUIT.to_string(),
+			0x1B if input.len() >= i + 2 && input[i] == b'[' => {
+				i += 2;
+				match input[i - 1] {
+					b'A' => "Up",
+					b'B' => "Down",
+					b'C' => "Right",
+					b'D' => "Left",
+					_ => continue
+				}.to_string()
+			},
+			0x1B => "Escape".to_string(),
+			b'0'..=b'9' => format!("Key{}", byte as char),
+			b'a'..=b'z' | b'A'..=b'Z' => (byte as char).to_ascii_uppercase().to_string(),
+			b' ' => "Space".to_string(),
+			b'\r' | b'\n' => "Return".to_string(),
+			b',' => "Comma".to_string(),
+			b'.' => "Period".to_string(),
+			b';' => "Semicolon".to_string(),
+			b'\'' => "Apostrophe".to_string(),
+			_ => continue
+		};
+		names.push(name);
+	}
+	names
+}
+
+fn _read_keys(names: Sender<String>) {
+	let stdin = io::stdin();
+	let mut stdin = stdin.lock();
+	let mut buffer = [0; 16];
+	loop {
+		let n = match stdin.read(&mut buffer) {
+			Ok(0) | Err(_) => return,
+			Ok(n) => n
+		};
+		for name in key_names(&buffer[..n]) {
+			if names.send(name).is_err() {
+				return;
+			}
+		}
+	}
+}
+
+/// Puts the terminal in raw mode with `stty` and restores the previous
+/// settings when dropped.
+struct RawMode {
+	saved: Option<String>
+}
+
+impl RawMode {
+	fn enter() -> RawMode {
+		let saved = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output().ok()
+			.map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+		let _ = Command::new("stty").arg("raw").arg("-echo").stdin(Stdio::inherit()).status();
+		RawMode {
+			saved
+		}
+	}
+}
+
+impl Drop for RawMode {
+	fn drop(&mut self) {
+		let mut stty = Command::new("stty");
+		match self.saved {
+			Some(ref saved) if !saved.is_empty() => stty.arg(saved),
+			_ => stty.arg("sane")
+		};
+		let _ = stty.stdin(Stdio::inherit()).status();
+	}
+}
+
+/// Rings the terminal bell whenever the buzzer starts sounding.
+pub struct BellSink {
+	sounding: bool
+}
+
+impl BellSink {
+	pub fn new() -> BellSink {
+		BellSink {
+			sounding: false
+		}
+	}
+}
+
+impl Default for BellSink {
+	fn default() -> BellSink {
+		BellSink::new()
+	}
+}
+
+impl AudioSink for BellSink {
+	fn write(&mut self, samples: &[f32]) {
+		let sounding = samples.iter().any(|&sample| sample != 0.0);
+		if sounding && !self.sounding {
+			print!("\x07");
+			let _ = io::stdout().flush();
+		}
+		self.sounding = sounding;
+	}
+}
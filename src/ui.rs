@@ -1,25 +1,29 @@
 use std::sync::mpsc::{Sender, Receiver};
 
-use vm::{GFX, Key};
+use chip8::display::{Renderer, Scaling};
+use chip8::keymap::Keymap;
+use chip8::vm::{GFX, Key};
 use gfx::Chip8GFX;
 
 use glutin;
 use glutin::{Event};
-use std::process;
 
 pub struct Chip8UI {
 	key_sender: Sender<(Key, bool)>,
 	gfx_receiver: Receiver<GFX>,
+	keymap: Keymap,
 	gfx: Chip8GFX
 }
 
 impl Chip8UI {
-	pub fn new(key_sender: Sender<(Key, bool)>, 
-		gfx_receiver: Receiver<GFX>) -> Chip8UI {
+	pub fn new(key_sender: Sender<(Key, bool)>,
+		gfx_receiver: Receiver<GFX>, keymap: Keymap, title: &str, renderer: Renderer,
+		scaling: Scaling) -> Chip8UI {
 		Chip8UI {
-			key_sender: key_sender,
-			gfx_receiver: gfx_receiver,
-			gfx: Chip8GFX::new()
+			key_sender,
+			gfx_receiver,
+			keymap,
+			gfx: Chip8GFX::new(title, renderer, scaling)
 		}
 	}
 
@@ -29,50 +33,42 @@ impl Chip8UI {
 		self.gfx.update_graphics(gfx);
 	}
 
-	pub fn _handle_ui_events(&mut self) {
-		for event in self.gfx.get_display().poll_events() {
-			if let Event::KeyboardInput(state, num, _) = event {
+	/// Returns false once the window has been closed.
+	pub fn _handle_ui_events(&mut self) -> bool {
+		let events : Vec<Event> = self.gfx.get_display().poll_events().collect();
+		for event in events {
+			if let Event::KeyboardInput(state, _, Some(virtual_key)) = event {
 				println!("Event: {:?}", event);
 				let up = state == glutin::ElementState::Pressed;
-				let send_result = match num {
-					11 => self.key_sender.send((Key::K0, up)), 
-					2 => self.key_sender.send((Key::K1, up)),
-					3 => self.key_sender.send((Key::K2, up)),
-					4 => self.key_sender.send((Key::K3, up)),
-					5 => self.key_sender.send((Key::K4, up)),
-					6 => self.key_sender.send((Key::K5, up)),
-					7 => self.key_sender.send((Key::K6, up)),
-					8 => self.key_sender.send((Key::K7, up)),
-					9 => self.key_sender.send((Key::K8, up)),
-					10 => self.key_sender.send((Key::K9, up)),
-					30 => self.key_sender.send((Key::A, up)),
-					48 => self.key_sender.send((Key::B, up)),
-					46 => self.key_sender.send((Key::C, up)),
-					32 => self.key_sender.send((Key::D, up)),
-					18 => self.key_sender.send((Key::E, up)),
-					33 => self.key_sender.send((Key::F, up)),
-					_ => (Ok(()))
+				let send_result = match self.keymap.get(&format!("{:?}", virtual_key)) {
+					Some(key) => self.key_sender.send((key, up)),
+					None => Ok(())
 				};
-				if !send_result.is_ok() {
+				if send_result.is_err() {
 					println!("Failed to send event: {:?}", event);
 				}
 			} else if let Event::Closed = event {
-                process::exit(0);
-            }
+				return false;
+			} else if let Event::Resized(_, _) = event {
+				self.gfx.redraw();
+			}
 		}
+		true
 	}
 
+	//the emulator sends either every draw or one screen a frame at vertical
+	//blank, as the renderer wants; draws can come faster than the window
+	//redraws, so only the newest screen waiting is shown
 	fn _handle_gfx_updates(&mut self) {
-		match self.gfx_receiver.try_recv() {
-			Ok(graphics_update) => self._update_graphics(graphics_update),
-			_ => ()
+		if let Some(graphics_update) = self.gfx_receiver.try_iter().last() {
+			self._update_graphics(graphics_update);
 		}
 	}
 
+	/// Runs until the window is closed.
 	pub fn start_session(mut self) {
-		loop {
-			//handle keyboard input if any
-			self._handle_ui_events();
+		//handle keyboard input if any
+		while self._handle_ui_events() {
 			self._handle_gfx_updates();
 		}
 	}
@@ -1,572 +1,928 @@
-use rand::distributions::{IndependentSample, Range};
+use rand::{Rng, SeedableRng, XorShiftRng};
 use rand;
-use std::sync::mpsc::{Sender, Receiver};
+use std::sync::mpsc::{Sender, Receiver, TryRecvError};
 use std::thread::sleep;
-use std::time::Duration;
-use std::process;
+use std::time::{Duration, Instant};
+use std::fmt;
+use std::sync::Arc;
+
+use audio::{Audio, Pattern, TIMER_HZ, DEFAULT_PITCH, PATTERN_BYTES};
+use instruction::Instruction;
+use block_cache::{BlockCache, Entry};
+use config::Config;
+use error::CpuError;
+use font::{Font, SMALL_GLYPH_SIZE, LARGE_GLYPH_SIZE};
+use machine_code::{MachineCodeHandler, MachineCodePolicy};
+use memory::{Memory, MemoryHook, RAM_SIZE, PROGRAM_START};
+use quirks::Quirks;
+use recompiler;
+use stack::Stack;
+use timing;
+use timing::{Timing, VIP_PROGRAM_CYCLES};
 
 //#[derive(Send)]
 pub struct CPU {
 	pub delay_timer: u8,
 	pub sound_timer: u8,
+	/// The XO-CHIP audio pattern, once F002 has loaded one.
+	pub audio_pattern: Option<[u8; PATTERN_BYTES]>,
+	pub audio_pitch: u8,
 	pub gfx: GFX,
 	pub registers: [u8; 16],
 	pub pc: u16,
 	pub index: u16,
+	pub logging: bool,
+	pub draw_flag: bool,
+	pub quirks: Quirks,
+	clock_period: Duration,
+	waiting_for_vblank: bool,
+	//FX0A is waiting for a key, and the key let go of since it started
+	waiting_for_key: bool,
+	released_key: Option<u8>,
 	keypad: [u8; 16],
-	//draw_flag: bool,
-	stack: Vec<u16>, 
-	ram: RAM,
+	stack: Stack,
+	memory: Memory,
+	block_cache: BlockCache,
+	font: Font,
+	font_address: u16,
+	machine_code: MachineCodePolicy,
+	machine_code_handler: Option<Box<dyn MachineCodeHandler>>,
+	rng: XorShiftRng,
 	key_input: Receiver<(Key, bool)>,
 	graphics_output: Sender<GFX>
 }
 
 type Keypad = [u8; 16];
-type RAM = [u8; 4096];
 pub type GFX = [[u8; 64]; 32];
 
 pub struct Chip8 {
-	pub cpu: CPU
+	pub cpu: CPU,
+	pub audio: Audio,
+	clock_speed: u32,
+	timing: Timing,
+	present_at_vblank: bool,
+	frames: u64,
+	//cycles the last instruction of a VIP timed frame ran into the next one
+	overrun: u32
+}
+
+/// Everything that determines how a CPU will go on to execute, used to
+/// compare two CPUs against each other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+	pub pc: u16,
+	pub index: u16,
+	pub registers: [u8; 16],
+	pub stack: Vec<u16>,
+	pub delay_timer: u8,
+	pub sound_timer: u8,
+	pub audio_pattern: Option<[u8; PATTERN_BYTES]>,
+	pub audio_pitch: u8,
+	pub keypad: Keypad,
+	pub gfx: GFX,
+	pub ram: Vec<u8>,
+	pub waiting_for_vblank: bool,
+	pub waiting_for_key: bool,
+	pub released_key: Option<u8>
 }
 
 
-const SPRITE_OFFSET : usize = 0;
-const CLOCK_PERIOD_MILLIS : u64 = 10;
+/// How many differing bytes of RAM `Snapshot::diff` lists before it just
+/// counts the rest.
+const MAX_LISTED_RAM_DIFFERENCES : usize = 16;
+
+impl Snapshot {
+	/// What differs between two snapshots, a line for each difference with
+	/// this snapshot's value first.
+	pub fn diff(&self, other: &Snapshot) -> Vec<String> {
+		let mut lines = Vec::new();
+		if self.pc != other.pc {
+			lines.push(format!("pc: {:03X} vs {:03X}", self.pc, other.pc));
+		}
+		if self.index != other.index {
+			lines.push(format!("I: {:03X} vs {:03X}", self.index, other.index));
+		}
+		for (i, (a, b)) in self.registers.iter().zip(other.registers.iter()).enumerate() {
+			if a != b {
+				lines.push(format!("V{:X}: {:02X} vs {:02X}", i, a, b));
+			}
+		}
+		if self.stack != other.stack {
+			lines.push(format!("stack: {:03X?} vs {:03X?}", self.stack, other.stack));
+		}
+		if self.delay_timer != other.delay_timer {
+			lines.push(format!("delay timer: {} vs {}", self.delay_timer, other.delay_timer));
+		}
+		if self.sound_timer != other.sound_timer {
+			lines.push(format!("sound timer: {} vs {}", self.sound_timer, other.sound_timer));
+		}
+		if self.audio_pattern != other.audio_pattern {
+			lines.push(format!("audio pattern: {:02X?} vs {:02X?}", self.audio_pattern, other.audio_pattern));
+		}
+		if self.audio_pitch != other.audio_pitch {
+			lines.push(format!("audio pitch: {} vs {}", self.audio_pitch, other.audio_pitch));
+		}
+		for (i, (a, b)) in self.keypad.iter().zip(other.keypad.iter()).enumerate() {
+			if a != b {
+				lines.push(format!("key {:X}: {} vs {}", i, a, b));
+			}
+		}
+		if self.waiting_for_vblank != other.waiting_for_vblank {
+			lines.push(format!("waiting for vblank: {} vs {}", self.waiting_for_vblank, other.waiting_for_vblank));
+		}
+		if self.waiting_for_key != other.waiting_for_key {
+			lines.push(format!("waiting for a key: {} vs {}", self.waiting_for_key, other.waiting_for_key));
+		}
+		if self.released_key != other.released_key {
+			lines.push(format!("released key: {:X?} vs {:X?}", self.released_key, other.released_key));
+		}
+		let pixels = self.gfx.iter().flat_map(|row| row.iter())
+			.zip(other.gfx.iter().flat_map(|row| row.iter()))
+			.filter(|&(a, b)| a != b).count();
+		if pixels > 0 {
+			lines.push(format!("screen: {} pixels differ", pixels));
+		}
+		let bytes : Vec<usize> = (0..self.ram.len().min(other.ram.len()))
+			.filter(|&i| self.ram[i] != other.ram[i]).collect();
+		for &i in bytes.iter().take(MAX_LISTED_RAM_DIFFERENCES) {
+			lines.push(format!("ram[{:03X}]: {:02X} vs {:02X}", i, self.ram[i], other.ram[i]));
+		}
+		if bytes.len() > MAX_LISTED_RAM_DIFFERENCES {
+			lines.push(format!("... and {} more bytes of ram", bytes.len() - MAX_LISTED_RAM_DIFFERENCES));
+		}
+		lines
+	}
+}
+
+/// A whole machine at one moment, from `Chip8::save`. Unlike a `Snapshot`
+/// it includes the random number generator and the frame clock, so a
+/// restored machine goes on exactly as the saved one would have.
+#[derive(Clone, Debug)]
+pub struct SavedState {
+	pub snapshot: Snapshot,
+	rng: XorShiftRng,
+	frames: u64,
+	overrun: u32
+}
+
+/// The generator CXNN uses after `CPU::seed(seed)`.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+	XorShiftRng::from_seed([seed as u32, (seed >> 32) as u32, 0x9E3779B9, 0x7F4A7C15])
+}
+
+/// One instruction every 10ms, the speed this emulator always ran at.
+pub const DEFAULT_CLOCK_SPEED : u32 = 100;
 
 impl CPU {
-	pub fn new(key_input: Receiver<(Key, bool)>, graphics_output: Sender<GFX>) -> CPU {
+	pub fn new(key_input: Receiver<(Key, bool)>, graphics_output: Sender<GFX>, config: &Config) -> CPU {
 		CPU {
 			delay_timer: 0,
 			sound_timer: 0,
+			audio_pattern: None,
+			audio_pitch: DEFAULT_PITCH,
 			gfx: [[0; 64]; 32],
 			registers: [0; 16],
 			pc: 0,
 			index: 0,
+			logging: true,
+			draw_flag: false,
+			quirks: config.quirks,
+			clock_period: Duration::from_secs(1) / config.clock_speed,
+			waiting_for_vblank: false,
+			waiting_for_key: false,
+			released_key: None,
 			keypad: [0; 16],
-			//draw_flag: false,
-			stack: Vec::new(), 
-			ram: [0; 4096],
-			key_input: key_input,
-			graphics_output: graphics_output
+			stack: Stack::new(config.stack_depth),
+			memory: Memory::new(config.address_mode, config.protect_interpreter_area),
+			block_cache: BlockCache::new(),
+			font: config.font.clone(),
+			font_address: config.font_address,
+			machine_code: config.machine_code,
+			machine_code_handler: None,
+			rng: rand::weak_rng(),
+			key_input,
+			graphics_output
 		}
 	}
 
+	/// Writes into memory on behalf of the host. Write protection and
+	/// memory hooks only apply to writes made by the program.
 	pub fn write_memory(&mut self, bytes: &[u8], address: u16) {
-		for i in 0..bytes.len() as usize {
-			self.ram[address as usize + i] = bytes[i];
-			//println!("B: {} {}", (address as usize + i), bytes[i]);
+		self.memory.load(address as usize, bytes);
+		self._invalidate_code(address as usize, bytes.len());
+	}
+
+	pub fn read_memory(&self, start: usize, end: usize) -> &[u8] {
+		&self.memory.as_slice()[start..end]
+	}
+
+	/// Registers a hook that sees every read and write made by the program.
+	pub fn add_memory_hook(&mut self, hook: MemoryHook) {
+		self.memory.add_hook(hook);
+	}
+
+	fn _store(&mut self, address: usize, bytes: &[u8]) -> Result<(), CpuError> {
+		self.memory.write(address, bytes)?;
+		self._invalidate_code(address, bytes.len());
+		Ok(())
+	}
+
+	fn _invalidate_code(&mut self, address: usize, len: usize) {
+		if address + len > RAM_SIZE {
+			//the write wrapped around to the start of memory
+			self.block_cache.invalidate(address % RAM_SIZE, RAM_SIZE);
+			self.block_cache.invalidate(0, (address + len) % RAM_SIZE);
+		} else {
+			self.block_cache.invalidate(address, len);
 		}
 	}
 
-	pub fn read_memory<'a>(&'a self, start: usize, end: usize) -> &'a [u8] {
-		&self.ram[start..end]
+	/// Seeds the generator behind CXNN so that runs can be reproduced.
+	pub fn seed(&mut self, seed: u64) {
+		self.rng = seeded_rng(seed);
 	}
 
-	pub fn emulate_cycle(&mut self) {
-			let register_x: usize;
-			let register_y: usize;
-			let mut update_gfx = false;
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			pc: self.pc,
+			index: self.index,
+			registers: self.registers,
+			stack: self.stack.entries().to_vec(),
+			delay_timer: self.delay_timer,
+			sound_timer: self.sound_timer,
+			audio_pattern: self.audio_pattern,
+			audio_pitch: self.audio_pitch,
+			keypad: self.keypad,
+			gfx: self.gfx,
+			ram: self.memory.as_slice().to_vec(),
+			waiting_for_vblank: self.waiting_for_vblank,
+			waiting_for_key: self.waiting_for_key,
+			released_key: self.released_key
+		}
+	}
 
-			let instruction = self.fetch() as usize;
-			if 0xF000 & instruction == 0x1000 {
-				self.pc = instruction as u16 & 0x0FFF;
-				return;
+	/// Puts the CPU in the state `snapshot` was taken in. Return addresses
+	/// past the depth of the stack are dropped.
+	pub fn restore(&mut self, snapshot: &Snapshot) {
+		self.pc = snapshot.pc;
+		self.index = snapshot.index;
+		self.registers = snapshot.registers;
+		self.stack = Stack::new(self.stack.depth());
+		for &address in snapshot.stack.iter() {
+			let _ = self.stack.push(address);
+		}
+		self.delay_timer = snapshot.delay_timer;
+		self.sound_timer = snapshot.sound_timer;
+		self.audio_pattern = snapshot.audio_pattern;
+		self.audio_pitch = snapshot.audio_pitch;
+		self.keypad = snapshot.keypad;
+		self.gfx = snapshot.gfx;
+		self.draw_flag = true;
+		self.write_memory(&snapshot.ram, 0);
+		self.waiting_for_vblank = snapshot.waiting_for_vblank;
+		self.waiting_for_key = snapshot.waiting_for_key;
+		self.released_key = snapshot.released_key;
+	}
+
+	/// Installs the handler 0NNN calls are dispatched to under
+	/// `MachineCodePolicy::Dispatch`.
+	pub fn set_machine_code_handler(&mut self, handler: Box<dyn MachineCodeHandler>) {
+		self.machine_code_handler = Some(handler);
+	}
+
+	pub fn tick_timers(&mut self) {
+		if self.delay_timer > 0 {
+			self.delay_timer -= 1;
+		}
+		if self.sound_timer > 0 {
+			self.sound_timer -= 1;
+		}
+	}
+
+	pub fn stack(&self) -> &Stack {
+		&self.stack
+	}
+
+	pub fn block_cache(&self) -> &BlockCache {
+		&self.block_cache
+	}
+
+	/// Whether a DXYN under the display wait quirk has stopped the CPU until
+	/// the next frame begins.
+	pub fn waiting_for_vblank(&self) -> bool {
+		self.waiting_for_vblank
+	}
+
+	/// Tells the CPU a new frame has begun, which ends any display wait.
+	pub fn vblank(&mut self) {
+		self.waiting_for_vblank = false;
+	}
+
+	/// Sends the screen to whoever is showing it if it changed since the
+	/// last time.
+	pub fn present(&mut self) {
+		if self.draw_flag {
+			self.send_frame();
+		}
+	}
+
+	/// Sends the screen to whoever is showing it, changed or not.
+	pub fn send_frame(&mut self) {
+		self.draw_flag = false;
+		if let Err(e) = self.graphics_output.send(self.gfx) {
+			self.log(format_args!("Failed to send graphics update: {:?}", e));
+		}
+	}
+
+	/// The instruction `step` would execute next.
+	pub fn next_instruction(&self) -> Result<Instruction, CpuError> {
+		let pc = self.memory.resolve(self.pc as usize)?;
+		Ok(Instruction::decode(self.memory.fetch(pc)?))
+	}
+
+	/// Fetches, decodes and executes a single instruction, without pacing
+	/// or sending graphics updates. A CPU waiting for vertical blank does
+	/// nothing.
+	pub fn step(&mut self) -> Result<(), CpuError> {
+		if self.waiting_for_vblank {
+			return Ok(());
+		}
+		self.pc = self.memory.resolve(self.pc as usize)? as u16;
+		let instruction = Instruction::decode(self.fetch()?);
+		self.execute(instruction)
+	}
+
+	/// Executes one instruction, sends the screen if it changed and sleeps
+	/// for a clock period. `Chip8::run_frame` is what paces whole frames.
+	pub fn emulate_cycle(&mut self) -> Result<(), CpuError> {
+		self.step()?;
+		self.present();
+
+		sleep(self.clock_period);
+		Ok(())
+	}
+
+	/// Runs up to `max_instructions` instructions through the block cache,
+	/// without pacing or sending graphics updates. Returns how many were
+	/// actually executed, which is fewer if the CPU starts waiting for
+	/// vertical blank; `draw_flag` is left set if the screen changed.
+	pub fn run_cached(&mut self, max_instructions: usize) -> Result<usize, CpuError> {
+		let mut executed = 0;
+		while executed < max_instructions && !self.waiting_for_vblank {
+			self.pc = self.memory.resolve(self.pc as usize)? as u16;
+			let start = self.pc;
+			let entry = self.block_cache.take(self.memory.as_slice(), start);
+			if entry.block.instructions.is_empty() {
+				//the pc is at the very end of memory, let step deal with it
+				self.block_cache.put_back(start, entry);
+				self.step()?;
+				executed += 1;
+				continue;
+			}
+			let remaining = max_instructions - executed;
+			let result = self._run_block(&entry, remaining, &mut executed);
+			self.block_cache.put_back(start, entry);
+			result?;
+		}
+		Ok(executed)
+	}
+
+	/// Like `run_cached`, but blocks that have run `HOT_BLOCK_THRESHOLD`
+	/// times are compiled to closure chains and run from then on.
+	pub fn run_recompiled(&mut self, max_instructions: usize) -> Result<usize, CpuError> {
+		let mut executed = 0;
+		while executed < max_instructions && !self.waiting_for_vblank {
+			self.pc = self.memory.resolve(self.pc as usize)? as u16;
+			let start = self.pc;
+			let mut entry = self.block_cache.take(self.memory.as_slice(), start);
+			if entry.block.instructions.is_empty() {
+				self.block_cache.put_back(start, entry);
+				self.step()?;
+				executed += 1;
+				continue;
 			}
-			else if instruction == 0x00E0 { //Clears the screen.
+			let remaining = max_instructions - executed;
+			let result = match entry.compiled {
+				Some(ref compiled) if compiled.len <= remaining => {
+					self.block_cache.record_compiled_run();
+					executed += compiled.len;
+					compiled.run(self)
+				},
+				_ => {
+					entry.hits += 1;
+					if entry.hits == recompiler::HOT_BLOCK_THRESHOLD {
+						entry.compiled = Some(Arc::new(recompiler::compile(&entry.block)));
+					}
+					self._run_block(&entry, remaining, &mut executed)
+				}
+			};
+			self.block_cache.put_back(start, entry);
+			result?;
+		}
+		Ok(executed)
+	}
+
+	fn _run_block(&mut self, entry: &Entry, remaining: usize, executed: &mut usize) -> Result<(), CpuError> {
+		for &instruction in entry.block.instructions.iter().take(remaining) {
+			self.execute(instruction)?;
+			*executed += 1;
+		}
+		Ok(())
+	}
+
+	pub fn execute(&mut self, instruction: Instruction) -> Result<(), CpuError> {
+		match instruction {
+			Instruction::MachineCall(address) => {
+				self.log(format_args!("Machine code call to {:X}", address));
+				match self.machine_code {
+					MachineCodePolicy::Ignore => (),
+					MachineCodePolicy::Error => return Err(CpuError::MachineCodeCall(address)),
+					MachineCodePolicy::Dispatch => {
+						let mut handler = match self.machine_code_handler.take() {
+							Some(handler) => handler,
+							None => return Err(CpuError::MachineCodeCall(address))
+						};
+						let result = handler.call(address, self);
+						self.machine_code_handler = Some(handler);
+						result?;
+					}
+				}
+			},
+			Instruction::ClearScreen => {
 				self.gfx = [[0; 64]; 32];
-				update_gfx = true;
+				self.draw_flag = true;
 				self.log_str("Screen now clear. GFX array zeroed-out");
-			}
-			else if instruction == 0x00EE { //Returns from a subroutine.
-				let new_pc = self.stack.pop().unwrap();
-				self.log_string(format!("Returning from subroutine. pc {:X} -> {:X}", 
+			},
+			Instruction::Return => {
+				let new_pc = self.stack.pop()?;
+				self.log(format_args!("Returning from subroutine. pc {:X} -> {:X}",
 					self.pc, new_pc));
 				self.pc = new_pc;
-				return;
-			}
-			else if instruction & 0xF000 == 0x1000 {
-				let address = instruction & 0x0FFF;
-				self.pc = address as u16;
-				self.log_string(format!("Jumping to address {:X}", self.pc));
-				return;
-			}
-			else if instruction & 0xF000 == 0x2000 { //Calls subroutine at NNN.
-				let sub = instruction & 0x0FFF;
-				self.log_string(format!("Saving pc {:X} and jumping to {:X}", self.pc, sub));
-				self.stack.push(self.pc);
-				self.pc = sub as u16;
-				return;
-			} 
-			else if instruction & 0xF000 == 0x3000 {
-				// 3XNN	//Skips the next instruction if VX equals NN.
-				let register = (instruction & 0x0F00) >> 8;
-				let n = (0x00FF & instruction) as u8;
-				self.log_string(format!("Checking whether register at {} is equal to {}", 
+				return Ok(());
+			},
+			Instruction::Jump(address) => {
+				self.pc = address;
+				self.log(format_args!("Jumping to address {:X}", self.pc));
+				return Ok(());
+			},
+			Instruction::Call(sub) => {
+				self.log(format_args!("Saving pc {:X} and jumping to {:X}", self.pc, sub));
+				self.stack.push(self.pc + 2)?;
+				self.pc = sub;
+				return Ok(());
+			},
+			Instruction::SkipEqualByte(register, n) => {
+				self.log(format_args!("Checking whether register at {} is equal to {}",
 					register, n));
-
 				if self.registers[register] == n {
 					self.pc += 2;
 				}
-			}
-			else if instruction & 0xF000 == 0x4000 {
-				//  4XNN	Skips the next instruction if VX doesn't equal NN.
-				let register = (instruction & 0x0F00) >> 8;
-				let n = (0x00FF & instruction) as u8;
-				self.log_string(format!("Checking whether register at {:X} is NOT equal to {:X}", 
+			},
+			Instruction::SkipNotEqualByte(register, n) => {
+				self.log(format_args!("Checking whether register at {:X} is NOT equal to {:X}",
 					register, n));
 				if self.registers[register] != n {
 					self.pc += 2;
 				}
-			}
-			else if instruction & 0xF00F == 0x5000 { 
-				// 5XY0 //Skips the next instruction if VX equals VY.
-				register_x = (0x0F00 & instruction) >> 8;
-				register_y = (0x00F0 & instruction) >> 4;
-				self.log_string(format!("Checking whether register at {:X} is equal to register at {:X}", 
+			},
+			Instruction::SkipEqualRegister(register_x, register_y) => {
+				self.log(format_args!("Checking whether register at {:X} is equal to register at {:X}",
 					register_x, register_y));
 				if self.registers[register_x] == self.registers[register_y] {
 					self.pc += 2;
 				}
-			}	
-			else if instruction & 0xF000 == 0x6000 {
-				//6XNN	Sets VX to NN.
-				register_x = (instruction & 0x0F00) >> 8;
-				println!("{:X} is setting register {:X} to {:X}",instruction, register_x, (instruction & 0x00FF));
-				self.log_string(format!("Setting register at {} to {}", 
-					register_x, (instruction & 0x00FF)));
-				self.registers[register_x] = (instruction & 0x00FF) as u8;
-			}
-			else if instruction & 0xF000 == 0x7000 {
-				//7XNN	Adds NN to VX.
-				register_x = (instruction & 0x0F00) >> 8;
-				let n = (instruction & 0x00FF) as u8;
-				self.log_string(format!("Adding {} to {:X}", register_x, n));
-				self.registers[register_x] = self.registers[register_x].overflowing_add(n).0; 
-			}
-			else if instruction & 0xF00F == 0x8000 { 
-				//8XY0	Sets VX to the value of VY. 
-				register_x = (instruction & 0x0F00) >> 8;
-				register_y = (instruction & 0x00F0) >> 4;
-				self.log_string(format!("Setting {:X} to {:X}", register_x, register_y));
+			},
+			Instruction::LoadByte(register_x, n) => {
+				self.log(format_args!("Setting register at {} to {}", register_x, n));
+				self.registers[register_x] = n;
+			},
+			Instruction::AddByte(register_x, n) => {
+				self.log(format_args!("Adding {} to {:X}", register_x, n));
+				self.registers[register_x] = self.registers[register_x].overflowing_add(n).0;
+			},
+			Instruction::Move(register_x, register_y) => {
+				self.log(format_args!("Setting {:X} to {:X}", register_x, register_y));
 				self.registers[register_x] = self.registers[register_y];
-			}
-			else if instruction & 0xF00F == 0x8001 { 
-				//8XY1	Sets VX to VX or VY.
-				register_x = (instruction & 0x0F00) >> 8;
-				register_y = (instruction & 0x00F0) >> 4;
-				self.log_string(format!("Bitwise ORing {:X} with {:X}", register_x, register_y));
-				self.registers[register_x] = self.registers[register_x] | self.registers[register_y];
-			}
-			else if instruction & 0xF00F == 0x8002 {
-				//8XY2	Sets VX to VX and VY.
-				register_x = (instruction & 0x0F00) >> 8;
-				register_y = (instruction & 0x00F0) >> 4;
-				self.log_string(format!("Bitwise ANDing {:X} with {:X}", register_x, register_y));
-				self.registers[register_x] = self.registers[register_x] & self.registers[register_y];
-			}
-			else if instruction & 0xF00F == 0x8003 {
-				//8XY3	Sets VX to VX xor VY.
-				register_x = (instruction & 0x0F00) >> 8;
-				register_y = (instruction & 0x00F0) >> 4;
-				self.log_string(format!("Bitwise XORing {:X} with {:X}", register_x, register_y));
-				self.registers[register_x] = self.registers[register_x] ^ self.registers[register_y];
-			}
-			else if instruction & 0xF00F == 0x8004 {
-				//8XY4	Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there isn't.
-				register_x = (instruction & 0x0F00) >> 8;
-				register_y = (instruction & 0x00F0) >> 4;
-				let result = self.registers[register_x].overflowing_add(self.registers[register_y]);
-				self.registers[register_x] =  result.0;
-				self.ram[0xF] = match result.1 {
-					 true => 1,
-					 false => 0
-				};
-				self.log_string(format!("Adding {:X} to {:X}", register_y, register_x));
-			}
-			else if instruction & 0xF00F == 0x8005 {
-				/*8XY5 VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there isn't.*/
-				let register_x = (instruction & 0x0F00) >> 8;
-				let register_y = (instruction & 0x00F0) >> 4;
-				let val = self.registers[register_x];
-				let result = val - self.registers[register_y];
-				self.registers[register_x] = result;
-				self.log_string(format!("Subtracting {:X} from {:X}: {}-{}", register_y, register_x, self.registers[register_x], self.registers[register_y]));
-				if result < val {
+			},
+			Instruction::Or(register_x, register_y) => {
+				self.log(format_args!("Bitwise ORing {:X} with {:X}", register_x, register_y));
+				self.registers[register_x] |= self.registers[register_y];
+				if self.quirks.logic_resets_vf {
 					self.registers[0xF] = 0;
 				}
-				else {
-					self.registers[0xF] = 1;
-				}
-				self.log_string(format!("Result is {:?}", result));
-			}
-			else if instruction & 0xF00F == 0x8006 {
-				/* 8XY6 Shifts VX right by one. VF is set to the value of the least significant bit of VX before the shift.[2]*/
-				register_x = (instruction & 0x0F00) >> 8;
-				self.registers[0xF] = self.registers[register_x] & 1;
-				self.registers[register_x] = self.registers[register_x] >> 1;
-			}
-			else if instruction & 0xF00F == 0x8007 {
-				/*8XY7	Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there isn't.*/
-				let register_x = (instruction & 0x0F00) >> 8;
-				let register_y = (instruction & 0x00F0) >> 4;
-				let val = self.registers[register_x];
-				let result = self.registers[register_y] - val;
-				self.registers[register_x] = result;
-				if result < val {
+			},
+			Instruction::And(register_x, register_y) => {
+				self.log(format_args!("Bitwise ANDing {:X} with {:X}", register_x, register_y));
+				self.registers[register_x] &= self.registers[register_y];
+				if self.quirks.logic_resets_vf {
 					self.registers[0xF] = 0;
 				}
-				else {
-					self.registers[0xF] = 1;
+			},
+			Instruction::Xor(register_x, register_y) => {
+				self.log(format_args!("Bitwise XORing {:X} with {:X}", register_x, register_y));
+				self.registers[register_x] ^= self.registers[register_y];
+				if self.quirks.logic_resets_vf {
+					self.registers[0xF] = 0;
 				}
-			}
-			else if instruction & 0xF00F == 0x800E {
-				/*8XYE	Shifts VX left by one. VF is set to the 
-				value of the most significant bit of VX before the shift.[2]*/
-				register_x = (instruction & 0x0F00) >> 8;
-				let val = self.registers[register_x];
-				self.registers[0xF] = (val & 129) >> 7;
-				self.registers[register_x] = self.registers[register_x] << 1;
-			}
-			else if instruction & 0xF00F == 0x9000 {
-				/*0x9XY0	Skips the next instruction if VX doesn't equal VY.*/
-				register_x = (instruction & 0x0F00) >> 8;
-				register_y = (instruction & 0x00F0) >> 4;
+			},
+			Instruction::AddRegister(register_x, register_y) => {
+				//VF is set to 1 when there's a carry, and to 0 when there isn't.
+				//VF is written last, so the flag wins when VX is VF.
+				let (result, carry) = self.registers[register_x].overflowing_add(self.registers[register_y]);
+				self.registers[register_x] = result;
+				self.registers[0xF] = carry as u8;
+				self.log(format_args!("Adding {:X} to {:X}", register_y, register_x));
+			},
+			Instruction::SubRegister(register_x, register_y) => {
+				//VF is set to 0 when there's a borrow, and 1 when there isn't.
+				let (result, borrow) = self.registers[register_x].overflowing_sub(self.registers[register_y]);
+				self.log(format_args!("Subtracting {:X} from {:X}: {}-{}", register_y, register_x,
+					self.registers[register_x], self.registers[register_y]));
+				self.registers[register_x] = result;
+				self.registers[0xF] = !borrow as u8;
+				self.log(format_args!("Result is {:?}", result));
+			},
+			Instruction::ShiftRight(register_x, register_y) => {
+				//VF is set to the value of the least significant bit of VX before the shift.
+				let val = if self.quirks.shift_uses_vy {
+					self.registers[register_y]
+				} else {
+					self.registers[register_x]
+				};
+				self.registers[register_x] = val >> 1;
+				self.registers[0xF] = val & 1;
+			},
+			Instruction::SubReverse(register_x, register_y) => {
+				//VF is set to 0 when there's a borrow, and 1 when there isn't.
+				let (result, borrow) = self.registers[register_y].overflowing_sub(self.registers[register_x]);
+				self.registers[register_x] = result;
+				self.registers[0xF] = !borrow as u8;
+			},
+			Instruction::ShiftLeft(register_x, register_y) => {
+				//VF is set to the value of the most significant bit of VX before the shift.
+				let val = if self.quirks.shift_uses_vy {
+					self.registers[register_y]
+				} else {
+					self.registers[register_x]
+				};
+				self.registers[register_x] = val << 1;
+				self.registers[0xF] = val >> 7;
+			},
+			Instruction::SkipNotEqualRegister(register_x, register_y) => {
 				if self.registers[register_x] != self.registers[register_y] {
 					self.pc += 2;
 				}
-			}
-			else if instruction & 0xF000 == 0xA000 {
-				/*ANNN	Sets I to the address NNN.*/
-				self.index = (instruction & 0x0FFF) as u16;
-			}
-			else if instruction & 0xF000 == 0xB000 {
-				//BNNN	Jumps to the address NNN plus V0.
-				self.pc = (0x0FFF & instruction as u16) + self.registers[0] as u16;
-				return;
-			} 
-			else if instruction & 0xF000 == 0xC000 {
-				//CXNN	Sets VX to the result of a bitwise and operation on a random number and NN.
-				let between = Range::new(0, 0xFF);
-				let mut rng = rand::thread_rng();
-				let n = (instruction & 0x00FF) as u8;
-				register_x = (instruction & 0x0F00) >> 8;
-				self.registers[register_x] = (n & between.ind_sample(&mut rng)) as u8;
-			} 
-			else if instruction & 0xF000 == 0xD000 {
-				// DXYN	Sprites stored in memory at location in index register (I), 
-				//8bits wide. Wraps around the screen. If when drawn, clears a 
-				//pixel, register VF is set to 1 otherwise it is zero. All drawing 
-				//is XOR drawing (i.e. it toggles the screen pixels). Sprites are 
-				//drawn starting at position VX, VY. N is the number of 8bit rows 
-				//that need to be drawn. If N is greater than 1, second line 
-				//continues at position VX, VY+1, and so on.
-				register_x = (instruction >> 8) & 0xF;
-				register_y = (instruction >> 4) & 0xF;
-                let max_height = if instruction & 0xF > 5 {
-                    5
-                } else {
-                    instruction & 0xF
-                };
-				let mut reg_0xf = 0;
-                let px = self.registers[register_x];
-                let py = self.registers[register_y];
-                for y in 0..max_height {
-                    let row = self.ram[self.index as usize + y] >> 4;
-                    for x in 0..8 {
-                        let pixel = row >> x as u8 & 1;
-                        let xi = (px as usize + x) % 64;
-                        let yi = (py as usize + y) % 32;
-                        let original_pixel = self.gfx[yi][xi];
-                        if pixel != 0 {
-                            if original_pixel == 0 {
-                                self.gfx[yi][xi] = 1;
-                            } else {
-                                reg_0xf = 1;
-                                self.gfx[yi][xi] = 0;
-                            }
-                        } else {
-                            self.gfx[yi][xi] = 0;
-                        }
-                    }
-                }
-				update_gfx = true;
-				self.registers[0xF] = reg_0xf;
-			} 
-			else if instruction & 0xF0FF == 0xE09E {
-				//EX9E	Skips the next instruction if the key stored in VX is pressed.
-				register_x = (instruction & 0x0F00) >> 8;
-				let key_index = self.registers[register_x];
+			},
+			Instruction::LoadIndex(address) => {
+				self.index = address;
+			},
+			Instruction::JumpOffset(address) => {
+				let register = if self.quirks.jump_uses_vx { (address >> 8) as usize & 0xF } else { 0 };
+				self.pc = address + self.registers[register] as u16;
+				return Ok(());
+			},
+			Instruction::Random(register_x, n) => {
+				self.registers[register_x] = self.rng.gen::<u8>() & n;
+			},
+			Instruction::Draw(register_x, register_y, height) => {
+				//Sprites are N rows of 8 pixels read from I, the most significant
+				//bit leftmost, XORed onto the screen at VX, VY. The position
+				//wraps, and the sprite itself is cut off at the edges or wraps
+				//as well depending on the quirks. VF is set if any pixel was
+				//turned off.
+				let px = self.registers[register_x] as usize % 64;
+				let py = self.registers[register_y] as usize % 32;
+				let mut collision = 0;
+				for y in 0..height {
+					let row = self.memory.read(self.index as usize + y)?;
+					for x in 0..8 {
+						if row & (0x80 >> x) == 0 {
+							continue;
+						}
+						if self.quirks.clip_sprites && (px + x >= 64 || py + y >= 32) {
+							continue;
+						}
+						let pixel = &mut self.gfx[(py + y) % 32][(px + x) % 64];
+						collision |= *pixel;
+						*pixel ^= 1;
+					}
+				}
+				self.draw_flag = true;
+				self.registers[0xF] = collision;
+				if self.quirks.display_wait {
+					self.waiting_for_vblank = true;
+				}
+			},
+			Instruction::SkipKeyPressed(register_x) => {
+				//only the low nibble picks a key, as on the VIP
+				let key_index = self.registers[register_x] & 0xF;
 				if self.keypad[key_index as usize] != 0 {
 					self.pc += 2;
 				}
-			} 
-			else if instruction & 0xF0FF == 0xE0A1 {
-				//EXA1	Skips the next instruction if the key stored in VX isn't pressed.
-				register_x = (instruction & 0x0F00) >> 8;
-				let key_index = self.registers[register_x];
+			},
+			Instruction::SkipKeyNotPressed(register_x) => {
+				let key_index = self.registers[register_x] & 0xF;
 				if self.keypad[key_index as usize] == 0 {
 					self.pc += 2;
 				}
-			} 
-			else if instruction & 0xF0FF == 0xF007 {
-				//FX07 Sets VX to the value of the delay timer.
-				register_x = (instruction & 0x0F00) >> 8;
+			},
+			Instruction::LoadDelay(register_x) => {
 				self.registers[register_x] = self.delay_timer;
-			}	
-			else if instruction & 0xF0FF == 0xF00A {
-				// FX0A	A key press is awaited, and then stored in VX.
-				register_x = (instruction & 0x0F00) >> 8;
-				self.log_string(format!("Waiting for a key press to store in {}", register_x));
-				let key = self.key_input.recv();
-				match key {
-					Ok(k) => {
-						println!("Key pressed is {:?}", key);
-						self.registers[register_x] = k.0.to_byte();
+			},
+			Instruction::WaitKey(register_x) => {
+				//a key only counts once it is let go of, as on the VIP, and
+				//until then the pc stays here so FX0A runs again
+				if !self.waiting_for_key {
+					self.log(format_args!("Waiting for a key press to store in {}", register_x));
+					self.waiting_for_key = true;
+					self.released_key = None;
+				}
+				match self.released_key.take() {
+					Some(key) => {
+						self.log(format_args!("Key pressed is {:X}", key));
+						self.registers[register_x] = key;
+						self.waiting_for_key = false;
 					},
-					_ => println!("Error while receiving input message")
+					None => return Ok(())
+				}
+			},
+			Instruction::SetDelay(register_x) => {
+				self.delay_timer = self.registers[register_x];
+			},
+			Instruction::SetSound(register_x) => {
+				self.log(format_args!("Setting sound timer to value of register {}: {:X}",
+					register_x, self.registers[register_x]));
+				self.sound_timer = self.registers[register_x];
+			},
+			Instruction::LoadAudioPattern => {
+				let mut pattern = [0; PATTERN_BYTES];
+				for (j, byte) in pattern.iter_mut().enumerate() {
+					*byte = self.memory.read(self.index as usize + j)?;
 				}
-			} 
-			else if instruction & 0xF0FF == 0xF015 {
-				//  FX15	Sets the delay timer to VX.
-				register_x = (instruction & 0x0F00) >> 8;
-				self.delay_timer = self.registers[register_x] as u8;
-			} 
-			else if instruction & 0xF0FF == 0xF018 {
-				// FX18	Sets the sound timer to VX.
-				register_x = (instruction & 0x0F00) >> 8;
-				self.log_string(format!("Setting sound timer to value of register {}: {:X}", register_x, self.registers[register_x]));
-				self.sound_timer = self.registers[register_x] as u8;
-			} 
-			else if instruction & 0xF0FF == 0xF01E {
-				//FX1E	Adds VX to I.[3]
-				register_x = (0x0F00 & instruction) >> 8; 
-				self.index = self.index + self.registers[register_x] as u16;
-			} 
-			else if instruction & 0xF0FF == 0xF029 {
-				// FX29	Sets I to the location of the sprite for the character in VX. 
+				self.audio_pattern = Some(pattern);
+			},
+			Instruction::SetPitch(register_x) => {
+				self.audio_pitch = self.registers[register_x];
+			},
+			Instruction::AddIndex(register_x) => {
+				self.index = self.index.wrapping_add(self.registers[register_x] as u16);
+			},
+			Instruction::LoadSprite(register_x) => {
 				// characters 0-F (in hexadecimal) are represented by a 4x5 font.
-				register_x = (0x0F00 & instruction) >> 8; 
-				let sprite_index = self.registers[register_x];
-				self.index = SPRITE_OFFSET as u16 + (sprite_index as u16 * 5);
-				println!("Sprite requested: {} {}", sprite_index, self.index);
-			} 
-			else if instruction & 0xF0FF == 0xF033 {
-				// FX33	Stores the Binary-coded decimal representation of VX, 
-				//with the most significant of three digits at the address in 
-				//I, the middle digit at I plus 1, and the least significant digit at I plus 2. 
-				//(In other words, take the decimal representation of VX, place the hundreds 
-				//digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2.)
-				register_x = (0x0F00 & instruction) >> 8; 
+				let sprite_index = self.registers[register_x] & 0xF;
+				self.index = self.font_address + (sprite_index as u16 * SMALL_GLYPH_SIZE as u16);
+				self.log(format_args!("Sprite requested: {} {}", sprite_index, self.index));
+			},
+			Instruction::LoadLargeSprite(register_x) => {
+				// the 8x10 digits follow right after the small font, if there are any
+				if self.font.large.is_none() {
+					let opcode = 0xF030 | (register_x as u16) << 8;
+					return self.execute(Instruction::Unknown(opcode));
+				}
+				let sprite_index = self.registers[register_x] & 0xF;
+				self.index = self.font_address + self.font.small.len() as u16 +
+					(sprite_index as u16 * LARGE_GLYPH_SIZE as u16);
+			},
+			Instruction::StoreBcd(register_x) => {
+				//The most significant of three digits goes at the address in I,
+				//the middle digit at I plus 1, and the least significant digit at I plus 2.
 				let i = self.index;
 				let val = self.registers[register_x];
 				let ones: u8 = val % 10;
 				let tens : u8 = (val % 100) / 10;
 				let hundreds: u8 = val / 100;
-				println!("Writing {} {} {} to {}", hundreds, tens, ones, i);
-				self.write_memory(&[hundreds, tens, ones], i);
-			} 
-			else if instruction & 0xF0FF == 0xF055 {
-				// FX55	Stores V0 to VX (including VX) in memory starting at address I.[4]
-				register_x = (instruction & 0x0F00) >> 8;
-				for j in 0..(register_x + 1) {
-					self.ram[self.index as usize + j] = self.registers[j];
+				self.log(format_args!("Writing {} {} {} to {}", hundreds, tens, ones, i));
+				self._store(i as usize, &[hundreds, tens, ones])?;
+			},
+			Instruction::StoreRegisters(register_x) => {
+				let registers = self.registers;
+				let i = self.index;
+				self._store(i as usize, &registers[0..register_x + 1])?;
+				if self.quirks.load_store_increments_index {
+					self.index = i.wrapping_add(register_x as u16 + 1);
 				}
-			}
-			else if instruction & 0xF0FF == 0xF065 {
-				// FX65	Fills V0 to VX (including VX) with values from memory starting at address I.[4]
-				let k = (instruction & 0x0F00) >> 8 as usize;
-				for j in 0..(k + 1) {
-					self.registers[j] = self.ram[self.index as usize + j as usize]
+			},
+			Instruction::LoadRegisters(register_x) => {
+				for j in 0..(register_x + 1) {
+					self.registers[j] = self.memory.read(self.index as usize + j)?;
 				}
-			} 
-			else {
-				panic!("Unknown instruction at {}: {:X}", self.pc, instruction);
-			}
-			self.pc += 2;
-
-			if update_gfx {
-				let mut gfx_out = self.gfx.clone();/*[0; 32 * 64];
-				for i in 0..self.gfx.len() {
-					gfx_out[i] = self.gfx[i];
-				}*/
-				if let Err(e) = self.graphics_output.send(gfx_out) {
-					println!("Failed to send graphics update: {:?}", e);
-                    process::exit(1);
+				if self.quirks.load_store_increments_index {
+					self.index = self.index.wrapping_add(register_x as u16 + 1);
 				}
+			},
+			Instruction::Unknown(opcode) => {
+				return Err(CpuError::UnknownInstruction(opcode));
 			}
+		}
+		self.pc += 2;
+		Ok(())
+	}
 
-			sleep(Duration::from_millis(CLOCK_PERIOD_MILLIS));
-		}		
+	/// Presses or releases a key directly, as if the frontend had sent it.
+	pub fn set_key(&mut self, key: Key, pressed: bool) {
+		let index = key.to_byte() as usize;
+		if !pressed && self.keypad[index] != 0 {
+			self.released_key = Some(key.to_byte());
+		}
+		self.keypad[index] = if pressed { 1 } else { 0 };
+	}
 
-	pub fn deal_with_input(&mut self) {
-		//TODO: Make this do stuff
-		let key = self.key_input.try_recv();
-		self._deal_with_keypress(key);
+	/// Takes in every key press and release since the last call. Returns
+	/// false once the frontend sending them has gone away.
+	pub fn deal_with_input(&mut self) -> bool {
+		loop {
+			let key = self.key_input.try_recv();
+			if let Err(TryRecvError::Disconnected) = key {
+				return false;
+			}
+			let done = key.is_err();
+			self._deal_with_keypress(key);
+			if done {
+				return true;
+			}
+		}
 	}
 
 	fn _deal_with_keypress<E>(&mut self, input: Result<(Key, bool), E>) {
 		if input.is_ok() {
 			let unwrapped_input = input.ok().unwrap();
-			println!("Input detected: {:?} {}", unwrapped_input, unwrapped_input.0.to_byte());
-			match unwrapped_input {		
-				(k, true) => self.keypad[k.to_byte() as usize] = 1,
-				(k, false) => self.keypad[k.to_byte() as usize] = 0
-			}
+			self.log(format_args!("Input detected: {:?} {}", unwrapped_input, unwrapped_input.0.to_byte()));
+			self.set_key(unwrapped_input.0, unwrapped_input.1);
 		}
 		else {
-			println!("No input");
+			self.log_str("No input");
 		}
 	}
 
-	fn fetch(&mut self) -> u16 {
-		let i1 = self.ram[self.pc as usize] as u16;
-		let i2 = self.ram[self.pc as usize + 1] as u16;
-		let opcode = (i1 << 8) | i2;
-		println!("OP: {} {:?} {}", format!("{:X} {:X}", self.pc, opcode), 
-			&self.registers, self.index);
-		return opcode;
+	fn fetch(&mut self) -> Result<u16, CpuError> {
+		let opcode = self.memory.fetch(self.pc as usize)?;
+		self.log(format_args!("OP: {:X} {:X} {:?} {}", self.pc, opcode,
+			&self.registers, self.index));
+		Ok(opcode)
 	}
 
 	fn _initialise_memory(&mut self) {
-		//TODO make this actually do something
-		//Needs to load sprites
-		let built_in_sprites = [
-			//0
-			0xF0,
-			0x90,
-			0x90,
-			0x90,
-			0xF0,
-			//1
-			0x20,
-			0x60,
-			0x20,
-			0x20,
-			0x70,
-			//2
-			0xF0,
-			0x10,
-			0xF0,
-			0x80,
-			0xF0,
-			//3
-			0xF0,
-			0x10,
-			0xF0,
-			0x10,
-			0xF0,
-			//4
-			0x90,
-			0x90,
-			0xF0,
-			0x10,
-			0x10,
-			//5
-			0xF0,
-			0x80,
-			0xF0,
-			0x10,
-			0xF0,
-			//6
-			0xF0,
-			0x80,
-			0xF0,
-			0x90,
-			0xF0,
-			//7
-			0xF0,
-			0x10,
-			0x20,
-			0x40,
-			0x40,
-			//8
-			0xF0,
-			0x90,
-			0xF0,
-			0x90,
-			0xF0,
-			//9
-			0xF0,
-			0x90,
-			0xF0,
-			0x10,
-			0xF0,
-			//A
-			0xF0,
-			0x90,
-			0xF0,
-			0x90,
-			0x90,
-			//B
-			0xE0,
-			0x90,
-			0xE0,
-			0x90,
-			0xE0,
-			//C
-			0xF0,
-			0x80,
-			0x80,
-			0x80,
-			0xF0,
-			//D
-			0xE0,
-			0x90,
-			0x90,
-			0x90,
-			0xE0,
-			//E
-			0xF0,
-			0x80,
-			0xF0,
-			0x80,
-			0xF0,
-			//F
-			0xF0,
-			0x80,
-			0xF0,
-			0x80,
-			0x80
-		];
-		
-		for j in 0..built_in_sprites.len() {
-			self.ram[SPRITE_OFFSET + j] = built_in_sprites[j];
-		}
-
-		println!("Ram: {:?}", &self.ram[0..512]);
-	}
-
-	fn log_string(&self, line: String) {
-		println!("{}", line);
+		let font_address = self.font_address as usize;
+		self.memory.load(font_address, &self.font.small);
+		if let Some(ref large) = self.font.large {
+			self.memory.load(font_address + self.font.small.len(), large);
+		}
+
+		self.log(format_args!("Ram: {:?}", &self.memory.as_slice()[0..PROGRAM_START]));
+	}
+
+	fn log(&self, args: fmt::Arguments) {
+		if self.logging {
+			println!("{}", args);
+		}
 	}
 
 	fn log_str(&self, line: &str) {
-		println!("{}", line);
+		if self.logging {
+			println!("{}", line);
+		}
 	}
 }
 
 impl Chip8 {
 	pub fn new(key_input: Receiver<(Key, bool)>, graphics_output: Sender<GFX>) -> Chip8 {
+		Chip8::with_config(key_input, graphics_output, Config::default())
+	}
+
+	pub fn with_config(key_input: Receiver<(Key, bool)>, graphics_output: Sender<GFX>,
+		config: Config) -> Chip8 {
 		Chip8 {
-			cpu: CPU::new(key_input, graphics_output)
+			cpu: CPU::new(key_input, graphics_output, &config),
+			audio: Audio::new(config.buzzer.clone()),
+			clock_speed: config.clock_speed,
+			timing: config.timing,
+			present_at_vblank: config.present_at_vblank,
+			frames: 0,
+			overrun: 0
 		}
 	}
 
 	pub fn load(&mut self, cartridge: &[u8]) {
 		self.cpu._initialise_memory();
-		self.cpu.write_memory(cartridge, 512);
-		self.cpu.pc = 512;
+		self.cpu.write_memory(cartridge, PROGRAM_START as u16);
+		self.cpu.pc = PROGRAM_START as u16;
+	}
+
+	pub fn save(&self) -> SavedState {
+		SavedState {
+			snapshot: self.cpu.snapshot(),
+			rng: self.cpu.rng.clone(),
+			frames: self.frames,
+			overrun: self.overrun
+		}
+	}
+
+	/// Puts the machine back in a state from `save`. The sound being played
+	/// carries on from where it is.
+	pub fn restore(&mut self, state: &SavedState) {
+		self.cpu.restore(&state.snapshot);
+		self.cpu.rng = state.rng.clone();
+		self.frames = state.frames;
+		self.overrun = state.overrun;
 	}
 
+	/// Advances the machine by one 60 Hz timer period: the buzzer sounds for
+	/// the period if the sound timer is running, then both timers count down
+	/// and the next frame begins.
+	pub fn tick(&mut self) {
+		let pitch = self.cpu.audio_pitch;
+		let pattern = self.cpu.audio_pattern.map(|bits| Pattern { bits, pitch });
+		self.audio.frame(self.cpu.sound_timer > 0, pattern.as_ref());
+		self.cpu.tick_timers();
+		self.cpu.vblank();
+		self.frames += 1;
+	}
+
+	/// How many instructions the clock speed allows in the current frame.
+	/// Speeds that aren't a multiple of 60 Hz spread the remainder evenly.
+	pub fn instructions_per_frame(&self) -> usize {
+		let speed = self.clock_speed as u64;
+		let frame = self.frames % TIMER_HZ as u64;
+		(speed * (frame + 1) / TIMER_HZ as u64 - speed * frame / TIMER_HZ as u64) as usize
+	}
+
+	/// Emulates one 60 Hz frame without pacing: runs the frame's
+	/// instructions, sending the screen after every change to it, or once
+	/// at the end of the frame if the config presents at vertical blank,
+	/// then ticks. A display wait ends the frame's instructions early.
+	/// Returns how many instructions ran.
+	pub fn run_frame(&mut self) -> Result<usize, CpuError> {
+		let executed = match self.timing {
+			Timing::Uniform => self._run_instructions()?,
+			Timing::Vip => self._run_cycles()?
+		};
+		if self.present_at_vblank {
+			self.cpu.send_frame();
+		}
+		self.tick();
+		Ok(executed)
+	}
+
+	fn _run_instructions(&mut self) -> Result<usize, CpuError> {
+		let mut executed = 0;
+		let budget = self.instructions_per_frame();
+		while executed < budget && !self.cpu.waiting_for_vblank() {
+			self.cpu.step()?;
+			executed += 1;
+			if !self.present_at_vblank {
+				self.cpu.present();
+			}
+		}
+		Ok(executed)
+	}
+
+	/// Runs instructions until they have used up the cycles the VIP had for
+	/// the program in a frame. An instruction that doesn't fit finishes in
+	/// the next frame's time.
+	fn _run_cycles(&mut self) -> Result<usize, CpuError> {
+		let mut executed = 0;
+		let mut cycles = self.overrun;
+		while cycles < VIP_PROGRAM_CYCLES && !self.cpu.waiting_for_vblank() {
+			let instruction = self.cpu.next_instruction()?;
+			let (pc, registers) = (self.cpu.pc, self.cpu.registers);
+			self.cpu.step()?;
+			let skipped = self.cpu.pc.wrapping_sub(pc) == 4;
+			cycles += timing::vip_cycles(instruction, &registers, skipped);
+			executed += 1;
+			if !self.present_at_vblank {
+				self.cpu.present();
+			}
+		}
+		self.overrun = cycles.saturating_sub(VIP_PROGRAM_CYCLES);
+		Ok(executed)
+	}
+
+	/// Runs frames at 60 Hz until the program stops or the frontend goes
+	/// away.
 	pub fn run(&mut self) {
+		let frame_period = Duration::from_secs(1) / TIMER_HZ;
+		let mut next_frame = Instant::now() + frame_period;
 		loop {
-			self.cpu.emulate_cycle();
-			self.cpu.deal_with_input();
+			if !self.cpu.deal_with_input() {
+				return;
+			}
+			if let Err(e) = self.run_frame() {
+				println!("Emulator stopped at {:X}: {}", self.cpu.pc, e);
+				return;
+			}
+			let now = Instant::now();
+			if next_frame > now {
+				sleep(next_frame - now);
+				next_frame += frame_period;
+			} else {
+				//too slow to keep up, so don't try to catch up either
+				next_frame = now + frame_period;
+			}
 		}
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Key {
 	K0,K1,K2,K3,K4,K5,K6,K7,K8,K9,
 	A,B,C,D,E,F
@@ -578,24 +934,47 @@ unsafe impl Sync for Key {
 
 
 impl Key {
+	pub fn from_byte(byte: u8) -> Option<Key> {
+		let key = match byte {
+			0x0 => Key::K0,
+			0x1 => Key::K1,
+			0x2 => Key::K2,
+			0x3 => Key::K3,
+			0x4 => Key::K4,
+			0x5 => Key::K5,
+			0x6 => Key::K6,
+			0x7 => Key::K7,
+			0x8 => Key::K8,
+			0x9 => Key::K9,
+			0xA => Key::A,
+			0xB => Key::B,
+			0xC => Key::C,
+			0xD => Key::D,
+			0xE => Key::E,
+			0xF => Key::F,
+			_ => return None
+		};
+		Some(key)
+	}
+
 	pub fn to_byte(&self) -> u8 {
-		match self {
-			&Key::K0 => 0x0,
-			&Key::K1 => 0x1,
-			&Key::K2 => 0x2,
-			&Key::K3 => 0x3,
-			&Key::K4 => 0x4,
-			&Key::K5 => 0x5,
-			&Key::K6 => 0x6,
-			&Key::K7 => 0x7,
-			&Key::K8 => 0x8,
-			&Key::K9 => 0x9,
-			&Key::A => 0xA,
-			&Key::B => 0xB,
-			&Key::C => 0xC,
-			&Key::D => 0xD,
-			&Key::E => 0xE,
-			&Key::F => 0xF
+		match *self {
+			Key::K0 => 0x0,
+			Key::K1 => 0x1,
+			Key::K2 => 0x2,
+			Key::K3 => 0x3,
+			Key::K4 => 0x4,
+			Key::K5 => 0x5,
+			Key::K6 => 0x6,
+			Key::K7 => 0x7,
+			Key::K8 => 0x8,
+			Key::K9 => 0x9,
+			Key::A => 0xA,
+			Key::B => 0xB,
+			Key::C => 0xC,
+			Key::D => 0xD,
+			Key::E => 0xE,
+			Key::F => 0xF
 		}
 	}
 }